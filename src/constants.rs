@@ -1,2 +1 @@
 pub const DEFAULT_TICK: u64 = 2000; //ms
-pub const NUMBER_OF_COLUMNS: u32 = 14;
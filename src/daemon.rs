@@ -0,0 +1,336 @@
+use crate::metrics::CPUTimeApp;
+use crate::remote::protocol::encode_snapshot;
+use crate::util::{config_dir, is_elmodeer_running, Event, Events};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::os::unix::io::AsRawFd;
+use std::os::unix::process::CommandExt;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+const LOG_NAME: &str = "el-modeer.log";
+// rotate well before the log directory becomes a problem on small disks
+const MAX_LOG_SIZE: u64 = 8 * 1024 * 1024;
+const SNAPSHOT_EVERY: u64 = 60;
+
+pub fn default_pidfile() -> PathBuf {
+    dirs::runtime_dir()
+        .unwrap_or_else(std::env::temp_dir)
+        .join("el-modeer.pid")
+}
+
+pub fn default_log_dir() -> PathBuf {
+    dirs::data_local_dir()
+        .unwrap_or_else(std::env::temp_dir)
+        .join("el-modeer")
+}
+
+/// Removes the pidfile when the daemon exits, however it exits.
+struct PidFile {
+    path: PathBuf,
+    /// Holds the lock for as long as the daemon runs.
+    _file: File,
+}
+
+impl PidFile {
+    /// Locks the pidfile before writing to it, so of two daemons starting at once only one
+    /// gets it.
+    fn create(path: &Path) -> Result<PidFile, String> {
+        let error = |e: io::Error| format!("Couldn't write pidfile {}: {}", path.display(), e);
+        let mut file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)
+            .map_err(error)?;
+        if unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX | libc::LOCK_NB) } != 0 {
+            return Err(format!(
+                "El-Modeer is already running (pidfile {}).",
+                path.display()
+            ));
+        }
+        // whatever is left there belongs to a process that is gone
+        file.set_len(0)
+            .and_then(|_| writeln!(file, "{}", std::process::id()))
+            .map_err(error)?;
+        Ok(PidFile {
+            path: path.to_path_buf(),
+            _file: file,
+        })
+    }
+}
+
+impl Drop for PidFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// Settings of `~/.config/el-modeer/daemon`, read at start and again on SIGHUP:
+///
+/// ```text
+/// snapshot-every 60   # refreshes between snapshots
+/// log-keep 5          # rotated logs to keep, overrides --log-keep
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct DaemonConfig {
+    snapshot_every: u64,
+    log_keep: usize,
+}
+
+impl DaemonConfig {
+    fn path() -> PathBuf {
+        config_dir().join("daemon")
+    }
+
+    /// `defaults` for what the file doesn't set, or all of them without a file.
+    fn load(defaults: DaemonConfig) -> Result<DaemonConfig, String> {
+        let path = DaemonConfig::path();
+        match fs::read_to_string(&path) {
+            Ok(text) => parse_config(&text, defaults).map_err(|e| format!("{}:{}", path.display(), e)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(defaults),
+            Err(e) => Err(format!("{}: {}", path.display(), e)),
+        }
+    }
+}
+
+/// `key value` lines, errors start with the line number.
+fn parse_config(text: &str, defaults: DaemonConfig) -> Result<DaemonConfig, String> {
+    let mut config = defaults;
+    for (n, line) in text.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or("").trim();
+        if line.is_empty() {
+            continue;
+        }
+        let error = |msg: String| format!("{}: {}", n + 1, msg);
+        let (key, value) = line
+            .split_once(char::is_whitespace)
+            .ok_or_else(|| error(format!("expected a key and a value, got '{}'", line)))?;
+        let value = value.trim();
+        match key {
+            "snapshot-every" => {
+                config.snapshot_every = value
+                    .parse()
+                    .ok()
+                    .filter(|every| *every > 0)
+                    .ok_or_else(|| error(format!("snapshot-every takes a positive number, got '{}'", value)))?
+            }
+            "log-keep" => {
+                config.log_keep = value
+                    .parse()
+                    .map_err(|_| error(format!("log-keep takes a number, got '{}'", value)))?
+            }
+            _ => return Err(error(format!("unknown setting '{}'", key))),
+        }
+    }
+    Ok(config)
+}
+
+/// Appends snapshots to `el-modeer.log`, keeping `keep` rotated logs as `el-modeer.log.N`.
+struct SnapshotLog {
+    dir: PathBuf,
+    keep: usize,
+    file: File,
+}
+
+impl SnapshotLog {
+    fn open(dir: &Path, keep: usize) -> Result<SnapshotLog, String> {
+        fs::create_dir_all(dir)
+            .map_err(|e| format!("Couldn't create log directory {}: {}", dir.display(), e))?;
+        Ok(SnapshotLog {
+            dir: dir.to_path_buf(),
+            keep,
+            file: SnapshotLog::open_file(dir)?,
+        })
+    }
+
+    fn open_file(dir: &Path) -> Result<File, String> {
+        let path = dir.join(LOG_NAME);
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .map_err(|e| format!("Couldn't open {}: {}", path.display(), e))
+    }
+
+    fn rotated(&self, n: usize) -> PathBuf {
+        self.dir.join(format!("{}.{}", LOG_NAME, n))
+    }
+
+    /// Shift every log one number up, dropping the oldest, and start a fresh one.
+    fn rotate(&mut self) -> Result<(), String> {
+        let _ = fs::remove_file(self.rotated(self.keep));
+        for n in (1..self.keep).rev() {
+            let _ = fs::rename(self.rotated(n), self.rotated(n + 1));
+        }
+        let current = self.dir.join(LOG_NAME);
+        if self.keep > 0 {
+            let _ = fs::rename(&current, self.rotated(1));
+        } else {
+            let _ = fs::remove_file(&current);
+        }
+        self.file = SnapshotLog::open_file(&self.dir)?;
+        Ok(())
+    }
+
+    /// A comment line, skipped by readers like the snapshot timestamps.
+    fn note(&mut self, text: &str) -> Result<(), String> {
+        writeln!(self.file, "# {}", text).map_err(|e| format!("Couldn't write to log: {}", e))
+    }
+
+    fn write(&mut self, app: &CPUTimeApp) -> Result<(), String> {
+        let mut text = format!("# {}\n", chrono::Local::now().to_rfc3339());
+        for record in encode_snapshot(app) {
+            text.push_str(&record);
+            text.push('\n');
+        }
+        text.push('\n');
+        self.file
+            .write_all(text.as_bytes())
+            .map_err(|e| format!("Couldn't write snapshot: {}", e))?;
+        let size = self.file.metadata().map(|m| m.len()).unwrap_or(0);
+        if size > MAX_LOG_SIZE {
+            self.rotate()?;
+        }
+        Ok(())
+    }
+}
+
+/// Set in the environment of the detached copy so it knows not to detach again.
+const DETACHED_ENV: &str = "EL_MODEER_DETACHED";
+
+pub fn is_detached() -> bool {
+    std::env::var_os(DETACHED_ENV).is_some()
+}
+
+/// Start a copy of ourselves with the same arguments in a new session with no terminal
+/// attached and return its PID. The copy runs the daemon, we are free to exit.
+pub async fn detach(pidfile: &Path) -> Result<u32, String> {
+    // checked here too so the error shows up on the terminal
+    if is_elmodeer_running(pidfile).await {
+        return Err(format!(
+            "El-Modeer is already running (pidfile {}).",
+            pidfile.display()
+        ));
+    }
+    let exe = std::env::current_exe().map_err(|e| format!("Couldn't find our executable: {}", e))?;
+    let mut command = Command::new(exe);
+    command
+        .args(std::env::args_os().skip(1))
+        .env(DETACHED_ENV, "1")
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+    unsafe {
+        command.pre_exec(|| {
+            libc::setsid();
+            Ok(())
+        });
+    }
+    let child = command
+        .spawn()
+        .map_err(|e| format!("Couldn't start the daemon: {}", e))?;
+    Ok(child.id())
+}
+
+/// Collect in the background, appending a snapshot to the log directory every
+/// `snapshot-every` ticks. SIGHUP reloads the config, SIGTERM/SIGINT stop the daemon.
+pub async fn run_daemon(
+    rate: u64,
+    sysfs_root: PathBuf,
+    pidfile: &Path,
+    log_dir: &Path,
+    keep: usize,
+) -> Result<(), String> {
+    let defaults = DaemonConfig {
+        snapshot_every: SNAPSHOT_EVERY,
+        log_keep: keep,
+    };
+    let mut config = DaemonConfig::load(defaults).map_err(|e| format!("Invalid daemon config {}", e))?;
+    let _pidfile = PidFile::create(pidfile)?;
+    let mut log = SnapshotLog::open(log_dir, config.log_keep)?;
+
+    let tick = Duration::from_millis(rate);
+    let mut app = CPUTimeApp::new(tick, sysfs_root, None);
+    app.init().await;
+    app.update(false).await;
+    log.write(&app)?;
+
+    let events = Events::headless(tick);
+    let mut ticks: u64 = 0;
+    loop {
+        match events.next().map_err(|e| e.to_string())? {
+            Event::Tick => {
+                app.update(false).await;
+                ticks += 1;
+                if ticks.is_multiple_of(config.snapshot_every) {
+                    log.write(&app)?;
+                }
+            }
+            // nobody sees our stderr, a config that doesn't load is noted in the log instead
+            Event::Reload => match DaemonConfig::load(defaults) {
+                Ok(reloaded) => {
+                    config = reloaded;
+                    log.keep = config.log_keep;
+                    log.note(&format!(
+                        "Reloaded config: snapshot every {} refreshes, keeping {} logs",
+                        config.snapshot_every, config.log_keep
+                    ))?;
+                }
+                Err(e) => log.note(&format!("Kept the old config, invalid daemon config {}", e))?,
+            },
+            Event::Terminate => break,
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DEFAULTS: DaemonConfig = DaemonConfig {
+        snapshot_every: 60,
+        log_keep: 5,
+    };
+
+    #[test]
+    fn config() {
+        assert_eq!(parse_config("", DEFAULTS), Ok(DEFAULTS));
+        assert_eq!(
+            parse_config("# every 10s at the default rate\nsnapshot-every 5\n\nlog-keep 0  # no rotated logs\n", DEFAULTS),
+            Ok(DaemonConfig {
+                snapshot_every: 5,
+                log_keep: 0
+            })
+        );
+        assert_eq!(
+            parse_config("log-keep 2\nsnapshot-every 0", DEFAULTS),
+            Err(String::from("2: snapshot-every takes a positive number, got '0'"))
+        );
+        assert_eq!(
+            parse_config("log-keep", DEFAULTS),
+            Err(String::from("1: expected a key and a value, got 'log-keep'"))
+        );
+        assert_eq!(
+            parse_config("rotate-size 8", DEFAULTS),
+            Err(String::from("1: unknown setting 'rotate-size'"))
+        );
+    }
+
+    #[test]
+    fn pidfile_is_locked() {
+        let path = std::env::temp_dir().join(format!("el-modeer-{}.pid", std::process::id()));
+        let pidfile = PidFile::create(&path).expect("pidfile");
+        assert_eq!(
+            fs::read_to_string(&path).ok(),
+            Some(format!("{}\n", std::process::id()))
+        );
+        assert!(PidFile::create(&path).is_err());
+        drop(pidfile);
+        assert!(!path.exists());
+        drop(PidFile::create(&path).expect("pidfile after the first went away"));
+    }
+}
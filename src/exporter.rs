@@ -0,0 +1,341 @@
+use crate::metrics::mprocess::{MProcess, ProcfsReads};
+use crate::metrics::CPUTimeApp;
+use signal_hook::consts::signal::{SIGINT, SIGTERM};
+use std::fmt::Write as FmtWrite;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+const CONTENT_TYPE: &str = "application/openmetrics-text; version=1.0.0; charset=utf-8";
+// label values are user controlled (process names), keep a runaway one from bloating the scrape
+const MAX_LABEL_LENGTH: usize = 64;
+// a client that connects and never finishes its request mustn't hold a thread forever
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Which processes get per-process series.
+pub struct ProcessSelection {
+    /// Most CPU hungry processes to export, also the cap when filtering by name.
+    pub top: usize,
+    /// Only export processes with one of these names.
+    pub names: Vec<String>,
+}
+
+impl ProcessSelection {
+    pub fn new(top: usize, names: Option<&str>) -> ProcessSelection {
+        let names = names
+            .map(|n| {
+                n.split(',')
+                    .map(|s| s.trim().to_string())
+                    .filter(|s| !s.is_empty())
+                    .collect()
+            })
+            .unwrap_or_default();
+        ProcessSelection { top, names }
+    }
+
+    /// The selected processes, highest CPU first, and how many matched but were left out.
+    fn select<'a>(&self, app: &'a CPUTimeApp) -> (Vec<&'a MProcess>, usize) {
+        let mut processes: Vec<&MProcess> = app
+            .process_map
+            .values()
+            .filter(|p| self.names.is_empty() || self.names.contains(&p.name))
+            .collect();
+        processes.sort_by(|a, b| b.cpu_usage.partial_cmp(&a.cpu_usage).unwrap_or(std::cmp::Ordering::Equal));
+        let dropped = processes.len().saturating_sub(self.top);
+        processes.truncate(self.top);
+        (processes, dropped)
+    }
+}
+
+fn label_value(value: &str) -> String {
+    value
+        .chars()
+        .take(MAX_LABEL_LENGTH)
+        .collect::<String>()
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+struct MetricsWriter {
+    out: String,
+}
+
+impl MetricsWriter {
+    fn family(&mut self, name: &str, kind: &str, unit: Option<&str>, help: &str) {
+        let _ = writeln!(self.out, "# TYPE {} {}", name, kind);
+        if let Some(unit) = unit {
+            let _ = writeln!(self.out, "# UNIT {} {}", name, unit);
+        }
+        let _ = writeln!(self.out, "# HELP {} {}", name, help);
+    }
+
+    fn sample<T: std::fmt::Display>(&mut self, name: &str, labels: &[(&str, &str)], value: T) {
+        self.out.push_str(name);
+        if !labels.is_empty() {
+            let labels: Vec<String> = labels
+                .iter()
+                .map(|(k, v)| format!("{}=\"{}\"", k, label_value(v)))
+                .collect();
+            let _ = write!(self.out, "{{{}}}", labels.join(","));
+        }
+        let _ = writeln!(self.out, " {}", value);
+    }
+
+    /// A family with one sample per process.
+    fn per_process<T: std::fmt::Display>(
+        &mut self,
+        family: (&str, &str, Option<&str>, &str),
+        sample_name: &str,
+        processes: &[&MProcess],
+        value: impl Fn(&MProcess) -> T,
+    ) {
+        let (name, kind, unit, help) = family;
+        self.family(name, kind, unit, help);
+        for p in processes {
+            let pid = p.pid.to_string();
+            self.sample(
+                sample_name,
+                &[("pid", &pid), ("name", &p.name), ("user", &p.user_name)],
+                value(p),
+            );
+        }
+    }
+}
+
+/// Render what the app collected in the OpenMetrics text format.
+pub fn render_metrics(app: &CPUTimeApp, selection: &ProcessSelection) -> String {
+    let mut w = MetricsWriter { out: String::new() };
+
+    w.family("elmodeer_cpu_usage_percent", "gauge", None, "CPU usage per core.");
+    for (cpu, usage) in &app.cpus {
+        w.sample("elmodeer_cpu_usage_percent", &[("cpu", cpu.as_str())], usage);
+    }
+    w.family("elmodeer_cpu_utilization_percent", "gauge", None, "CPU usage averaged over all cores.");
+    w.sample("elmodeer_cpu_utilization_percent", &[], app.cpu_utilization);
+
+    // sysinfo reports memory in KiB
+    w.family("elmodeer_memory_used_bytes", "gauge", Some("bytes"), "Used memory.");
+    w.sample("elmodeer_memory_used_bytes", &[], app.mem_utilization * 1024);
+    w.family("elmodeer_memory_total_bytes", "gauge", Some("bytes"), "Total memory.");
+    w.sample("elmodeer_memory_total_bytes", &[], app.mem_total * 1024);
+    w.family("elmodeer_swap_used_bytes", "gauge", Some("bytes"), "Used swap.");
+    w.sample("elmodeer_swap_used_bytes", &[], app.swap_utilization * 1024);
+    w.family("elmodeer_swap_total_bytes", "gauge", Some("bytes"), "Total swap.");
+    w.sample("elmodeer_swap_total_bytes", &[], app.swap_total * 1024);
+
+    w.family("elmodeer_tasks", "gauge", None, "Processes by state.");
+    for (state, count) in &[
+        ("running", app.running_processes),
+        ("sleeping", app.sleeping_processes),
+        ("stopped", app.stopped_processes),
+        ("zombie", app.zombie_processes),
+    ] {
+        w.sample("elmodeer_tasks", &[("state", *state)], count);
+    }
+    w.family("elmodeer_processes", "gauge", None, "Number of processes.");
+    w.sample("elmodeer_processes", &[], app.total_processes);
+    w.family("elmodeer_threads", "gauge", None, "Number of threads of all processes.");
+    w.sample("elmodeer_threads", &[], app.threads_total);
+
+    let (processes, dropped) = selection.select(app);
+    w.family(
+        "elmodeer_exporter_processes_dropped",
+        "gauge",
+        None,
+        "Processes matching the selection but left out by the series cap.",
+    );
+    w.sample("elmodeer_exporter_processes_dropped", &[], dropped);
+    w.per_process(
+        ("elmodeer_process_cpu_usage_percent", "gauge", None, "CPU usage of the process."),
+        "elmodeer_process_cpu_usage_percent",
+        &processes,
+        |p| p.cpu_usage,
+    );
+    w.per_process(
+        ("elmodeer_process_memory_bytes", "gauge", Some("bytes"), "Resident memory of the process."),
+        "elmodeer_process_memory_bytes",
+        &processes,
+        |p| p.memory * 1024,
+    );
+    w.per_process(
+        ("elmodeer_process_virtual_memory_bytes", "gauge", Some("bytes"), "Virtual memory of the process."),
+        "elmodeer_process_virtual_memory_bytes",
+        &processes,
+        |p| p.virtual_memory * 1024,
+    );
+    w.per_process(
+        ("elmodeer_process_threads", "gauge", None, "Threads of the process."),
+        "elmodeer_process_threads",
+        &processes,
+        |p| p.threads_total,
+    );
+    w.per_process(
+        ("elmodeer_process_read_bytes", "counter", Some("bytes"), "Bytes read by the process."),
+        "elmodeer_process_read_bytes_total",
+        &processes,
+        |p| p.read_bytes,
+    );
+    w.per_process(
+        ("elmodeer_process_written_bytes", "counter", Some("bytes"), "Bytes written by the process."),
+        "elmodeer_process_written_bytes_total",
+        &processes,
+        |p| p.write_bytes,
+    );
+
+    w.out.push_str("# EOF\n");
+    w.out
+}
+
+fn serve(stream: TcpStream, metrics: Arc<Mutex<String>>) -> std::io::Result<()> {
+    stream.set_read_timeout(Some(CLIENT_TIMEOUT))?;
+    stream.set_write_timeout(Some(CLIENT_TIMEOUT))?;
+    let mut writer = stream.try_clone()?;
+    let mut reader = BufReader::new(stream);
+    let mut request = String::new();
+    reader.read_line(&mut request)?;
+    // drain the headers, we don't need any of them
+    let mut header = String::new();
+    while reader.read_line(&mut header)? > 2 {
+        header.clear();
+    }
+
+    let mut parts = request.split_whitespace();
+    let (status, content_type, body) = match (parts.next(), parts.next()) {
+        (Some("GET"), Some("/metrics")) => (
+            "200 OK",
+            CONTENT_TYPE,
+            metrics.lock().expect("Metrics lock poisoned").clone(),
+        ),
+        (Some("GET"), _) => ("404 Not Found", "text/plain", String::from("Try /metrics\n")),
+        _ => ("405 Method Not Allowed", "text/plain", String::new()),
+    };
+    write!(
+        writer,
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        content_type,
+        body.len(),
+        body
+    )?;
+    writer.flush()
+}
+
+/// Collect without a terminal and serve `/metrics` on `address` until SIGINT or SIGTERM.
+pub async fn run_exporter(
+    address: &str,
+    rate: u64,
+    sysfs_root: PathBuf,
+    selection: ProcessSelection,
+) -> Result<(), String> {
+    let listener =
+        TcpListener::bind(address).map_err(|e| format!("Couldn't listen on {}: {}", address, e))?;
+
+    let terminate = Arc::new(AtomicBool::new(false));
+    for sig in &[SIGINT, SIGTERM] {
+        signal_hook::flag::register(*sig, Arc::clone(&terminate))
+            .map_err(|e| format!("Couldn't install signal handler: {}", e))?;
+    }
+
+    let tick = Duration::from_millis(rate);
+    let mut app = CPUTimeApp::new(tick, sysfs_root, None);
+    // none of the metrics come from the extra procfs reads
+    app.procfs_reads = ProcfsReads::default();
+    app.init().await;
+    app.update(false).await;
+    let metrics = Arc::new(Mutex::new(render_metrics(&app, &selection)));
+
+    {
+        let metrics = Arc::clone(&metrics);
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let metrics = Arc::clone(&metrics);
+                thread::spawn(move || {
+                    let _ = serve(stream, metrics);
+                });
+            }
+        });
+    }
+    println!("El-Modeer exporter serving http://{}/metrics", address);
+
+    while !terminate.load(Ordering::Relaxed) {
+        thread::sleep(tick);
+        app.update(false).await;
+        let text = render_metrics(&app, &selection);
+        *metrics.lock().expect("Metrics lock poisoned") = text;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn app(processes: Vec<MProcess>) -> CPUTimeApp {
+        let mut app = CPUTimeApp::new(Duration::from_secs(1), PathBuf::from("/sys"), None);
+        app.cpus = vec![(String::from("cpu0"), 12.5)];
+        app.process_map = processes.into_iter().map(|p| (p.pid, p)).collect();
+        app
+    }
+
+    fn process(pid: i32, name: &str, cpu_usage: f32) -> MProcess {
+        let mut p = MProcess::for_test(pid, name);
+        p.cpu_usage = cpu_usage;
+        p
+    }
+
+    /// The pids of the samples of `name`, in the order they were rendered.
+    fn pids(metrics: &str, name: &str) -> Vec<String> {
+        let prefix = format!("{}{{pid=\"", name);
+        metrics
+            .lines()
+            .filter_map(|l| l.strip_prefix(&prefix))
+            .map(|l| l[..l.find('"').unwrap_or(0)].to_string())
+            .collect()
+    }
+
+    #[test]
+    fn escapes_and_cuts_labels() {
+        let long = "x".repeat(MAX_LABEL_LENGTH + 20);
+        let metrics = render_metrics(
+            &app(vec![process(1, "a\"b\\c\nd", 2.0), process(2, &long, 1.0)]),
+            &ProcessSelection::new(10, None),
+        );
+        assert!(metrics.contains(r#"elmodeer_process_threads{pid="1",name="a\"b\\c\nd",user="alice"} 1"#));
+        let cut = format!(r#"elmodeer_process_threads{{pid="2",name="{}",user="alice"}} 1"#, "x".repeat(MAX_LABEL_LENGTH));
+        assert!(metrics.contains(&cut));
+        assert!(!metrics.contains(&"x".repeat(MAX_LABEL_LENGTH + 1)));
+    }
+
+    #[test]
+    fn caps_the_series() {
+        let processes = vec![
+            process(1, "postgres", 5.0),
+            process(2, "nginx", 50.0),
+            process(3, "postgres", 30.0),
+            process(4, "postgres", 10.0),
+            process(5, "bash", 1.0),
+        ];
+        let metrics = render_metrics(&app(processes.clone()), &ProcessSelection::new(2, None));
+        assert_eq!(pids(&metrics, "elmodeer_process_cpu_usage_percent"), vec!["2", "3"]);
+        assert_eq!(pids(&metrics, "elmodeer_process_written_bytes_total"), vec!["2", "3"]);
+        assert!(metrics.contains("\nelmodeer_exporter_processes_dropped 3\n"));
+
+        // the names narrow the processes down, the cap still holds
+        let metrics = render_metrics(&app(processes), &ProcessSelection::new(2, Some("postgres, bash")));
+        assert_eq!(pids(&metrics, "elmodeer_process_memory_bytes"), vec!["3", "4"]);
+        assert!(metrics.contains("\nelmodeer_exporter_processes_dropped 2\n"));
+    }
+
+    #[test]
+    fn ends_with_eof() {
+        let metrics = render_metrics(&app(vec![process(1, "init", 0.0)]), &ProcessSelection::new(10, None));
+        assert!(metrics.ends_with("\n# EOF\n"));
+        assert_eq!(metrics.matches("# EOF").count(), 1);
+        assert!(metrics.contains("\nelmodeer_cpu_usage_percent{cpu=\"cpu0\"} 12.5\n"));
+    }
+}
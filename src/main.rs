@@ -2,11 +2,21 @@
 extern crate num_derive;
 
 mod constants;
+mod daemon;
+mod exporter;
 mod metrics;
+mod remote;
 mod renderer;
 mod util;
 
+use crate::daemon::{default_log_dir, default_pidfile, detach, is_detached, run_daemon};
+use crate::exporter::{run_exporter, ProcessSelection};
+use crate::remote::agent::run_agent;
+use crate::remote::{default_token_file, read_token, RemoteClient};
+use crate::renderer::confirm::SignalGuard;
+use crate::renderer::layout::SectionLayout;
 use crate::renderer::section::{sum_section_heights, Section};
+use crate::renderer::theme::Theme;
 use crate::renderer::TerminalRenderer;
 use gumdrop::Options;
 use std::panic;
@@ -15,7 +25,9 @@ use std::error::Error;
 
 
 use crossterm::{
-    cursor, execute,
+    cursor,
+    event::{DisableBracketedPaste, DisableMouseCapture},
+    execute,
     terminal::{
         disable_raw_mode, enable_raw_mode, Clear, ClearType, EnterAlternateScreen,
         LeaveAlternateScreen,
@@ -23,7 +35,9 @@ use crossterm::{
 };
 use futures::executor::block_on;
 use std::io::stdout;
+use std::path::PathBuf;
 use std::process::{Command,exit};
+use std::time::Duration;
 use dirs;
 use execute::Execute;
 
@@ -55,6 +69,8 @@ fn restore_terminal() {
     // Restore cursor position and clear screen for TTYs
     execute!(sout, cursor::MoveTo(0, 0)).expect("Attempt to write to alternate screen failed.");
     execute!(sout, Clear(ClearType::All)).expect("Unable to clear screen.");
+    execute!(sout, DisableMouseCapture).expect("Unable to disable mouse capture.");
+    execute!(sout, DisableBracketedPaste).expect("Unable to disable bracketed paste.");
     execute!(sout, LeaveAlternateScreen).expect("Unable to leave alternate screen.");
     execute!(sout, cursor::Show).expect("Unable to restore cursor.");
     disable_raw_mode().expect("Unable to disable raw mode");
@@ -76,13 +92,13 @@ macro_rules! exit_with_message {
     };
 }
 
-fn create_geometry(
-    system_info_height: u16,
-    process_height: u16,
-) -> Vec<(Section, f64)> {
+fn create_geometry(opts: &MOptions) -> Vec<(Section, f64)> {
     let mut geometry: Vec<(Section, f64)> = Vec::new();
-    push_geometry!(geometry, Section::SystemInfo, system_info_height);
-    push_geometry!(geometry, Section::Process, process_height);
+    push_geometry!(geometry, Section::SystemInfo, opts.system_info_height);
+    push_geometry!(geometry, Section::Sensors, opts.sensors_height);
+    push_geometry!(geometry, Section::Events, opts.events_height);
+    push_geometry!(geometry, Section::Exits, opts.exits_height);
+    push_geometry!(geometry, Section::Process, opts.process_height);
 
     if geometry.is_empty() {
         exit_with_message!("All sections have size specified as zero!", 1);
@@ -110,9 +126,11 @@ fn create_geometry(
 }
 
 fn start_elmodeer(
-    rate: u64,
-    system_info_height: u16,
-    process_height: u16,
+    opts: MOptions,
+    remote: Option<RemoteClient>,
+    theme: Theme,
+    layout: Option<SectionLayout>,
+    signal_guard: SignalGuard,
 ) -> Result<(), Box<dyn Error>> {
 
     init_terminal();
@@ -126,19 +144,28 @@ fn start_elmodeer(
     // let main_pid = std::process::id();
 
     let run = || async {
-        let geometry: Vec<(Section, f64)> = create_geometry(
-            system_info_height,
-            process_height,
+        // without a layout, the sections are stacked by their percent heights
+        let layout = layout.unwrap_or_else(|| SectionLayout::stacked(&create_geometry(&opts)));
+        let mut r = TerminalRenderer::new(
+            opts.refresh_rate,
+            layout,
+            opts.sysfs_root,
+            remote,
+            Duration::from_secs(opts.dead_grace),
+            theme,
+            signal_guard,
         );
-        let mut r = TerminalRenderer::new(rate, &geometry);
 
-        r.start().await;
+        r.start(opts.proc_connector).await
     };
 
-    block_on(run());
+    let result = block_on(run());
 
     restore_terminal();
 
+    if let Err(e) = result {
+        eprintln!("{}", e);
+    }
     Ok(())
 }
 
@@ -194,11 +221,85 @@ Usage: {} [OPTIONS]
         return Ok(());
     }
 
-    start_elmodeer(
-        opts.refresh_rate,
-        opts.system_info_height, 
-        opts.process_height,
-    )
+    if opts.daemon {
+        let pidfile = opts.pidfile.unwrap_or_else(default_pidfile);
+        let result = if is_detached() {
+            let log_dir = opts.log_dir.unwrap_or_else(default_log_dir);
+            block_on(run_daemon(opts.refresh_rate, opts.sysfs_root, &pidfile, &log_dir, opts.log_keep))
+        } else {
+            block_on(detach(&pidfile)).map(|pid| println!("El-Modeer daemon started with PID {}.", pid))
+        };
+        if let Err(e) = result {
+            eprintln!("{}", e);
+            exit(1);
+        }
+        return Ok(());
+    }
+
+    if let Some(addr) = opts.agent {
+        let token_file = opts.token_file.clone().unwrap_or_else(default_token_file);
+        let result = block_on(run_agent(
+            &addr,
+            opts.refresh_rate,
+            opts.sysfs_root,
+            &token_file,
+            opts.agent_allow_control,
+        ));
+        if let Err(e) = result {
+            eprintln!("{}", e);
+            exit(1);
+        }
+        return Ok(());
+    }
+
+    if let Some(addr) = opts.exporter {
+        let selection = ProcessSelection::new(opts.exporter_top, opts.exporter_names.as_deref());
+        if let Err(e) = block_on(run_exporter(&addr, opts.refresh_rate, opts.sysfs_root, selection)) {
+            eprintln!("{}", e);
+            exit(1);
+        }
+        return Ok(());
+    }
+
+    // load the theme and connect before taking over the terminal so errors are readable
+    let theme = match Theme::load(opts.theme.as_deref()) {
+        Ok(theme) => theme,
+        Err(e) => {
+            eprintln!("{}", e);
+            exit(1);
+        }
+    };
+    let layout = match SectionLayout::load(opts.layout.as_deref()) {
+        Ok(layout) => layout,
+        Err(e) => {
+            eprintln!("{}", e);
+            exit(1);
+        }
+    };
+    let signal_guard = match SignalGuard::load(!opts.no_confirm, Duration::from_secs(opts.stop_timeout)) {
+        Ok(guard) => guard,
+        Err(e) => {
+            eprintln!("Invalid protected processes in {}", e);
+            exit(1);
+        }
+    };
+    let remote = match opts.connect.as_deref() {
+        Some(addr) => {
+            let token_file = opts.token_file.clone().unwrap_or_else(default_token_file);
+            let client = read_token(&token_file)
+                .and_then(|token| RemoteClient::connect(addr, &token).map_err(|e| e.to_string()));
+            match client {
+                Ok(client) => Some(client),
+                Err(e) => {
+                    eprintln!("Couldn't connect to agent at {}: {}", addr, e);
+                    exit(1);
+                }
+            }
+        }
+        None => None,
+    };
+
+    start_elmodeer(opts, remote, theme, layout, signal_guard)
 }
 
 #[derive(Options)]
@@ -216,10 +317,26 @@ struct MOptions {
     #[options(short = "s", long = "system-info-height", default = "7", meta = "INT")]
     system_info_height: u16,
 
+    /// Min Percent Height of Sensors.
+    #[options(no_short, long = "sensors-height", default = "0", meta = "INT")]
+    sensors_height: u16,
+
+    /// Min Percent Height of the process Events log.
+    #[options(no_short, long = "events-height", default = "0", meta = "INT")]
+    events_height: u16,
+
+    /// Min Percent Height of the Exited section.
+    #[options(no_short, long = "exits-height", default = "0", meta = "INT")]
+    exits_height: u16,
+
     /// Min Percent Height of Process Table.
     #[options(short = "p", long = "process-height", default = "32", meta = "INT")]
     process_height: u16,
 
+    /// Section layout, e.g. "30%: system_info 60%, sensors; process". Overrides the heights.
+    #[options(no_short, long = "layout", meta = "SPEC")]
+    layout: Option<String>,
+
     /// Refresh rate in milliseconds.
     #[options(
         short = "r",
@@ -230,6 +347,74 @@ struct MOptions {
     )]
     refresh_rate: u64,
 
+    /// Root of the sysfs tree to read hwmon and thermal sensors from.
+    #[options(no_short, long = "sysfs-root", default = "/sys", meta = "PATH")]
+    sysfs_root: PathBuf,
+
+    /// Colour theme: dark, light, high-contrast, monochrome or a user theme name/path.
+    #[options(no_short, long = "theme", meta = "NAME")]
+    theme: Option<String>,
+
+    /// Kill, terminate and suspend without asking first. Protected processes still ask.
+    #[options(no_short, long = "no-confirm")]
+    no_confirm: bool,
+
+    /// Seconds a stop waits after SIGTERM before sending SIGKILL.
+    #[options(no_short, long = "stop-timeout", default = "5", meta = "INT")]
+    stop_timeout: u64,
+
+    /// Seconds exited processes stay greyed out in the table, 0 removes them right away.
+    #[options(no_short, long = "dead-grace", default = "5", meta = "INT")]
+    dead_grace: u64,
+
+    /// Run headless, serving data to `--connect` clients on a Unix socket path or host:port.
+    #[options(no_short, long = "agent", meta = "ADDR")]
+    agent: Option<String>,
+
+    /// Let `--connect` clients signal and renice processes on the agent's host.
+    #[options(no_short, long = "agent-allow-control")]
+    agent_allow_control: bool,
+
+    /// Show the data of an agent listening on a Unix socket path or host:port.
+    #[options(no_short, long = "connect", meta = "ADDR")]
+    connect: Option<String>,
+
+    /// Token the agent checks and `--connect` sends, created by the agent if missing.
+    #[options(no_short, long = "token-file", meta = "PATH")]
+    token_file: Option<PathBuf>,
+
+    /// Run headless, serving OpenMetrics on http://ADDR/metrics for Prometheus to scrape.
+    #[options(no_short, long = "exporter", meta = "ADDR")]
+    exporter: Option<String>,
+
+    /// Max processes with per-process series in the exporter.
+    #[options(no_short, long = "exporter-top", default = "10", meta = "INT")]
+    exporter_top: usize,
+
+    /// Comma separated process names to export instead of the top CPU users.
+    #[options(no_short, long = "exporter-names", meta = "NAMES")]
+    exporter_names: Option<String>,
+
+    /// Record short-lived processes with the Linux process connector (needs CAP_NET_ADMIN).
+    #[options(no_short, long = "proc-connector")]
+    proc_connector: bool,
+
+    /// Run in the background, periodically writing snapshots to the log directory.
+    #[options(no_short, long = "daemon")]
+    daemon: bool,
+
+    /// Pidfile of the daemon. [default: $XDG_RUNTIME_DIR/el-modeer.pid]
+    #[options(no_short, long = "pidfile", meta = "PATH")]
+    pidfile: Option<PathBuf>,
+
+    /// Where the daemon writes snapshots. [default: ~/.local/share/el-modeer]
+    #[options(no_short, long = "log-dir", meta = "PATH")]
+    log_dir: Option<PathBuf>,
+
+    /// Number of rotated snapshot logs the daemon keeps.
+    #[options(no_short, long = "log-keep", default = "5", meta = "INT")]
+    log_keep: usize,
+
     /// Start GUI tree
     #[options(
         short = "t",
@@ -0,0 +1,264 @@
+use crate::metrics::mprocess::MProcess;
+use crate::metrics::ProcessTableSortOrder;
+use chrono::{DateTime, Local};
+use std::collections::{HashMap, HashSet};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DeltaKind {
+    /// Running at the baseline and still running.
+    Running,
+    Appeared,
+    Disappeared,
+}
+
+impl DeltaKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            DeltaKind::Running => "",
+            DeltaKind::Appeared => "new",
+            DeltaKind::Disappeared => "gone",
+        }
+    }
+}
+
+/// The columns of the delta view, in the order the sort keys cycle through them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DeltaSort {
+    CpuTime,
+    Memory,
+    ReadBytes,
+    WriteBytes,
+    Threads,
+}
+
+impl DeltaSort {
+    pub const ALL: [DeltaSort; 5] = [
+        DeltaSort::CpuTime,
+        DeltaSort::Memory,
+        DeltaSort::ReadBytes,
+        DeltaSort::WriteBytes,
+        DeltaSort::Threads,
+    ];
+
+    /// The next column, or the previous one going `backwards`.
+    pub fn cycle(self, backwards: bool) -> DeltaSort {
+        let i = DeltaSort::ALL.iter().position(|s| *s == self).unwrap_or(0);
+        let n = DeltaSort::ALL.len();
+        let next = if backwards { (i + n - 1) % n } else { (i + 1) % n };
+        DeltaSort::ALL[next]
+    }
+}
+
+/// What a process used and how it grew between the baseline and the latest update.
+#[derive(Clone, Copy, Debug)]
+pub struct Change {
+    /// Seconds of CPU time.
+    pub cpu_time: u64,
+    pub memory: i64,
+    pub read_bytes: u64,
+    pub write_bytes: u64,
+    /// The net change, threads that came and went in between don't count.
+    pub threads: i64,
+}
+
+impl Change {
+    /// Since `before`, or since the process started when it appeared after the baseline.
+    fn between(before: Option<&MProcess>, now: &MProcess) -> Change {
+        let (cpu_time, memory, read_bytes, write_bytes, threads) = match before {
+            Some(b) => (b.cpu_time, b.memory, b.read_bytes, b.write_bytes, b.threads_total),
+            None => (0, 0, 0, 0, 0),
+        };
+        Change {
+            cpu_time: now.cpu_time.saturating_sub(cpu_time),
+            memory: now.memory as i64 - memory as i64,
+            read_bytes: now.read_bytes.saturating_sub(read_bytes),
+            write_bytes: now.write_bytes.saturating_sub(write_bytes),
+            threads: now.threads_total as i64 - threads as i64,
+        }
+    }
+
+    fn value(&self, sort: DeltaSort) -> i64 {
+        match sort {
+            DeltaSort::CpuTime => self.cpu_time as i64,
+            DeltaSort::Memory => self.memory,
+            DeltaSort::ReadBytes => self.read_bytes as i64,
+            DeltaSort::WriteBytes => self.write_bytes as i64,
+            DeltaSort::Threads => self.threads,
+        }
+    }
+}
+
+pub struct ProcessDelta {
+    pub kind: DeltaKind,
+    pub pid: i32,
+    pub name: String,
+    pub user: String,
+    /// Unknown for a process gone from the updates, whatever it did after the baseline.
+    pub change: Option<Change>,
+}
+
+/// The processes as they were, to compare the updates against.
+pub struct Baseline {
+    pub taken: DateTime<Local>,
+    processes: HashMap<i32, MProcess>,
+    /// PID and start time of the exited processes still shown at the baseline, neither
+    /// compared nor new.
+    exited: HashSet<(i32, u64)>,
+}
+
+impl Baseline {
+    /// The running processes of the latest update, not the exited ones still shown.
+    pub fn capture(processes: &HashMap<i32, MProcess>) -> Baseline {
+        let (exited, running): (Vec<&MProcess>, Vec<&MProcess>) =
+            processes.values().partition(|p| p.end_time.is_some());
+        Baseline {
+            taken: Local::now(),
+            processes: running.into_iter().map(|p| (p.pid, p.clone())).collect(),
+            exited: exited.into_iter().map(|p| (p.pid, p.start_time)).collect(),
+        }
+    }
+
+    /// Every process of the baseline and of `processes`, the latest update. A PID now
+    /// belonging to a process started after the baseline is one that disappeared and one
+    /// that appeared.
+    pub fn deltas(&self, processes: &HashMap<i32, MProcess>) -> Vec<ProcessDelta> {
+        let mut deltas = vec![];
+        for (pid, before) in &self.processes {
+            let now = processes
+                .get(pid)
+                .filter(|p| p.start_time == before.start_time);
+            let kind = match now {
+                Some(p) if p.end_time.is_none() => DeltaKind::Running,
+                _ => DeltaKind::Disappeared,
+            };
+            deltas.push(ProcessDelta {
+                kind,
+                pid: *pid,
+                name: before.name.clone(),
+                user: before.user_name.clone(),
+                change: now.map(|p| Change::between(Some(before), p)),
+            });
+        }
+        for p in processes.values() {
+            let known = matches!(self.processes.get(&p.pid), Some(b) if b.start_time == p.start_time)
+                || self.exited.contains(&(p.pid, p.start_time));
+            if !known {
+                deltas.push(ProcessDelta {
+                    kind: DeltaKind::Appeared,
+                    pid: p.pid,
+                    name: p.name.clone(),
+                    user: p.user_name.clone(),
+                    change: Some(Change::between(None, p)),
+                });
+            }
+        }
+        deltas
+    }
+}
+
+/// Unknown changes go last whichever the order, ties by PID.
+pub fn sort_deltas(deltas: &mut [ProcessDelta], sort: DeltaSort, order: ProcessTableSortOrder) {
+    let value = |d: &ProcessDelta| d.change.map(|c| c.value(sort));
+    deltas.sort_by(|a, b| {
+        let ordering = match order {
+            ProcessTableSortOrder::Ascending => value(a).cmp(&value(b)),
+            ProcessTableSortOrder::Descending => value(b).cmp(&value(a)),
+        };
+        a.change
+            .is_none()
+            .cmp(&b.change.is_none())
+            .then(ordering)
+            .then(a.pid.cmp(&b.pid))
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A process with what the delta view compares.
+    fn process(pid: i32, start_time: u64, cpu_time: u64, memory: u64, threads: u64) -> MProcess {
+        let mut p = MProcess::for_test(pid, "cmd");
+        p.start_time = start_time;
+        p.cpu_time = cpu_time;
+        p.memory = memory;
+        p.threads_total = threads;
+        p
+    }
+
+    fn map(processes: Vec<MProcess>) -> HashMap<i32, MProcess> {
+        processes.into_iter().map(|p| (p.pid, p)).collect()
+    }
+
+    fn kinds(deltas: &[ProcessDelta]) -> Vec<(i32, DeltaKind)> {
+        let mut kinds: Vec<(i32, DeltaKind)> = deltas.iter().map(|d| (d.pid, d.kind)).collect();
+        kinds.sort_by_key(|(pid, kind)| (*pid, kind.as_str()));
+        kinds
+    }
+
+    #[test]
+    fn running_appeared_and_gone() {
+        let baseline = Baseline::capture(&map(vec![process(10, 100, 5, 1000, 4), process(11, 100, 1, 500, 1)]));
+        let deltas = baseline.deltas(&map(vec![process(10, 100, 8, 800, 6), process(12, 200, 2, 300, 2)]));
+        assert_eq!(
+            kinds(&deltas),
+            vec![(10, DeltaKind::Running), (11, DeltaKind::Disappeared), (12, DeltaKind::Appeared)]
+        );
+
+        let change = |pid| deltas.iter().find(|d| d.pid == pid).and_then(|d| d.change);
+        let running = change(10).expect("a change");
+        assert_eq!((running.cpu_time, running.memory, running.threads), (3, -200, 2));
+        assert!(change(11).is_none());
+        // a new process counts from its start
+        let appeared = change(12).expect("a change");
+        assert_eq!((appeared.cpu_time, appeared.memory, appeared.threads), (2, 300, 2));
+    }
+
+    #[test]
+    fn reused_pid() {
+        let baseline = Baseline::capture(&map(vec![process(10, 100, 5, 1000, 4)]));
+        let deltas = baseline.deltas(&map(vec![process(10, 300, 1, 200, 1)]));
+        assert_eq!(kinds(&deltas), vec![(10, DeltaKind::Disappeared), (10, DeltaKind::Appeared)]);
+        let gone = deltas.iter().find(|d| d.kind == DeltaKind::Disappeared).expect("the old process");
+        assert!(gone.change.is_none());
+    }
+
+    #[test]
+    fn exited_at_the_baseline() {
+        let mut dead = process(11, 100, 1, 500, 1);
+        dead.end_time = Some(150);
+        let baseline = Baseline::capture(&map(vec![process(10, 100, 5, 1000, 4), dead.clone()]));
+        // still shown during the grace period, neither new nor gone
+        let deltas = baseline.deltas(&map(vec![process(10, 100, 5, 1000, 4), dead]));
+        assert_eq!(kinds(&deltas), vec![(10, DeltaKind::Running)]);
+        // a process exiting after the baseline is gone, with what it did until then
+        let mut exiting = process(10, 100, 7, 1000, 4);
+        exiting.end_time = Some(200);
+        let deltas = baseline.deltas(&map(vec![exiting]));
+        assert_eq!(kinds(&deltas), vec![(10, DeltaKind::Disappeared)]);
+        assert_eq!(deltas[0].change.map(|c| c.cpu_time), Some(2));
+    }
+
+    #[test]
+    fn unknown_sorts_last() {
+        let baseline = Baseline::capture(&map(vec![
+            process(10, 100, 5, 1000, 4),
+            process(11, 100, 1, 500, 1),
+            process(12, 100, 1, 500, 1),
+        ]));
+        let now = map(vec![
+            process(10, 100, 6, 1000, 4),
+            process(12, 100, 4, 500, 1),
+            process(13, 200, 1, 100, 1),
+            process(14, 200, 1, 100, 1),
+        ]);
+        let order = |sort, order| {
+            let mut deltas = baseline.deltas(&now);
+            sort_deltas(&mut deltas, sort, order);
+            deltas.iter().map(|d| d.pid).collect::<Vec<i32>>()
+        };
+        assert_eq!(order(DeltaSort::CpuTime, ProcessTableSortOrder::Descending), vec![12, 10, 13, 14, 11]);
+        assert_eq!(order(DeltaSort::CpuTime, ProcessTableSortOrder::Ascending), vec![10, 13, 14, 12, 11]);
+        assert_eq!(order(DeltaSort::Memory, ProcessTableSortOrder::Ascending), vec![10, 12, 13, 14, 11]);
+    }
+}
@@ -0,0 +1,171 @@
+use crate::metrics::mprocess::parse_cpu_list;
+use std::os::unix::process::CommandExt;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::thread;
+
+/// What the run prompt asked for. The input looks like a shell command line with optional
+/// `nice=N`, `cpus=LIST`, `cwd=DIR` and `VAR=value` assignments in front of the command, e.g.
+/// `nice=10 cpus=1-2 cwd=~/src RUST_LOG=debug cargo build`.
+#[derive(Debug, Default)]
+pub struct LaunchSpec {
+    pub command: Vec<String>,
+    pub nice: Option<i32>,
+    pub cpus: Option<Vec<usize>>,
+    pub cwd: Option<PathBuf>,
+    pub env: Vec<(String, String)>,
+}
+
+/// Split a command line into words, honouring single/double quotes and backslash escapes.
+fn split_words(input: &str) -> Result<Vec<String>, String> {
+    let mut words = vec![];
+    let mut word = String::new();
+    let mut in_word = false;
+    let mut quote: Option<char> = None;
+    let mut chars = input.chars();
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some('"'), '\\') | (None, '\\') => match chars.next() {
+                Some(n) => {
+                    word.push(n);
+                    in_word = true;
+                }
+                None => return Err(String::from("Trailing backslash")),
+            },
+            (Some(_), c) => word.push(c),
+            (None, '\'') | (None, '"') => {
+                quote = Some(c);
+                in_word = true;
+            }
+            (None, c) if c.is_whitespace() => {
+                if in_word {
+                    words.push(std::mem::take(&mut word));
+                    in_word = false;
+                }
+            }
+            (None, c) => {
+                word.push(c);
+                in_word = true;
+            }
+        }
+    }
+    if quote.is_some() {
+        return Err(String::from("Unterminated quote"));
+    }
+    if in_word {
+        words.push(word);
+    }
+    Ok(words)
+}
+
+fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix('~'), dirs::home_dir()) {
+        (Some(rest), Some(home)) if rest.is_empty() || rest.starts_with('/') => {
+            home.join(rest.trim_start_matches('/'))
+        }
+        _ => PathBuf::from(path),
+    }
+}
+
+fn is_env_name(name: &str) -> bool {
+    !name.is_empty()
+        && !name.starts_with(|c: char| c.is_ascii_digit())
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+impl LaunchSpec {
+    pub fn parse(input: &str) -> Result<LaunchSpec, String> {
+        let mut spec = LaunchSpec::default();
+        let mut words = split_words(input)?.into_iter().peekable();
+        while let Some(word) = words.peek() {
+            let (key, value) = match word.split_once('=') {
+                Some((k, v)) if is_env_name(k) => (k.to_string(), v.to_string()),
+                _ => break,
+            };
+            match key.as_str() {
+                "nice" => {
+                    let nice = value
+                        .parse::<i32>()
+                        .ok()
+                        .filter(|n| (-20..=19).contains(n))
+                        .ok_or_else(|| String::from("nice must be between -20 and 19"))?;
+                    spec.nice = Some(nice);
+                }
+                "cpus" => {
+                    spec.cpus = Some(
+                        parse_cpu_list(&value).ok_or_else(|| format!("Invalid CPU list {}", value))?,
+                    );
+                }
+                "cwd" => spec.cwd = Some(expand_home(&value)),
+                _ => spec.env.push((key, value)),
+            }
+            words.next();
+        }
+        spec.command = words.collect();
+        if spec.command.is_empty() {
+            return Err(String::from("No command given"));
+        }
+        Ok(spec)
+    }
+
+    /// Start the command in its own session with no terminal attached, so it can't draw over
+    /// the TUI and keeps running after we quit. Returns the PID of the new process.
+    pub fn spawn(&self) -> Result<i32, String> {
+        let mut command = Command::new(&self.command[0]);
+        command
+            .args(&self.command[1..])
+            .envs(self.env.iter().map(|(k, v)| (k, v)))
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null());
+        if let Some(cwd) = &self.cwd {
+            if !cwd.is_dir() {
+                return Err(format!("{} is not a directory", cwd.display()));
+            }
+            command.current_dir(cwd);
+        }
+
+        let nice = self.nice;
+        #[cfg(target_os = "linux")]
+        let cpu_set = self.cpus.as_ref().map(|cpus| unsafe {
+            let mut set: libc::cpu_set_t = std::mem::zeroed();
+            for cpu in cpus {
+                libc::CPU_SET(*cpu, &mut set);
+            }
+            set
+        });
+        // runs in the child between fork and exec, only async-signal-safe calls in here
+        unsafe {
+            command.pre_exec(move || {
+                libc::setsid();
+                if let Some(n) = nice {
+                    if libc::setpriority(libc::PRIO_PROCESS, 0, n) < 0 {
+                        return Err(std::io::Error::last_os_error());
+                    }
+                }
+                #[cfg(target_os = "linux")]
+                {
+                    if let Some(set) = cpu_set.as_ref() {
+                        if libc::sched_setaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), set)
+                            < 0
+                        {
+                            return Err(std::io::Error::last_os_error());
+                        }
+                    }
+                }
+                Ok(())
+            });
+        }
+
+        let mut child = command
+            .spawn()
+            .map_err(|e| format!("Couldn't run {}: {}", self.command[0], e))?;
+        let pid = child.id() as i32;
+        // we are the parent, reap the child when it exits so it doesn't linger as a zombie
+        thread::spawn(move || {
+            let _ = child.wait();
+        });
+        Ok(pid)
+    }
+}
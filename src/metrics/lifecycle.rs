@@ -0,0 +1,92 @@
+use crate::metrics::mprocess::MProcess;
+use chrono::Duration as CDuration;
+use chrono::{DateTime, Local};
+use std::collections::VecDeque;
+
+const EVENT_LOG_CAPACITY: usize = 1000;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LifecycleKind {
+    Started,
+    Exited,
+    /// A new process got the PID of one that exited since the last refresh.
+    PidReused,
+}
+
+impl LifecycleKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            LifecycleKind::Started => "started",
+            LifecycleKind::Exited => "exited",
+            LifecycleKind::PidReused => "pid reused",
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct LifecycleEvent {
+    pub time: DateTime<Local>,
+    pub kind: LifecycleKind,
+    pub pid: i32,
+    pub name: String,
+    pub user: String,
+    /// Only known for exited processes.
+    pub runtime: Option<CDuration>,
+    pub peak_memory: Option<u64>,
+}
+
+impl LifecycleEvent {
+    fn new(kind: LifecycleKind, p: &MProcess) -> LifecycleEvent {
+        let exited = kind == LifecycleKind::Exited;
+        LifecycleEvent {
+            time: Local::now(),
+            kind,
+            pid: p.pid,
+            name: p.name.clone(),
+            user: p.user_name.clone(),
+            runtime: if exited { Some(p.get_run_duration()) } else { None },
+            peak_memory: if exited { Some(p.peak_memory) } else { None },
+        }
+    }
+
+    /// Case insensitive match of the filter against kind, PID, name and user.
+    pub fn matches(&self, filter: &str) -> bool {
+        let filter = filter.to_lowercase();
+        self.kind.as_str().contains(&filter)
+            || self.pid.to_string().contains(&filter)
+            || self.name.to_lowercase().contains(&filter)
+            || self.user.to_lowercase().contains(&filter)
+    }
+}
+
+/// The most recent process lifecycle events, oldest first.
+#[derive(Default)]
+pub struct ProcessEventLog {
+    pub events: VecDeque<LifecycleEvent>,
+}
+
+impl ProcessEventLog {
+    fn push(&mut self, kind: LifecycleKind, p: &MProcess) {
+        if self.events.len() == EVENT_LOG_CAPACITY {
+            self.events.pop_front();
+        }
+        self.events.push_back(LifecycleEvent::new(kind, p));
+    }
+
+    pub fn started(&mut self, p: &MProcess) {
+        self.push(LifecycleKind::Started, p);
+    }
+
+    pub fn exited(&mut self, p: &MProcess) {
+        self.push(LifecycleKind::Exited, p);
+    }
+
+    /// `old` is gone and `new` took over its PID.
+    pub fn reused(&mut self, old: &MProcess, new: &MProcess) {
+        // one kept in the table after exiting was logged when it exited
+        if old.end_time.is_none() {
+            self.push(LifecycleKind::Exited, old);
+        }
+        self.push(LifecycleKind::PidReused, new);
+    }
+}
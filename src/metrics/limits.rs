@@ -0,0 +1,163 @@
+use std::fs;
+
+/// One row of /proc/<pid>/limits. `None` soft/hard values mean unlimited.
+#[derive(Clone, Debug)]
+pub struct ResourceLimit {
+    pub name: String,
+    pub resource: Option<i32>,
+    pub soft: Option<u64>,
+    pub hard: Option<u64>,
+    pub units: String,
+}
+
+/// Map the names used by /proc/<pid>/limits to the resources prlimit understands.
+fn resource_for_name(name: &str) -> Option<i32> {
+    let r = match name {
+        "Max cpu time" => libc::RLIMIT_CPU,
+        "Max file size" => libc::RLIMIT_FSIZE,
+        "Max data size" => libc::RLIMIT_DATA,
+        "Max stack size" => libc::RLIMIT_STACK,
+        "Max core file size" => libc::RLIMIT_CORE,
+        "Max resident set" => libc::RLIMIT_RSS,
+        "Max processes" => libc::RLIMIT_NPROC,
+        "Max open files" => libc::RLIMIT_NOFILE,
+        "Max locked memory" => libc::RLIMIT_MEMLOCK,
+        "Max address space" => libc::RLIMIT_AS,
+        #[cfg(target_os = "linux")]
+        "Max file locks" => libc::RLIMIT_LOCKS,
+        #[cfg(target_os = "linux")]
+        "Max pending signals" => libc::RLIMIT_SIGPENDING,
+        #[cfg(target_os = "linux")]
+        "Max msgqueue size" => libc::RLIMIT_MSGQUEUE,
+        #[cfg(target_os = "linux")]
+        "Max nice priority" => libc::RLIMIT_NICE,
+        #[cfg(target_os = "linux")]
+        "Max realtime priority" => libc::RLIMIT_RTPRIO,
+        #[cfg(target_os = "linux")]
+        "Max realtime timeout" => libc::RLIMIT_RTTIME,
+        _ => return None,
+    };
+    Some(r as i32)
+}
+
+/// Parse a limit value, "unlimited" (or "-1"/"inf" when typed by the user) is `Some(None)`.
+pub fn parse_limit_value(value: &str) -> Option<Option<u64>> {
+    match value {
+        "unlimited" | "inf" | "infinity" | "-1" => Some(None),
+        v => v.parse::<u64>().ok().map(Some),
+    }
+}
+
+pub fn format_limit_value(value: Option<u64>) -> String {
+    match value {
+        Some(v) => format!("{}", v),
+        None => String::from("unlimited"),
+    }
+}
+
+pub fn read_limits(pid: i32) -> Option<Vec<ResourceLimit>> {
+    let data = fs::read_to_string(format!("/proc/{}/limits", pid)).ok()?;
+    Some(parse_limits(&data))
+}
+
+fn parse_limits(data: &str) -> Vec<ResourceLimit> {
+    let mut limits = vec![];
+    // the file is a fixed width table: name (26), soft (21), hard (21), units
+    for line in data.lines().skip(1) {
+        if line.len() < 47 || !line.is_char_boundary(25) || !line.is_char_boundary(46) {
+            continue;
+        }
+        let name = line[..25].trim().to_string();
+        let mut rest = line[25..].split_whitespace();
+        let soft = match rest.next().and_then(parse_limit_value) {
+            Some(v) => v,
+            None => continue,
+        };
+        let hard = match rest.next().and_then(parse_limit_value) {
+            Some(v) => v,
+            None => continue,
+        };
+        let units = rest.next().unwrap_or("").to_string();
+        limits.push(ResourceLimit {
+            resource: resource_for_name(&name),
+            name,
+            soft,
+            hard,
+            units,
+        });
+    }
+    limits
+}
+
+pub fn count_open_fds(pid: i32) -> Option<usize> {
+    fs::read_dir(format!("/proc/{}/fd", pid))
+        .ok()
+        .map(|rd| rd.count())
+}
+
+#[cfg(target_os = "linux")]
+pub fn set_limit(pid: i32, resource: i32, soft: Option<u64>, hard: Option<u64>) -> Result<(), i32> {
+    let new = libc::rlimit {
+        rlim_cur: soft.unwrap_or(libc::RLIM_INFINITY),
+        rlim_max: hard.unwrap_or(libc::RLIM_INFINITY),
+    };
+    if unsafe { libc::prlimit(pid, resource as _, &new, std::ptr::null_mut()) } < 0 {
+        return Err(std::io::Error::last_os_error().raw_os_error().unwrap_or(0));
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn set_limit(_pid: i32, _resource: i32, _soft: Option<u64>, _hard: Option<u64>) -> Result<(), i32> {
+    Err(libc::ENOSYS)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LIMITS: &str = "\
+Limit                     Soft Limit           Hard Limit           Units     
+Max cpu time              unlimited            unlimited            seconds   
+Max stack size            8388608              unlimited            bytes     
+Max processes             63356                63356                processes 
+Max open files            1024                 524288               files     
+Max nice priority         0                    0                    
+Max frobs                 1                    2                    frobs     
+";
+
+    #[test]
+    fn parses_proc_limits() {
+        let limits = parse_limits(LIMITS);
+        assert_eq!(limits.len(), 6);
+        let cpu = &limits[0];
+        assert_eq!(cpu.name, "Max cpu time");
+        assert_eq!(cpu.resource, Some(libc::RLIMIT_CPU as i32));
+        assert_eq!((cpu.soft, cpu.hard), (None, None));
+        assert_eq!(cpu.units, "seconds");
+        let files = &limits[3];
+        assert_eq!(files.resource, Some(libc::RLIMIT_NOFILE as i32));
+        assert_eq!((files.soft, files.hard), (Some(1024), Some(524288)));
+        // no units column
+        assert_eq!(limits[4].units, "");
+        // shown, but prlimit doesn't know it
+        assert_eq!(limits[5].resource, None);
+    }
+
+    #[test]
+    fn skips_short_and_garbled_lines() {
+        let limits = parse_limits("header\nMax open files\nMax open files            many                 1024                 files\n");
+        assert!(limits.is_empty());
+    }
+
+    #[test]
+    fn limit_values() {
+        assert_eq!(parse_limit_value("unlimited"), Some(None));
+        assert_eq!(parse_limit_value("-1"), Some(None));
+        assert_eq!(parse_limit_value("inf"), Some(None));
+        assert_eq!(parse_limit_value("4096"), Some(Some(4096)));
+        assert_eq!(parse_limit_value("4k"), None);
+        assert_eq!(format_limit_value(None), "unlimited");
+        assert_eq!(format_limit_value(Some(4096)), "4096");
+    }
+}
@@ -1,6 +1,17 @@
+pub mod baseline;
+pub mod launch;
+pub mod lifecycle;
+pub mod limits;
 pub mod mprocess;
-
-use crate::metrics::mprocess::MProcess;
+pub mod proc_connector;
+pub mod sensors;
+
+use crate::metrics::lifecycle::ProcessEventLog;
+use crate::metrics::mprocess::{renice, send_signal, MProcess, ProcessSignal, ProcfsReads};
+use crate::metrics::proc_connector::ProcConnector;
+use crate::metrics::sensors::Sensors;
+use crate::remote::protocol::apply_snapshot;
+use crate::remote::RemoteClient;
 use crate::renderer::column::Column;
 use crate::util::percent_of;
 
@@ -8,17 +19,17 @@ use heim::host;
 use heim::units::frequency::megahertz;
 use heim::units::time;
 use std::collections::{HashMap, HashSet};
-use std::time::{Duration, SystemTime};
+use std::time::{Duration, Instant, SystemTime};
 use chrono::prelude::DateTime;
 use std::time::{UNIX_EPOCH};
 use chrono::Local;
 
 use std::fs;
-use std::path::{Path};
+use std::path::{Path, PathBuf};
 use sysinfo::{Disk, DiskExt, ProcessExt, ProcessStatus, ProcessorExt, System, SystemExt};
 use users::{Users, UsersCache};
 
-#[derive(PartialEq, Eq)]
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
 pub enum ProcessTableSortOrder {
     Ascending = 0,
     Descending = 1,
@@ -117,11 +128,30 @@ pub struct CPUTimeApp {
     pub selected_process: Option<Box<MProcess>>,
     pub max_pid_len: usize,
     pub uptime: Duration,
-    pub tick: Duration
+    pub tick: Duration,
+    pub sensors: Sensors,
+    pub lifecycle: ProcessEventLog,
+    /// How long exited processes stay in the table.
+    pub dead_grace: Duration,
+    pub proc_connector: Option<ProcConnector>,
+    /// Why the process connector isn't running when it was asked for.
+    pub proc_connector_error: Option<String>,
+    /// What the columns shown need from procfs, besides the sort column. Headless modes
+    /// read everything unless they say otherwise.
+    pub procfs_reads: ProcfsReads,
+    /// Set when the data comes from an agent instead of this machine.
+    pub remote: Option<RemoteClient>,
+    pub remote_error: Option<String>,
+    /// When to try the agent again after losing it, and how long the wait after that is.
+    reconnect_at: Option<(Instant, Duration)>,
 }
 
+/// First wait before reconnecting to an agent, doubled after each failed attempt.
+const RECONNECT_DELAY: Duration = Duration::from_secs(1);
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(30);
+
 impl CPUTimeApp {
-    pub fn new(tick: Duration) -> CPUTimeApp { 
+    pub fn new(tick: Duration, sysfs_root: PathBuf, remote: Option<RemoteClient>) -> CPUTimeApp {
         let mut s = CPUTimeApp {
             cpus: vec![],
             system: System::new_all(),
@@ -160,6 +190,15 @@ impl CPUTimeApp {
             top_pids: Top::default(),
             uptime: Duration::from_secs(0),
             tick: tick,
+            sensors: Sensors::new(sysfs_root),
+            lifecycle: ProcessEventLog::default(),
+            dead_grace: Duration::from_secs(0),
+            proc_connector: None,
+            proc_connector_error: None,
+            procfs_reads: ProcfsReads::ALL,
+            remote,
+            remote_error: None,
+            reconnect_at: None,
         };
         s.system.refresh_all();
         s.system.refresh_all(); // apparently multiple refreshes are necessary to fill in all values.
@@ -191,12 +230,18 @@ impl CPUTimeApp {
         }
     }
 
-    pub fn select_process(&mut self, highlighted_process: Option<Box<MProcess>>) {
+    pub fn select_process(&mut self, mut highlighted_process: Option<Box<MProcess>>) {
+        if let Some(p) = highlighted_process.as_mut() {
+            if self.remote.is_none() {
+                p.refresh_details();
+            }
+        }
         self.selected_process = highlighted_process;
     }
 
     fn update_process_list(&mut self, keep_order: bool) {
         let process_list = self.system.get_processes();
+        let reads = self.procfs_reads.union(self.psortby.procfs_reads());
         #[cfg(target_os = "linux")]
       //  let client = &self.netlink_client;
         let mut current_pids: HashSet<i32> = HashSet::with_capacity(process_list.len());
@@ -207,6 +252,8 @@ impl CPUTimeApp {
             None => 0.0,
         };
 
+        // everything is new on the first refresh, that's not worth logging
+        let first_update = self.process_map.is_empty();
         self.threads_total = 0;
         self.total_processes = process_list.len();
         self.running_processes = 0;
@@ -226,7 +273,10 @@ impl CPUTimeApp {
                 if zp.start_time == process.start_time() {
                     let disk_usage = process.disk_usage();
                     // check for PID reuse
+                    zp.prev_memory = zp.memory;
+                    zp.prev_cpu_usage = zp.cpu_usage;
                     zp.memory = process.memory();
+                    zp.peak_memory = zp.peak_memory.max(zp.memory);
                     zp.cpu_usage = process.cpu_usage();
                     zp.cum_cpu_usage += zp.cpu_usage as f64;
                     zp.status = process.status();
@@ -240,6 +290,7 @@ impl CPUTimeApp {
                     zp.read_bytes = disk_usage.total_read_bytes;
                     zp.write_bytes = disk_usage.total_written_bytes;
                     zp.last_updated = SystemTime::now();
+                    zp.update_from_procfs(reads);
 
                     zp.et = match zp.end_time {
                         Some(t) => DateTime::<Local>::from(UNIX_EPOCH + Duration::from_secs(t)),
@@ -252,7 +303,8 @@ impl CPUTimeApp {
                         .get_user_by_uid(process.uid)
                         .map(|user| user.name().to_string_lossy().to_string())
                         .unwrap_or(format!("{:}", process.uid));
-                    let mprocess = MProcess::from_user_and_process(user_name, process);
+                    let mprocess = MProcess::from_user_and_process(user_name, process, reads);
+                    self.lifecycle.reused(zp, &mprocess);
                     self.threads_total += mprocess.threads_total as usize;
                     self.process_map.insert(mprocess.pid, mprocess);
                 }
@@ -263,22 +315,30 @@ impl CPUTimeApp {
                     .map(|user| user.name().to_string_lossy().to_string())
                     .unwrap_or(format!("{:}", process.uid));
                 #[allow(unused_mut)]
-                let mut mprocess = MProcess::from_user_and_process(user_name, process);
-                
+                let mut mprocess = MProcess::from_user_and_process(user_name, process, reads);
+                if !first_update {
+                    self.lifecycle.started(&mprocess);
+                }
                 self.threads_total += mprocess.threads_total as usize;
                 self.process_map.insert(mprocess.pid, mprocess);
             }
             current_pids.insert(*pid);
         }
 
-        if keep_order {
-            self.processes.retain(|pid| current_pids.contains(pid));
-        } else {
-            self.processes = current_pids.iter().cloned().collect();
-        }
-
-        // remove pids that are gone
-        self.process_map.retain(|&k, _| current_pids.contains(&k));
+        // remove pids that are gone, once they have been shown as exited for the grace period
+        let lifecycle = &mut self.lifecycle;
+        let grace = self.dead_grace;
+        self.process_map.retain(|k, p| {
+            if current_pids.contains(k) {
+                return true;
+            }
+            if p.end_time.is_none() {
+                lifecycle.exited(p);
+                p.set_end_time();
+            }
+            p.in_dead_grace(grace)
+        });
+        current_pids.extend(self.process_map.keys());
 
         //set top cumulative process if we've changed it.
         if let Some(p) = top.cum_cpu.pid {
@@ -300,11 +360,26 @@ impl CPUTimeApp {
 
         self.top_pids = top;
 
+        self.update_process_order(&current_pids, keep_order);
+    }
+
+    /// Sync the table order and the selected process with a freshly collected `process_map`.
+    fn update_process_order(&mut self, current_pids: &HashSet<i32>, keep_order: bool) {
+        if keep_order {
+            self.processes.retain(|pid| current_pids.contains(pid));
+        } else {
+            self.processes = current_pids.iter().cloned().collect();
+        }
+
         // update selected process
         if let Some(p) = self.selected_process.as_mut() {
             let pid = &p.pid;
             if let Some(proc) = self.process_map.get(pid) {
-                self.selected_process = Some(Box::new(proc.clone()));
+                let mut proc = Box::new(proc.clone());
+                if self.remote.is_none() && proc.end_time.is_none() {
+                    proc.refresh_details();
+                }
+                self.selected_process = Some(proc);
             } else {
                 p.set_end_time();
             }
@@ -315,6 +390,119 @@ impl CPUTimeApp {
         }
     }
 
+    fn update_remote(&mut self, keep_order: bool) {
+        let records = match self.remote.as_mut().map(|r| r.snapshot()) {
+            Some(Ok(records)) => records,
+            Some(Err(e)) => {
+                self.remote_error = Some(e.to_string());
+                self.reconnect();
+                return;
+            }
+            None => return,
+        };
+        self.remote_error = None;
+        self.reconnect_at = None;
+        let previous = std::mem::take(&mut self.process_map);
+        apply_snapshot(self, &records);
+        self.log_lifecycle(previous);
+        let current_pids: HashSet<i32> = self.process_map.keys().cloned().collect();
+        self.update_process_order(&current_pids, keep_order);
+    }
+
+    /// The agent may have restarted. Connecting blocks the update, so while it stays unreachable
+    /// the attempts get further apart.
+    fn reconnect(&mut self) {
+        let (at, delay) = self.reconnect_at.unwrap_or((Instant::now(), RECONNECT_DELAY));
+        if Instant::now() < at {
+            return;
+        }
+        match self.remote.as_ref().map(|r| r.reconnect()) {
+            Some(Ok(client)) => {
+                self.remote = Some(client);
+                self.reconnect_at = None;
+            }
+            _ => self.reconnect_at = Some((Instant::now() + delay, (delay * 2).min(MAX_RECONNECT_DELAY))),
+        }
+    }
+
+    /// Compare a fresh `process_map` with the previous one to log what started and exited.
+    /// Exited processes are carried over for the grace period.
+    fn log_lifecycle(&mut self, previous: HashMap<i32, MProcess>) {
+        for (pid, p) in self.process_map.iter_mut() {
+            match previous.get(pid) {
+                Some(old) if old.start_time != p.start_time => self.lifecycle.reused(old, p),
+                Some(old) => {
+                    p.peak_memory = p.peak_memory.max(old.peak_memory);
+                    p.prev_memory = old.memory;
+                    p.prev_cpu_usage = old.cpu_usage;
+                }
+                None if !previous.is_empty() => self.lifecycle.started(p),
+                None => {}
+            }
+        }
+        for (pid, mut old) in previous {
+            if self.process_map.contains_key(&pid) {
+                continue;
+            }
+            if old.end_time.is_none() {
+                self.lifecycle.exited(&old);
+                old.set_end_time();
+            }
+            if old.in_dead_grace(self.dead_grace) {
+                self.process_map.insert(pid, old);
+            }
+        }
+    }
+
+    /// Send a signal to a process, through the agent when we are connected to one. With
+    /// its `start_time`, a process that got the PID since is left alone.
+    pub async fn signal_process(
+        &mut self,
+        pid: i32,
+        signal: ProcessSignal,
+        start_time: Option<u64>,
+    ) -> Result<(), String> {
+        match self.remote.as_mut() {
+            Some(r) => r
+                .signal(pid, signal, start_time)
+                .map_err(|e| format!("Agent error: {}", e)),
+            None => send_signal(pid, signal, start_time).await,
+        }
+    }
+
+    /// Set the nice value of a process, through the agent when we are connected to one.
+    pub fn renice_process(&mut self, pid: i32, nice: i32) -> Result<(), String> {
+        let start_time = self
+            .selected_process
+            .as_deref()
+            .filter(|p| p.pid == pid)
+            .or_else(|| self.process_map.get(&pid))
+            .map(|p| p.start_time);
+        match self.remote.as_mut() {
+            Some(r) => r.renice(pid, nice, start_time).map_err(|e| e.to_string())?,
+            None => renice(pid, nice, start_time)?,
+        }
+        let targets = self
+            .selected_process
+            .iter_mut()
+            .map(|p| p.as_mut())
+            .chain(self.process_map.get_mut(&pid));
+        for p in targets.filter(|p| p.pid == pid) {
+            p.nice = nice;
+            p.priority = nice + 20;
+        }
+        Ok(())
+    }
+
+    /// Threads of the running processes of `uid`, the tasks its RLIMIT_NPROC counts.
+    pub fn user_tasks(&self, uid: u32) -> u64 {
+        self.process_map
+            .values()
+            .filter(|p| p.uid == uid && p.end_time.is_none())
+            .map(|p| p.threads_total)
+            .sum()
+    }
+
     pub fn sort_process_table(&mut self) {
         let pm = &self.process_map;
         let sorter = MProcess::field_comparator(self.psortby);
@@ -343,15 +531,20 @@ impl CPUTimeApp {
         }
     }
 
+    fn get_processor_name(&mut self) {
+        self.processor_name = match self.system.get_processors().first() {
+            Some(p) if !p.get_brand().trim().is_empty() => p.get_brand().trim().to_owned(),
+            Some(p) => p.get_name().to_owned(),
+            None => String::from("unknown"),
+        };
+    }
+
     pub async fn update_cpu(&mut self) {
         let procs = self.system.get_processors();
         let mut usage: f32 = 0.0;
         self.cpus.clear();
         let mut usagev: Vec<f32> = vec![];
         for (i, p) in procs.iter().enumerate() {
-            if i == 0 {
-                self.processor_name = p.get_name().to_owned();
-            }
             let mut u = p.get_cpu_usage();
             if u.is_nan() {
                 u = 0.0;
@@ -368,7 +561,33 @@ impl CPUTimeApp {
         }
     }
 
+    /// Subscribe to fork/exec/exit events to catch processes that don't live until a refresh.
+    pub fn start_proc_connector(&mut self) {
+        if self.remote.is_some() {
+            self.proc_connector_error =
+                Some(String::from("The process connector only watches the local machine."));
+            return;
+        }
+        match ProcConnector::start() {
+            Ok(c) => self.proc_connector = Some(c),
+            Err(e) => self.proc_connector_error = Some(e),
+        }
+    }
+
+    /// Fetch the parts of the host identity that do not change while we run.
+    pub async fn init(&mut self) {
+        if self.remote.is_some() {
+            return;
+        }
+        self.get_platform().await;
+        self.get_processor_name();
+    }
+
     pub async fn update(&mut self, keep_order: bool) {
+        if self.remote.is_some() {
+            self.update_remote(keep_order);
+            return;
+        }
         self.system.refresh_all();
         self.update_cpu().await;
 
@@ -380,7 +599,7 @@ impl CPUTimeApp {
         
         self.update_process_list(keep_order);
         self.update_frequency().await;
-        self.get_platform().await;
         self.get_uptime().await;
+        self.sensors.refresh();
     }
 }
@@ -1,7 +1,7 @@
+use crate::metrics::limits::{count_open_fds, format_limit_value, read_limits, set_limit, ResourceLimit};
 use crate::renderer::column::Column;
 use heim::process;
 use heim::process::ProcessError;
-use libc::getpriority;
 use libc::{id_t, setpriority};
 use std::cmp::Ordering::{self, Equal};
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
@@ -14,7 +14,7 @@ use sysinfo::ProcessStatus;
 use chrono::prelude::DateTime;
 use chrono::Duration as CDuration;
 use chrono::Local;
-use crate::{convert_result_to_string, convert_error_to_string};
+use crate::convert_error_to_string;
 
 pub fn get_tty(process: &sysinfo::Process) -> String {
     let pid = process.pid();
@@ -35,6 +35,388 @@ pub fn get_tty(process: &sysinfo::Process) -> String {
     "?".to_string()
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SchedPolicy {
+    Other,
+    Fifo,
+    RoundRobin,
+    Batch,
+    Idle,
+    Deadline,
+}
+
+impl SchedPolicy {
+    /// The policies that can be chosen in the scheduling editor, in display order.
+    pub const SETTABLE: [SchedPolicy; 5] = [
+        SchedPolicy::Other,
+        SchedPolicy::Batch,
+        SchedPolicy::Idle,
+        SchedPolicy::Fifo,
+        SchedPolicy::RoundRobin,
+    ];
+
+    pub fn from_raw(policy: i32) -> Option<SchedPolicy> {
+        match policy {
+            0 => Some(SchedPolicy::Other),
+            1 => Some(SchedPolicy::Fifo),
+            2 => Some(SchedPolicy::RoundRobin),
+            3 => Some(SchedPolicy::Batch),
+            5 => Some(SchedPolicy::Idle),
+            6 => Some(SchedPolicy::Deadline),
+            _ => None,
+        }
+    }
+
+    pub fn to_raw(self) -> i32 {
+        match self {
+            SchedPolicy::Other => 0,
+            SchedPolicy::Fifo => 1,
+            SchedPolicy::RoundRobin => 2,
+            SchedPolicy::Batch => 3,
+            SchedPolicy::Idle => 5,
+            SchedPolicy::Deadline => 6,
+        }
+    }
+
+    /// FIFO and RR take a static priority between 1 and 99, the others only accept 0.
+    pub fn is_realtime(self) -> bool {
+        self == SchedPolicy::Fifo || self == SchedPolicy::RoundRobin
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SchedPolicy::Other => "OTHER",
+            SchedPolicy::Fifo => "FIFO",
+            SchedPolicy::RoundRobin => "RR",
+            SchedPolicy::Batch => "BATCH",
+            SchedPolicy::Idle => "IDLE",
+            SchedPolicy::Deadline => "DEADLINE",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum IoClass {
+    None = 0,
+    RealTime = 1,
+    BestEffort = 2,
+    Idle = 3,
+}
+
+const IOPRIO_CLASS_SHIFT: i32 = 13;
+const IOPRIO_WHO_PROCESS: i32 = 1;
+
+impl IoClass {
+    pub const SETTABLE: [IoClass; 3] = [IoClass::RealTime, IoClass::BestEffort, IoClass::Idle];
+
+    pub fn from_raw(class: i32) -> IoClass {
+        match class {
+            1 => IoClass::RealTime,
+            2 => IoClass::BestEffort,
+            3 => IoClass::Idle,
+            _ => IoClass::None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            IoClass::None => "none",
+            IoClass::RealTime => "realtime",
+            IoClass::BestEffort => "best-effort",
+            IoClass::Idle => "idle",
+        }
+    }
+}
+
+/// I/O scheduling class and level (0 highest - 7 lowest) as reported by ioprio_get.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct IoPriority {
+    pub class: IoClass,
+    pub level: i32,
+}
+
+impl IoPriority {
+    /// Short form used in the process table, like ionice prints it: "be/4", "rt/0", "idle".
+    pub fn short(&self) -> String {
+        match self.class {
+            IoClass::None => String::from("none"),
+            IoClass::RealTime => format!("rt/{}", self.level),
+            IoClass::BestEffort => format!("be/{}", self.level),
+            IoClass::Idle => String::from("idle"),
+        }
+    }
+}
+
+/// Human readable reason for an errno returned by the scheduling syscalls.
+pub fn errno_message(errno: i32) -> &'static str {
+    match errno {
+        libc::EPERM => "Access Denied",
+        libc::EACCES => "Access Denied",
+        libc::ESRCH => "No Such Process",
+        libc::EINVAL => "Invalid value",
+        libc::ENOSYS => "Not supported on this platform",
+        _ => "Unknown error",
+    }
+}
+
+fn last_errno() -> i32 {
+    std::io::Error::last_os_error().raw_os_error().unwrap_or(0)
+}
+
+/// Fields of /proc/<pid>/stat that sysinfo doesn't expose.
+struct StatFields {
+    pgid: i32,
+    sid: i32,
+    minor_faults: u64,
+    major_faults: u64,
+    last_cpu: Option<usize>,
+    rt_priority: Option<i32>,
+    policy: Option<SchedPolicy>,
+}
+
+fn read_stat_fields(pid: i32) -> Option<StatFields> {
+    let stat = std::fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+    // the command name may contain spaces and parentheses so skip past the last ')',
+    // the remaining fields start at field 3 (state)
+    let fields: Vec<&str> = stat[stat.rfind(')')? + 1..].split_whitespace().collect();
+    let number = |i: usize| fields.get(i).and_then(|f| f.parse::<u64>().ok()).unwrap_or(0);
+    Some(StatFields {
+        pgid: number(2) as i32,
+        sid: number(3) as i32,
+        minor_faults: number(7),
+        major_faults: number(9),
+        last_cpu: fields.get(36).and_then(|f| f.parse::<usize>().ok()),
+        rt_priority: fields.get(37).and_then(|f| f.parse::<i32>().ok()),
+        policy: fields
+            .get(38)
+            .and_then(|f| f.parse::<i32>().ok())
+            .and_then(SchedPolicy::from_raw),
+    })
+}
+
+/// Voluntary and involuntary context switches from /proc/<pid>/status.
+fn read_context_switches(pid: i32) -> Option<(u64, u64)> {
+    let status = std::fs::read_to_string(format!("/proc/{}/status", pid)).ok()?;
+    let mut voluntary = None;
+    let mut involuntary = None;
+    for line in status.lines() {
+        if let Some(v) = line.strip_prefix("voluntary_ctxt_switches:") {
+            voluntary = v.trim().parse().ok();
+        } else if let Some(v) = line.strip_prefix("nonvoluntary_ctxt_switches:") {
+            involuntary = v.trim().parse().ok();
+        }
+    }
+    Some((voluntary?, involuntary?))
+}
+
+fn read_oom_score(pid: i32) -> Option<i32> {
+    std::fs::read_to_string(format!("/proc/{}/oom_score", pid))
+        .ok()?
+        .trim()
+        .parse()
+        .ok()
+}
+
+/// Kernel function the process sleeps in, empty while it runs.
+fn read_wchan(pid: i32) -> String {
+    match std::fs::read_to_string(format!("/proc/{}/wchan", pid)) {
+        Ok(w) if w != "0" => w,
+        _ => String::new(),
+    }
+}
+
+/// Empty when the link can't be read, e.g. processes of other users.
+fn read_cwd(pid: i32) -> String {
+    read_link(format!("/proc/{}/cwd", pid))
+        .map(|p| p.display().to_string())
+        .unwrap_or_default()
+}
+
+#[cfg(target_os = "linux")]
+fn get_io_priority(tid: i32) -> Option<IoPriority> {
+    let prio = unsafe { libc::syscall(libc::SYS_ioprio_get, IOPRIO_WHO_PROCESS, tid) };
+    if prio < 0 {
+        return None;
+    }
+    let prio = prio as i32;
+    Some(IoPriority {
+        class: IoClass::from_raw(prio >> IOPRIO_CLASS_SHIFT),
+        level: prio & ((1 << IOPRIO_CLASS_SHIFT) - 1),
+    })
+}
+
+#[cfg(not(target_os = "linux"))]
+fn get_io_priority(_tid: i32) -> Option<IoPriority> {
+    None
+}
+
+#[cfg(target_os = "linux")]
+fn set_io_priority(tid: i32, prio: IoPriority) -> Result<(), i32> {
+    let value = ((prio.class as i32) << IOPRIO_CLASS_SHIFT) | prio.level;
+    if unsafe { libc::syscall(libc::SYS_ioprio_set, IOPRIO_WHO_PROCESS, tid, value) } < 0 {
+        return Err(last_errno());
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn set_io_priority(_tid: i32, _prio: IoPriority) -> Result<(), i32> {
+    Err(libc::ENOSYS)
+}
+
+#[cfg(target_os = "linux")]
+fn set_scheduler(tid: i32, policy: SchedPolicy, rt_priority: i32) -> Result<(), i32> {
+    unsafe {
+        let mut param: libc::sched_param = std::mem::zeroed();
+        param.sched_priority = rt_priority;
+        if libc::sched_setscheduler(tid, policy.to_raw(), &param) < 0 {
+            return Err(last_errno());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn set_scheduler(_tid: i32, _policy: SchedPolicy, _rt_priority: i32) -> Result<(), i32> {
+    Err(libc::ENOSYS)
+}
+
+#[cfg(target_os = "linux")]
+fn get_affinity(tid: i32) -> Result<Vec<usize>, i32> {
+    unsafe {
+        let mut set: libc::cpu_set_t = std::mem::zeroed();
+        if libc::sched_getaffinity(tid, std::mem::size_of::<libc::cpu_set_t>(), &mut set) < 0 {
+            return Err(last_errno());
+        }
+        Ok((0..libc::CPU_SETSIZE as usize)
+            .filter(|cpu| libc::CPU_ISSET(*cpu, &set))
+            .collect())
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn get_affinity(_tid: i32) -> Result<Vec<usize>, i32> {
+    Err(libc::ENOSYS)
+}
+
+#[cfg(target_os = "linux")]
+fn set_affinity(tid: i32, cpus: &[usize]) -> Result<(), i32> {
+    unsafe {
+        let mut set: libc::cpu_set_t = std::mem::zeroed();
+        libc::CPU_ZERO(&mut set);
+        for cpu in cpus {
+            libc::CPU_SET(*cpu, &mut set);
+        }
+        if libc::sched_setaffinity(tid, std::mem::size_of::<libc::cpu_set_t>(), &set) < 0 {
+            return Err(last_errno());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn set_affinity(_tid: i32, _cpus: &[usize]) -> Result<(), i32> {
+    Err(libc::ENOSYS)
+}
+
+/// Thread ids of a process, the process itself if they can't be listed.
+fn get_tids(pid: i32) -> Vec<i32> {
+    let mut tids: Vec<i32> = match std::fs::read_dir(format!("/proc/{}/task", pid)) {
+        Ok(rd) => rd
+            .filter_map(|e| e.ok())
+            .filter_map(|e| e.file_name().to_string_lossy().parse::<i32>().ok())
+            .collect(),
+        Err(_) => vec![],
+    };
+    if tids.is_empty() {
+        tids.push(pid);
+    }
+    tids
+}
+
+/// Format a set of CPUs as ranges like taskset does (e.g. "1-4,6") using the 1-based
+/// labels of the system info section.
+pub fn format_cpu_list(cpus: &[usize]) -> String {
+    let mut ranges: Vec<String> = vec![];
+    let mut i = 0;
+    while i < cpus.len() {
+        let start = cpus[i];
+        let mut end = start;
+        while i + 1 < cpus.len() && cpus[i + 1] == end + 1 {
+            end = cpus[i + 1];
+            i += 1;
+        }
+        if start == end {
+            ranges.push(format!("{}", start + 1));
+        } else {
+            ranges.push(format!("{}-{}", start + 1, end + 1));
+        }
+        i += 1;
+    }
+    ranges.join(",")
+}
+
+/// Inverse of `format_cpu_list`, "1-4,6" gives the 0-based CPUs [0, 1, 2, 3, 5].
+pub fn parse_cpu_list(list: &str) -> Option<Vec<usize>> {
+    let mut cpus = vec![];
+    for part in list.split(',') {
+        let (start, end) = match part.split_once('-') {
+            Some((s, e)) => (s.trim().parse::<usize>().ok()?, e.trim().parse::<usize>().ok()?),
+            None => {
+                let c = part.trim().parse::<usize>().ok()?;
+                (c, c)
+            }
+        };
+        if start == 0 || start > end || end > 1024 {
+            return None;
+        }
+        cpus.extend(start - 1..end);
+    }
+    cpus.sort_unstable();
+    cpus.dedup();
+    if cpus.is_empty() {
+        None
+    } else {
+        Some(cpus)
+    }
+}
+
+/// Which of the procfs reads `update_from_procfs` does. Each is a file read (or a syscall)
+/// per process and update, so only what's shown or sorted by is read.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ProcfsReads {
+    /// Process group, session, page faults, last CPU and scheduling policy.
+    pub stat: bool,
+    /// Context switches.
+    pub status: bool,
+    pub oom_score: bool,
+    pub wchan: bool,
+    pub cwd: bool,
+    pub io_priority: bool,
+}
+
+impl ProcfsReads {
+    pub const ALL: ProcfsReads = ProcfsReads {
+        stat: true,
+        status: true,
+        oom_score: true,
+        wchan: true,
+        cwd: true,
+        io_priority: true,
+    };
+
+    pub fn union(self, other: ProcfsReads) -> ProcfsReads {
+        ProcfsReads {
+            stat: self.stat || other.stat,
+            status: self.status || other.status,
+            oom_score: self.oom_score || other.oom_score,
+            wchan: self.wchan || other.wchan,
+            cwd: self.cwd || other.cwd,
+            io_priority: self.io_priority || other.io_priority,
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct MProcess {
     pub pid: i32,
@@ -43,7 +425,10 @@ pub struct MProcess {
     pub user_name: String,
     pub tty: String,
     pub memory: u64,
+    pub peak_memory: u64,
+    pub prev_memory: u64,
     pub cpu_usage: f32,
+    pub prev_cpu_usage: f32,
     pub cum_cpu_usage: f64,
     pub command: Vec<String>,
     pub exe: String,
@@ -53,6 +438,15 @@ pub struct MProcess {
     pub nice: i32,
     pub virtual_memory: u64,
     pub threads_total: u64,
+    pub pgid: i32,
+    pub sid: i32,
+    pub minor_faults: u64,
+    pub major_faults: u64,
+    pub voluntary_switches: u64,
+    pub involuntary_switches: u64,
+    pub oom_score: Option<i32>,
+    pub wchan: String,
+    pub cwd: String,
     pub read_bytes: u64,
     pub write_bytes: u64,
     pub prev_read_bytes: u64,
@@ -71,19 +465,29 @@ pub struct MProcess {
     pub prev_io_delay: Duration,
     pub prev_swap_delay: Duration,
     pub et: DateTime<Local>, 
+    pub last_cpu: Option<usize>,
+    pub affinity: Option<Vec<usize>>,
+    pub sched_policy: Option<SchedPolicy>,
+    pub rt_priority: i32,
+    pub io_priority: Option<IoPriority>,
+    pub limits: Option<Vec<ResourceLimit>>,
+    pub open_fds: Option<usize>,
 }
 
 impl MProcess {
-    pub fn from_user_and_process(user_name: String, process: &Process) -> Self {
+    pub fn from_user_and_process(user_name: String, process: &Process, reads: ProcfsReads) -> Self {
         let disk_usage = process.disk_usage();
-        MProcess {
+        let mut mprocess = MProcess {
             uid: process.uid,
             user_name,
             pid: process.pid(),
             ppid: process.parent().unwrap_or_else(|| 1), // if you can't get the parent, it's init
             tty: get_tty(process), 
             memory: process.memory(),
+            peak_memory: process.memory(),
+            prev_memory: process.memory(),
             cpu_usage: process.cpu_usage(),
+            prev_cpu_usage: process.cpu_usage(),
             command: process.cmd().to_vec(),
             status: process.status(),
             exe: format!("{}", process.exe().display()),
@@ -93,6 +497,15 @@ impl MProcess {
             nice: process.nice,
             virtual_memory: process.virtual_memory(),
             threads_total: process.threads_total,
+            pgid: 0,
+            sid: 0,
+            minor_faults: 0,
+            major_faults: 0,
+            voluntary_switches: 0,
+            involuntary_switches: 0,
+            oom_score: None,
+            wchan: String::new(),
+            cwd: String::new(),
             read_bytes: disk_usage.total_read_bytes,
             write_bytes: disk_usage.total_written_bytes,
             prev_read_bytes: disk_usage.total_read_bytes,
@@ -111,6 +524,50 @@ impl MProcess {
             prev_io_delay: Duration::from_nanos(0),
             prev_swap_delay: Duration::from_nanos(0),
             et: Local::now(),
+            last_cpu: None,
+            affinity: None,
+            sched_policy: None,
+            rt_priority: 0,
+            io_priority: None,
+            limits: None,
+            open_fds: None,
+        };
+        mprocess.update_from_procfs(reads);
+        mprocess
+    }
+
+    /// Refresh what sysinfo doesn't provide: process group, session, page faults, context
+    /// switches, OOM score, wait channel, working directory, last CPU, scheduling policy and
+    /// I/O priority.
+    pub fn update_from_procfs(&mut self, reads: ProcfsReads) {
+        if reads.stat {
+            if let Some(stat) = read_stat_fields(self.pid) {
+                self.pgid = stat.pgid;
+                self.sid = stat.sid;
+                self.minor_faults = stat.minor_faults;
+                self.major_faults = stat.major_faults;
+                self.last_cpu = stat.last_cpu;
+                self.rt_priority = stat.rt_priority.unwrap_or(0);
+                self.sched_policy = stat.policy;
+            }
+        }
+        if reads.status {
+            if let Some((voluntary, involuntary)) = read_context_switches(self.pid) {
+                self.voluntary_switches = voluntary;
+                self.involuntary_switches = involuntary;
+            }
+        }
+        if reads.oom_score {
+            self.oom_score = read_oom_score(self.pid);
+        }
+        if reads.wchan {
+            self.wchan = read_wchan(self.pid);
+        }
+        if reads.cwd {
+            self.cwd = read_cwd(self.pid);
+        }
+        if reads.io_priority {
+            self.io_priority = get_io_priority(self.pid);
         }
     }
     pub fn get_read_bytes_sec(&self, tick_rate: &Duration) -> f64 {
@@ -120,38 +577,6 @@ impl MProcess {
         (self.write_bytes - self.prev_write_bytes) as f64 / tick_rate.as_secs_f64()
     }    
     
-    pub async fn suspend(&self) -> String {
-        match process::get(self.pid).await {
-            Ok(p) => convert_result_to_string!(p.suspend().await),
-            Err(e) => convert_error_to_string!(e),
-        }
-    }
-
-    pub async fn resume(&self) -> String {
-        match process::get(self.pid).await {
-            Ok(p) => convert_result_to_string!(p.resume().await),
-            Err(e) => convert_error_to_string!(e),
-        }
-    }
-
-    pub async fn kill(&self) -> String {
-        match process::get(self.pid).await {
-            Ok(p) => convert_result_to_string!(p.kill().await),
-            Err(e) => convert_error_to_string!(e),
-        }
-    }
-
-    pub async fn terminate(&self) -> String {
-        match process::get(self.pid).await {
-            Ok(p) => convert_result_to_string!(p.terminate().await),
-            Err(e) => convert_error_to_string!(e),
-        }
-    }
-
-    pub fn nice(&mut self) -> String {
-        self.set_priority(19)
-    }
-
     pub fn get_run_duration(&self) -> CDuration {
         let start_time = DateTime::<Local>::from(UNIX_EPOCH + Duration::from_secs(self.start_time));
         self.et - start_time
@@ -184,19 +609,164 @@ impl MProcess {
         (self.swap_delay.as_secs_f64() / process_duration.as_secs_f64()) * 100.0
     }
 
-    pub fn set_priority(&mut self, priority: i32) -> String {
-        let mut result = unsafe { setpriority(0, self.pid as id_t, priority) };
 
-        if result < 0 {
-            String::from("Couldn't set priority.")
+    /// Affinity is only read on demand (e.g. for the selected process) as it needs a syscall.
+    pub fn refresh_affinity(&mut self) {
+        self.affinity = get_affinity(self.pid).ok();
+    }
+
+    pub fn refresh_limits(&mut self) {
+        self.limits = read_limits(self.pid);
+        self.open_fds = count_open_fds(self.pid);
+    }
+
+    /// Refresh what is only shown in the process view, too expensive to read for every process.
+    /// Everything the process view shows, whatever the table reads.
+    pub fn refresh_details(&mut self) {
+        self.update_from_procfs(ProcfsReads::ALL);
+        self.refresh_affinity();
+        self.refresh_limits();
+    }
+
+    /// Current usage of a limit where we know it, in the units of /proc/<pid>/limits.
+    /// `user_tasks` are the threads of all processes of the user, what RLIMIT_NPROC counts.
+    pub fn limit_usage(&self, limit: &ResourceLimit, user_tasks: u64) -> Option<u64> {
+        match limit.resource {
+            Some(r) if r == libc::RLIMIT_NOFILE as i32 => self.open_fds.map(|n| n as u64),
+            Some(r) if r == libc::RLIMIT_NPROC as i32 => Some(user_tasks),
+            // memory is in KiB, the limits in bytes
+            Some(r) if r == libc::RLIMIT_AS as i32 => Some(self.virtual_memory * 1024),
+            Some(r) if r == libc::RLIMIT_RSS as i32 => Some(self.memory * 1024),
+            Some(r) if r == libc::RLIMIT_CPU as i32 => Some(self.cpu_time),
+            _ => None,
+        }
+    }
+
+    /// Change the soft and hard value of the `index`th limit with prlimit.
+    pub fn set_limit(&mut self, index: usize, soft: Option<u64>, hard: Option<u64>) -> String {
+        let limit = match self.limits.as_ref().and_then(|l| l.get(index)) {
+            Some(l) => l.clone(),
+            None => return String::from("No Such Limit"),
+        };
+        let resource = match limit.resource {
+            Some(r) => r,
+            None => return format!("{} can't be changed.", limit.name),
+        };
+        if let (Some(s), Some(h)) = (soft, hard) {
+            if s > h {
+                return String::from("Soft limit can't exceed the hard limit.");
+            }
+        } else if soft.is_none() && hard.is_some() {
+            return String::from("Soft limit can't exceed the hard limit.");
+        }
+        let result = set_limit(self.pid, resource, soft, hard);
+        self.refresh_limits();
+        match result {
+            Ok(_) => format!(
+                "{} set to {} / {}.",
+                limit.name,
+                format_limit_value(soft),
+                format_limit_value(hard)
+            ),
+            // raising a hard limit needs CAP_SYS_RESOURCE, changing another user's process too
+            Err(libc::EPERM) => format!(
+                "Access Denied: raising the hard limit of {} requires root.",
+                limit.name
+            ),
+            Err(e) => format!("Couldn't set {}: {}.", limit.name, errno_message(e)),
+        }
+    }
+
+    /// Restrict every thread of the process to the given CPUs.
+    pub fn set_affinity(&mut self, cpus: &[usize]) -> String {
+        if cpus.is_empty() {
+            return String::from("At least one CPU must be allowed.");
+        }
+        let (failed, total, errno) = self.for_each_thread(|tid| set_affinity(tid, cpus));
+        self.refresh_affinity();
+        if failed == 0 {
+            format!("Affinity set to {}.", format_cpu_list(cpus))
         } else {
-            unsafe {
-                result = getpriority(0, self.pid as id_t);
+            format!(
+                "Couldn't set affinity on {} of {} threads: {}.",
+                failed,
+                total,
+                errno_message(errno)
+            )
+        }
+    }
+
+    /// Apply `f` to every thread of the process, ignoring threads that exit meanwhile.
+    /// Returns the number of failures, the number of threads and the last errno.
+    fn for_each_thread<F>(&self, f: F) -> (usize, usize, i32)
+    where
+        F: Fn(i32) -> Result<(), i32>,
+    {
+        let tids = get_tids(self.pid);
+        let mut failed = 0;
+        let mut last_err = 0;
+        for tid in &tids {
+            if let Err(e) = f(*tid) {
+                if e != libc::ESRCH || *tid == self.pid {
+                    failed += 1;
+                    last_err = e;
+                }
             }
-            self.priority = result + 20;
-            self.nice = result;
-            String::from("Priority Set.")
         }
+        (failed, tids.len(), last_err)
+    }
+
+    /// Change the scheduling policy of every thread, `rt_priority` is only used by FIFO/RR.
+    pub fn set_scheduler(&mut self, policy: SchedPolicy, rt_priority: i32) -> String {
+        let rt_priority = if policy.is_realtime() {
+            if !(1..=99).contains(&rt_priority) {
+                return String::from("Real-time priority must be between 1 and 99.");
+            }
+            rt_priority
+        } else {
+            0
+        };
+        let (failed, total, errno) = self.for_each_thread(|tid| set_scheduler(tid, policy, rt_priority));
+        self.update_from_procfs(ProcfsReads::ALL);
+        if failed == 0 {
+            format!("Scheduling policy set to {}.", policy.as_str())
+        } else if errno == libc::EPERM && policy.is_realtime() {
+            String::from("Access Denied: real-time policies require root (CAP_SYS_NICE).")
+        } else {
+            format!(
+                "Couldn't set policy on {} of {} threads: {}.",
+                failed,
+                total,
+                errno_message(errno)
+            )
+        }
+    }
+
+    /// Change the I/O class and level of every thread, like ionice does.
+    pub fn set_io_priority(&mut self, class: IoClass, level: i32) -> String {
+        if !(0..=7).contains(&level) {
+            return String::from("I/O priority must be between 0 and 7.");
+        }
+        let prio = IoPriority { class, level };
+        let (failed, total, errno) = self.for_each_thread(|tid| set_io_priority(tid, prio));
+        self.update_from_procfs(ProcfsReads::ALL);
+        if failed == 0 {
+            format!("I/O priority set to {}.", prio.short())
+        } else if errno == libc::EPERM && class == IoClass::RealTime {
+            String::from("Access Denied: the realtime I/O class requires root (CAP_SYS_ADMIN).")
+        } else {
+            format!(
+                "Couldn't set I/O priority on {} of {} threads: {}.",
+                failed,
+                total,
+                errno_message(errno)
+            )
+        }
+    }
+
+    /// An exited process that should still be shown, `last_updated` is when it was last seen.
+    pub fn in_dead_grace(&self, grace: Duration) -> bool {
+        matches!(self.last_updated.elapsed(), Ok(since) if since < grace)
     }
 
     pub fn set_end_time(&mut self) {
@@ -230,11 +800,162 @@ impl MProcess {
             Column::VirtualMemory => |pa, pb, _tick| pa.virtual_memory.cmp(&pb.virtual_memory),
             Column::CPUTime => |pa, pb, _tick| pa.cpu_time.cmp(&pb.cpu_time),
             Column::StartTime => |pa, pb, _tick| pa.start_time.cmp(&pb.start_time),
+            Column::LastCPU => |pa, pb, _tick| pa.last_cpu.cmp(&pb.last_cpu),
+            Column::SchedPolicy => |pa, pb, _tick| {
+                pa.sched_policy
+                    .map(SchedPolicy::to_raw)
+                    .cmp(&pb.sched_policy.map(SchedPolicy::to_raw))
+                    .then(pa.rt_priority.cmp(&pb.rt_priority))
+            },
+            Column::IOPriority => |pa, pb, _tick| pa.io_priority.cmp(&pb.io_priority),
+            Column::PGID => |pa, pb, _tick| pa.pgid.cmp(&pb.pgid),
+            Column::SID => |pa, pb, _tick| pa.sid.cmp(&pb.sid),
+            Column::Threads => |pa, pb, _tick| pa.threads_total.cmp(&pb.threads_total),
+            Column::MinorFaults => |pa, pb, _tick| pa.minor_faults.cmp(&pb.minor_faults),
+            Column::MajorFaults => |pa, pb, _tick| pa.major_faults.cmp(&pb.major_faults),
+            Column::VoluntarySwitches => {
+                |pa, pb, _tick| pa.voluntary_switches.cmp(&pb.voluntary_switches)
+            }
+            Column::InvoluntarySwitches => {
+                |pa, pb, _tick| pa.involuntary_switches.cmp(&pb.involuntary_switches)
+            }
+            Column::OOMScore => |pa, pb, _tick| pa.oom_score.cmp(&pb.oom_score),
+            Column::WChan => |pa, pb, _tick| pa.wchan.cmp(&pb.wchan),
+            Column::Exe => |pa, pb, _tick| pa.exe.cmp(&pb.exe),
+            Column::Cwd => |pa, pb, _tick| pa.cwd.cmp(&pb.cwd),
+            Column::Elapsed => |pa, pb, _tick| pa.get_run_duration().cmp(&pb.get_run_duration()),
             Column::CMD => |pa, pb, _tick| pa.name.cmp(&pb.name),
         }
     }
 }
 
+/// Inverse of `ProcessStatusExt::to_single_char`.
+pub fn status_from_single_char(c: &str) -> ProcessStatus {
+    match c {
+        "I" => ProcessStatus::Idle,
+        "R" => ProcessStatus::Run,
+        "S" => ProcessStatus::Sleep,
+        "T" => ProcessStatus::Stop,
+        "Z" => ProcessStatus::Zombie,
+        "t" => ProcessStatus::Tracing,
+        "x" => ProcessStatus::Dead,
+        "K" => ProcessStatus::Wakekill,
+        "W" => ProcessStatus::Waking,
+        "P" => ProcessStatus::Parked,
+        _ => ProcessStatus::Unknown(0),
+    }
+}
+
+/// The signals the process actions send.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProcessSignal {
+    Kill,
+    Terminate,
+    Suspend,
+    Resume,
+}
+
+impl ProcessSignal {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ProcessSignal::Kill => "kill",
+            ProcessSignal::Terminate => "terminate",
+            ProcessSignal::Suspend => "suspend",
+            ProcessSignal::Resume => "resume",
+        }
+    }
+
+    /// The signal actually sent.
+    pub fn signal_name(self) -> &'static str {
+        match self {
+            ProcessSignal::Kill => "SIGKILL",
+            ProcessSignal::Terminate => "SIGTERM",
+            ProcessSignal::Suspend => "SIGSTOP",
+            ProcessSignal::Resume => "SIGCONT",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<ProcessSignal> {
+        match name {
+            "kill" => Some(ProcessSignal::Kill),
+            "terminate" => Some(ProcessSignal::Terminate),
+            "suspend" => Some(ProcessSignal::Suspend),
+            "resume" => Some(ProcessSignal::Resume),
+            _ => None,
+        }
+    }
+}
+
+/// When a process started in seconds since the epoch, read now rather than taken from the
+/// last update, worked out from /proc/<pid>/stat the way sysinfo does.
+pub fn read_start_time(pid: i32) -> Option<u64> {
+    let stat = std::fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+    // field 22, the 20th after the command name
+    let ticks: u64 = stat[stat.rfind(')')? + 1..]
+        .split_whitespace()
+        .nth(19)?
+        .parse()
+        .ok()?;
+    let boot_time: u64 = std::fs::read_to_string("/proc/stat")
+        .ok()?
+        .lines()
+        .find_map(|l| l.strip_prefix("btime "))?
+        .trim()
+        .parse()
+        .ok()?;
+    let ticks_per_sec = unsafe { libc::sysconf(libc::_SC_CLK_TCK) };
+    if ticks_per_sec <= 0 {
+        return None;
+    }
+    Some(ticks / ticks_per_sec as u64 + boot_time)
+}
+
+/// Whether `pid` still belongs to the process started at `start_time`, and not to one that
+/// got the PID after it exited.
+pub fn is_same_process(pid: i32, start_time: u64) -> bool {
+    // the boot time the kernel reports moves by a second now and then
+    matches!(read_start_time(pid), Some(t) if t.abs_diff(start_time) <= 1)
+}
+
+/// Send a signal to a local process by PID. With the `start_time` of the process meant, a
+/// process that got its PID since is left alone.
+pub async fn send_signal(
+    pid: i32,
+    signal: ProcessSignal,
+    start_time: Option<u64>,
+) -> Result<(), String> {
+    if matches!(start_time, Some(t) if !is_same_process(pid, t)) {
+        return Err(String::from("No Such Process"));
+    }
+    let p = process::get(pid)
+        .await
+        .map_err(|e| convert_error_to_string!(e))?;
+    match signal {
+        ProcessSignal::Kill => p.kill().await,
+        ProcessSignal::Terminate => p.terminate().await,
+        ProcessSignal::Suspend => p.suspend().await,
+        ProcessSignal::Resume => p.resume().await,
+    }
+    .map_err(|e| convert_error_to_string!(e))
+}
+
+/// Set the nice value of a local process by PID, left alone like in `send_signal` when it
+/// didn't start at `start_time`.
+pub fn renice(pid: i32, nice: i32, start_time: Option<u64>) -> Result<(), String> {
+    if matches!(start_time, Some(t) if !is_same_process(pid, t)) {
+        return Err(String::from("No Such Process"));
+    }
+    if unsafe { setpriority(0, pid as id_t, nice) } < 0 {
+        let errno = last_errno();
+        return Err(match errno {
+            // lowering the nice value needs CAP_SYS_NICE
+            libc::EACCES => String::from("Access Denied: raising priority requires root."),
+            _ => format!("Couldn't set priority: {}.", errno_message(errno)),
+        });
+    }
+    Ok(())
+}
+
 pub trait ProcessStatusExt {
     fn to_single_char(&self) -> &str;
 }
@@ -256,3 +977,19 @@ impl ProcessStatusExt for ProcessStatus {
         }
     }
 }
+
+#[cfg(test)]
+impl MProcess {
+    /// A sleeping process of alice's with nothing used yet, for tests to fill in.
+    pub fn for_test(pid: i32, name: &str) -> MProcess {
+        let (pid, exe) = (pid.to_string(), format!("/usr/bin/{}", name));
+        let fields: Vec<String> = [
+            "P", &pid, "1", "1000", "alice", "", "0", "0", "0", name, &exe, "S", name, "20", "0", "0", "1",
+            "0", "0", "0", "0", "0", "0", "", "", "0", "", "",
+        ]
+        .iter()
+        .map(|f| f.to_string())
+        .collect();
+        crate::remote::protocol::decode_process(&fields).expect("a full record")
+    }
+}
@@ -0,0 +1,314 @@
+//! Subscriber for the Linux process connector, which reports every fork, exec and exit as it
+//! happens. Sampling only sees processes alive at refresh time, this catches the ones that
+//! live for a few milliseconds (compilers, scripts, ...). Subscribing needs CAP_NET_ADMIN.
+
+use chrono::{DateTime, Local};
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+const RECENT_CAPACITY: usize = 500;
+
+/// How an exited process ended, decoded from its wait status.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExitStatus {
+    Code(i32),
+    Signal(i32),
+}
+
+impl ExitStatus {
+    fn from_wait_status(status: u32) -> ExitStatus {
+        let signal = (status & 0x7f) as i32;
+        if signal == 0 {
+            ExitStatus::Code(((status >> 8) & 0xff) as i32)
+        } else {
+            ExitStatus::Signal(signal)
+        }
+    }
+
+    pub fn is_failure(self) -> bool {
+        self != ExitStatus::Code(0)
+    }
+
+    pub fn describe(self) -> String {
+        match self {
+            ExitStatus::Code(c) => format!("exit {}", c),
+            ExitStatus::Signal(s) => format!("signal {}", s),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ExitedProcess {
+    pub time: DateTime<Local>,
+    pub pid: i32,
+    pub ppid: i32,
+    pub name: String,
+    pub status: ExitStatus,
+    /// None when we didn't see the process start.
+    pub lifetime: Option<Duration>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct CommandCount {
+    pub execs: u64,
+    pub exits: u64,
+    pub failures: u64,
+}
+
+struct Tracked {
+    name: String,
+    ppid: i32,
+    started: Instant,
+}
+
+#[derive(Default)]
+pub struct ConnectorState {
+    /// Most recent exits, oldest first.
+    pub recent: VecDeque<ExitedProcess>,
+    pub counts: HashMap<String, CommandCount>,
+    /// Set when the subscription failed or the socket stopped working.
+    pub error: Option<String>,
+    live: HashMap<i32, Tracked>,
+}
+
+impl ConnectorState {
+    fn fork(&mut self, parent: i32, child: i32) {
+        // until it execs, a child runs the code of its parent under the parent's name
+        let name = self
+            .live
+            .get(&parent)
+            .map(|t| t.name.clone())
+            .or_else(|| read_comm(child))
+            .unwrap_or_default();
+        self.live.insert(
+            child,
+            Tracked {
+                name,
+                ppid: parent,
+                started: Instant::now(),
+            },
+        );
+    }
+
+    fn exec(&mut self, pid: i32) {
+        let name = read_comm(pid).unwrap_or_default();
+        self.counts.entry(name.clone()).or_default().execs += 1;
+        match self.live.get_mut(&pid) {
+            Some(t) => t.name = name,
+            None => {
+                self.live.insert(
+                    pid,
+                    Tracked {
+                        name,
+                        ppid: 0,
+                        started: Instant::now(),
+                    },
+                );
+            }
+        }
+    }
+
+    fn exit(&mut self, pid: i32, wait_status: u32) {
+        let tracked = self.live.remove(&pid);
+        let status = ExitStatus::from_wait_status(wait_status);
+        let (name, ppid, lifetime) = match tracked {
+            Some(t) => (t.name, t.ppid, Some(t.started.elapsed())),
+            // started before we subscribed, it may still be readable as a zombie
+            None => (read_comm(pid).unwrap_or_else(|| String::from("?")), 0, None),
+        };
+        let count = self.counts.entry(name.clone()).or_default();
+        count.exits += 1;
+        if status.is_failure() {
+            count.failures += 1;
+        }
+        if self.recent.len() == RECENT_CAPACITY {
+            self.recent.pop_front();
+        }
+        self.recent.push_back(ExitedProcess {
+            time: Local::now(),
+            pid,
+            ppid,
+            name,
+            status,
+            lifetime,
+        });
+    }
+}
+
+fn read_comm(pid: i32) -> Option<String> {
+    std::fs::read_to_string(format!("/proc/{}/comm", pid))
+        .ok()
+        .map(|c| c.trim_end().to_string())
+}
+
+/// Handle to the subscriber thread, its findings are in `state`.
+pub struct ProcConnector {
+    pub state: Arc<Mutex<ConnectorState>>,
+}
+
+impl ProcConnector {
+    /// Subscribe and start listening in the background. Errors explain what's missing.
+    pub fn start() -> Result<ProcConnector, String> {
+        let socket = sys::subscribe()?;
+        let state = Arc::new(Mutex::new(ConnectorState::default()));
+        let thread_state = Arc::clone(&state);
+        std::thread::spawn(move || sys::listen(socket, thread_state));
+        Ok(ProcConnector { state })
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod sys {
+    use super::ConnectorState;
+    use std::convert::TryInto;
+    use std::io;
+    use std::mem::size_of;
+    use std::sync::{Arc, Mutex};
+
+    // linux/connector.h and linux/cn_proc.h
+    const CN_IDX_PROC: u32 = 1;
+    const CN_VAL_PROC: u32 = 1;
+    const PROC_CN_MCAST_LISTEN: u32 = 1;
+    const PROC_EVENT_FORK: u32 = 0x0000_0001;
+    const PROC_EVENT_EXEC: u32 = 0x0000_0002;
+    const PROC_EVENT_EXIT: u32 = 0x8000_0000;
+    const NLMSG_HDR_LEN: usize = 16;
+    const CN_MSG_LEN: usize = 20;
+    // what, cpu and timestamp come before the event data in struct proc_event
+    const EVENT_DATA: usize = NLMSG_HDR_LEN + CN_MSG_LEN + 16;
+
+    fn u32_at(buf: &[u8], offset: usize) -> Option<u32> {
+        buf.get(offset..offset + 4)
+            .map(|b| u32::from_ne_bytes(b.try_into().expect("slice of 4 bytes")))
+    }
+
+    fn subscribe_error(what: &str) -> String {
+        let err = io::Error::last_os_error();
+        match err.raw_os_error() {
+            Some(libc::EPERM) | Some(libc::EACCES) => format!(
+                "Process connector needs root or CAP_NET_ADMIN ({}: {}).",
+                what, err
+            ),
+            _ => format!("Process connector unavailable ({}: {}).", what, err),
+        }
+    }
+
+    pub fn subscribe() -> Result<i32, String> {
+        unsafe {
+            let fd = libc::socket(
+                libc::AF_NETLINK,
+                libc::SOCK_DGRAM | libc::SOCK_CLOEXEC,
+                libc::NETLINK_CONNECTOR,
+            );
+            if fd < 0 {
+                return Err(subscribe_error("socket"));
+            }
+            let mut addr: libc::sockaddr_nl = std::mem::zeroed();
+            addr.nl_family = libc::AF_NETLINK as u16;
+            addr.nl_pid = 0;
+            addr.nl_groups = CN_IDX_PROC;
+            if libc::bind(
+                fd,
+                &addr as *const libc::sockaddr_nl as *const libc::sockaddr,
+                size_of::<libc::sockaddr_nl>() as u32,
+            ) < 0
+            {
+                let e = subscribe_error("bind");
+                libc::close(fd);
+                return Err(e);
+            }
+
+            let len = NLMSG_HDR_LEN + CN_MSG_LEN + 4;
+            let mut msg: Vec<u8> = Vec::with_capacity(len);
+            // nlmsghdr
+            msg.extend_from_slice(&(len as u32).to_ne_bytes());
+            msg.extend_from_slice(&(libc::NLMSG_DONE as u16).to_ne_bytes());
+            msg.extend_from_slice(&0u16.to_ne_bytes());
+            msg.extend_from_slice(&0u32.to_ne_bytes());
+            msg.extend_from_slice(&(libc::getpid() as u32).to_ne_bytes());
+            // cn_msg
+            msg.extend_from_slice(&CN_IDX_PROC.to_ne_bytes());
+            msg.extend_from_slice(&CN_VAL_PROC.to_ne_bytes());
+            msg.extend_from_slice(&0u32.to_ne_bytes());
+            msg.extend_from_slice(&0u32.to_ne_bytes());
+            msg.extend_from_slice(&4u16.to_ne_bytes());
+            msg.extend_from_slice(&0u16.to_ne_bytes());
+            // the operation
+            msg.extend_from_slice(&PROC_CN_MCAST_LISTEN.to_ne_bytes());
+            if libc::send(fd, msg.as_ptr() as *const libc::c_void, msg.len(), 0) < 0 {
+                let e = subscribe_error("send");
+                libc::close(fd);
+                return Err(e);
+            }
+            Ok(fd)
+        }
+    }
+
+    pub fn listen(fd: i32, state: Arc<Mutex<ConnectorState>>) {
+        let mut buf = vec![0u8; 4096];
+        loop {
+            let n = unsafe { libc::recv(fd, buf.as_mut_ptr() as *mut libc::c_void, buf.len(), 0) };
+            if n < 0 {
+                let err = io::Error::last_os_error();
+                match err.raw_os_error() {
+                    // ENOBUFS means we fell behind and lost some events, keep going
+                    Some(libc::EINTR) | Some(libc::ENOBUFS) => continue,
+                    _ => {
+                        state.lock().expect("Connector lock poisoned").error =
+                            Some(format!("Process connector stopped: {}.", err));
+                        break;
+                    }
+                }
+            }
+            let msg = &buf[..n as usize];
+            let what = match u32_at(msg, NLMSG_HDR_LEN + CN_MSG_LEN) {
+                Some(w) => w,
+                None => continue,
+            };
+            let data = |i: usize| u32_at(msg, EVENT_DATA + 4 * i).map(|v| v as i32);
+            let mut state = state.lock().expect("Connector lock poisoned");
+            match what {
+                // parent pid, parent tgid, child pid, child tgid; threads have pid != tgid
+                PROC_EVENT_FORK => {
+                    if let (Some(ptgid), Some(pid), Some(tgid)) = (data(1), data(2), data(3)) {
+                        if pid == tgid {
+                            state.fork(ptgid, pid);
+                        }
+                    }
+                }
+                PROC_EVENT_EXEC => {
+                    if let (Some(pid), Some(tgid)) = (data(0), data(1)) {
+                        if pid == tgid {
+                            state.exec(pid);
+                        }
+                    }
+                }
+                // pid, tgid, exit code (a wait status), exit signal
+                PROC_EVENT_EXIT => {
+                    if let (Some(pid), Some(tgid), Some(code)) = (data(0), data(1), data(2)) {
+                        if pid == tgid {
+                            state.exit(pid, code as u32);
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        unsafe {
+            libc::close(fd);
+        }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+mod sys {
+    use super::ConnectorState;
+    use std::sync::{Arc, Mutex};
+
+    pub fn subscribe() -> Result<i32, String> {
+        Err(String::from("The process connector is only available on Linux."))
+    }
+
+    pub fn listen(_fd: i32, _state: Arc<Mutex<ConnectorState>>) {}
+}
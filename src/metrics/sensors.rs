@@ -0,0 +1,335 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// A single temperature reading in degrees Celsius.
+#[derive(Clone, Debug)]
+pub struct Temperature {
+    pub label: String,
+    pub current: f64,
+    pub high: Option<f64>,
+    pub critical: Option<f64>,
+}
+
+/// A single fan reading in revolutions per minute.
+#[derive(Clone, Debug)]
+pub struct Fan {
+    pub label: String,
+    pub rpm: u64,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TemperatureLevel {
+    Normal,
+    Warm,
+    High,
+    Critical,
+}
+
+impl Temperature {
+    pub fn level(&self) -> TemperatureLevel {
+        if let Some(c) = self.critical {
+            if self.current >= c {
+                return TemperatureLevel::Critical;
+            }
+        }
+        // without a high threshold from the hardware, fall back to something sensible for a CPU
+        let high = self.high.unwrap_or(80.0);
+        if self.current >= high {
+            TemperatureLevel::High
+        } else if self.current >= high * 0.85 {
+            TemperatureLevel::Warm
+        } else {
+            TemperatureLevel::Normal
+        }
+    }
+}
+
+fn read_trimmed(path: &Path) -> Option<String> {
+    fs::read_to_string(path).ok().map(|s| s.trim().to_string())
+}
+
+fn read_number(path: &Path) -> Option<i64> {
+    read_trimmed(path)?.parse::<i64>().ok()
+}
+
+/// sysfs reports temperatures in millidegrees Celsius.
+fn read_millidegrees(path: &Path) -> Option<f64> {
+    read_number(path).map(|v| v as f64 / 1000.0)
+}
+
+/// Entries of `dir` whose file name starts with `prefix`, sorted by the numeric suffix so that
+/// hwmon10 comes after hwmon9.
+fn numbered_entries(dir: &Path, prefix: &str) -> Vec<(u32, PathBuf)> {
+    let mut entries: Vec<(u32, PathBuf)> = match fs::read_dir(dir) {
+        Ok(rd) => rd
+            .filter_map(|e| e.ok())
+            .filter_map(|e| {
+                let name = e.file_name().to_string_lossy().to_string();
+                let idx = name.strip_prefix(prefix)?.parse::<u32>().ok()?;
+                Some((idx, e.path()))
+            })
+            .collect(),
+        Err(_) => vec![],
+    };
+    entries.sort_by_key(|(i, _)| *i);
+    entries
+}
+
+/// Indexes N of every `<kind>N_input` file in an hwmon chip directory.
+fn hwmon_inputs(chip: &Path, kind: &str) -> Vec<u32> {
+    let mut idx: Vec<u32> = match fs::read_dir(chip) {
+        Ok(rd) => rd
+            .filter_map(|e| e.ok())
+            .filter_map(|e| {
+                let name = e.file_name().to_string_lossy().to_string();
+                name.strip_prefix(kind)?
+                    .strip_suffix("_input")?
+                    .parse::<u32>()
+                    .ok()
+            })
+            .collect(),
+        Err(_) => vec![],
+    };
+    idx.sort_unstable();
+    idx
+}
+
+fn hwmon_chip_name(chip: &Path) -> String {
+    read_trimmed(&chip.join("name"))
+        .or_else(|| read_trimmed(&chip.join("device/name")))
+        .unwrap_or_else(|| {
+            chip.file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_default()
+        })
+}
+
+/// Reads temperatures and fans from hwmon and thermal zones below a sysfs root
+/// (normally `/sys`, but any directory with the same layout works).
+pub struct Sensors {
+    pub root: PathBuf,
+    pub temperatures: Vec<Temperature>,
+    pub fans: Vec<Fan>,
+}
+
+impl Sensors {
+    pub fn new(root: PathBuf) -> Sensors {
+        Sensors {
+            root,
+            temperatures: vec![],
+            fans: vec![],
+        }
+    }
+
+    pub fn refresh(&mut self) {
+        self.temperatures.clear();
+        self.fans.clear();
+        let chips = self.read_hwmon();
+        self.read_thermal_zones(&chips);
+    }
+
+    /// Returns the names of the chips found so that thermal zones duplicating them can be skipped.
+    fn read_hwmon(&mut self) -> HashSet<String> {
+        let mut chips = HashSet::new();
+        for (_, chip) in numbered_entries(&self.root.join("class/hwmon"), "hwmon") {
+            let chip_name = hwmon_chip_name(&chip);
+            for i in hwmon_inputs(&chip, "temp") {
+                let current = match read_millidegrees(&chip.join(format!("temp{}_input", i))) {
+                    Some(t) => t,
+                    None => continue,
+                };
+                let label = read_trimmed(&chip.join(format!("temp{}_label", i)))
+                    .map(|l| format!("{} {}", chip_name, l))
+                    .unwrap_or_else(|| format!("{} temp{}", chip_name, i));
+                // some drivers report 0 or absurd values for thresholds they don't know
+                let high = read_millidegrees(&chip.join(format!("temp{}_max", i)))
+                    .filter(|t| *t > 0.0 && *t < 200.0);
+                let critical = read_millidegrees(&chip.join(format!("temp{}_crit", i)))
+                    .filter(|t| *t > 0.0 && *t < 200.0);
+                self.temperatures.push(Temperature {
+                    label,
+                    current,
+                    high,
+                    critical,
+                });
+            }
+            for i in hwmon_inputs(&chip, "fan") {
+                let rpm = match read_number(&chip.join(format!("fan{}_input", i))) {
+                    Some(r) if r >= 0 => r as u64,
+                    _ => continue,
+                };
+                let label = read_trimmed(&chip.join(format!("fan{}_label", i)))
+                    .map(|l| format!("{} {}", chip_name, l))
+                    .unwrap_or_else(|| format!("{} fan{}", chip_name, i));
+                self.fans.push(Fan { label, rpm });
+            }
+            chips.insert(chip_name);
+        }
+        chips
+    }
+
+    fn read_thermal_zones(&mut self, chips: &HashSet<String>) {
+        for (_, zone) in numbered_entries(&self.root.join("class/thermal"), "thermal_zone") {
+            let zone_type = read_trimmed(&zone.join("type")).unwrap_or_default();
+            if chips.contains(&zone_type) {
+                continue;
+            }
+            let current = match read_millidegrees(&zone.join("temp")) {
+                Some(t) => t,
+                None => continue,
+            };
+            let mut high: Option<f64> = None;
+            let mut critical: Option<f64> = None;
+            // trip points are numbered contiguously from 0
+            let mut i = 0;
+            while let Some(trip_type) = read_trimmed(&zone.join(format!("trip_point_{}_type", i))) {
+                let temp = read_millidegrees(&zone.join(format!("trip_point_{}_temp", i)));
+                match trip_type.as_str() {
+                    "critical" => critical = temp.or(critical),
+                    "hot" | "passive" => {
+                        high = match (high, temp) {
+                            (Some(h), Some(t)) => Some(h.min(t)),
+                            (h, t) => h.or(t),
+                        }
+                    }
+                    _ => {}
+                }
+                i += 1;
+            }
+            let label = if zone_type.is_empty() {
+                zone.file_name()
+                    .map(|n| n.to_string_lossy().to_string())
+                    .unwrap_or_default()
+            } else {
+                zone_type
+            };
+            self.temperatures.push(Temperature {
+                label,
+                current,
+                high,
+                critical,
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A sysfs-like directory under the temp dir, removed when dropped.
+    struct Fixture(PathBuf);
+
+    impl Fixture {
+        fn new(name: &str) -> Fixture {
+            let root = std::env::temp_dir().join(format!("el-modeer-{}-{}", name, std::process::id()));
+            let _ = fs::remove_dir_all(&root);
+            Fixture(root)
+        }
+
+        fn file(&self, path: &str, contents: &str) {
+            let path = self.0.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, contents).unwrap();
+        }
+
+        fn refresh(&self) -> Sensors {
+            let mut sensors = Sensors::new(self.0.clone());
+            sensors.refresh();
+            sensors
+        }
+    }
+
+    impl Drop for Fixture {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[test]
+    fn reads_hwmon_temperatures_and_fans() {
+        let sys = Fixture::new("hwmon");
+        sys.file("class/hwmon/hwmon0/name", "coretemp\n");
+        sys.file("class/hwmon/hwmon0/temp1_input", "45000\n");
+        sys.file("class/hwmon/hwmon0/temp1_label", "Package id 0\n");
+        sys.file("class/hwmon/hwmon0/temp1_max", "90000\n");
+        sys.file("class/hwmon/hwmon0/temp1_crit", "0\n");
+        sys.file("class/hwmon/hwmon0/temp2_input", "51500\n");
+        sys.file("class/hwmon/hwmon0/fan1_input", "1200\n");
+        let sensors = sys.refresh();
+
+        assert_eq!(sensors.temperatures.len(), 2);
+        let package = &sensors.temperatures[0];
+        assert_eq!(package.label, "coretemp Package id 0");
+        assert_eq!(package.current, 45.0);
+        assert_eq!(package.high, Some(90.0));
+        // a zero threshold means the driver doesn't know it
+        assert_eq!(package.critical, None);
+        assert_eq!(sensors.temperatures[1].label, "coretemp temp2");
+        assert_eq!(sensors.temperatures[1].current, 51.5);
+        assert_eq!(sensors.fans.len(), 1);
+        assert_eq!(sensors.fans[0].label, "coretemp fan1");
+        assert_eq!(sensors.fans[0].rpm, 1200);
+    }
+
+    #[test]
+    fn orders_chips_numerically() {
+        let sys = Fixture::new("order");
+        sys.file("class/hwmon/hwmon10/name", "ten\n");
+        sys.file("class/hwmon/hwmon10/temp1_input", "10000\n");
+        sys.file("class/hwmon/hwmon9/name", "nine\n");
+        sys.file("class/hwmon/hwmon9/temp1_input", "9000\n");
+        let labels: Vec<String> = sys.refresh().temperatures.into_iter().map(|t| t.label).collect();
+        assert_eq!(labels, vec!["nine temp1", "ten temp1"]);
+    }
+
+    #[test]
+    fn reads_thermal_zones_and_skips_duplicated_chips() {
+        let sys = Fixture::new("thermal");
+        sys.file("class/hwmon/hwmon0/name", "acpitz\n");
+        sys.file("class/hwmon/hwmon0/temp1_input", "40000\n");
+        sys.file("class/thermal/thermal_zone0/type", "acpitz\n");
+        sys.file("class/thermal/thermal_zone0/temp", "40000\n");
+        sys.file("class/thermal/thermal_zone1/type", "x86_pkg_temp\n");
+        sys.file("class/thermal/thermal_zone1/temp", "62000\n");
+        sys.file("class/thermal/thermal_zone1/trip_point_0_type", "passive\n");
+        sys.file("class/thermal/thermal_zone1/trip_point_0_temp", "95000\n");
+        sys.file("class/thermal/thermal_zone1/trip_point_1_type", "hot\n");
+        sys.file("class/thermal/thermal_zone1/trip_point_1_temp", "85000\n");
+        sys.file("class/thermal/thermal_zone1/trip_point_2_type", "critical\n");
+        sys.file("class/thermal/thermal_zone1/trip_point_2_temp", "100000\n");
+        let sensors = sys.refresh();
+
+        assert_eq!(sensors.temperatures.len(), 2);
+        let zone = &sensors.temperatures[1];
+        assert_eq!(zone.label, "x86_pkg_temp");
+        assert_eq!(zone.current, 62.0);
+        // the lowest of the hot and passive trip points
+        assert_eq!(zone.high, Some(85.0));
+        assert_eq!(zone.critical, Some(100.0));
+    }
+
+    #[test]
+    fn missing_root_has_no_sensors() {
+        let sys = Fixture::new("missing");
+        let sensors = sys.refresh();
+        assert!(sensors.temperatures.is_empty());
+        assert!(sensors.fans.is_empty());
+    }
+
+    #[test]
+    fn temperature_levels() {
+        let t = |current, high, critical| Temperature {
+            label: String::new(),
+            current,
+            high,
+            critical,
+        };
+        assert_eq!(t(50.0, None, None).level(), TemperatureLevel::Normal);
+        assert_eq!(t(70.0, None, None).level(), TemperatureLevel::Warm);
+        assert_eq!(t(80.0, None, None).level(), TemperatureLevel::High);
+        assert_eq!(t(60.0, Some(60.0), Some(70.0)).level(), TemperatureLevel::High);
+        assert_eq!(t(70.0, Some(60.0), Some(70.0)).level(), TemperatureLevel::Critical);
+    }
+}
@@ -0,0 +1,352 @@
+use crate::metrics::mprocess::{renice, send_signal, MProcess, ProcessSignal};
+use crate::metrics::CPUTimeApp;
+use crate::remote::protocol::{
+    check_handshake, encode_snapshot, handshake, MAX_REQUEST_LENGTH, PROTOCOL_VERSION,
+};
+use crate::remote::{read_token, Address, Stream};
+use crate::renderer::confirm::SignalGuard;
+use futures::executor::block_on;
+use signal_hook::consts::signal::{SIGINT, SIGTERM};
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, ToSocketAddrs};
+use std::os::unix::fs::OpenOptionsExt;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+/// The latest update: encoded for SNAPSHOT, and the processes to check signals against.
+struct Update {
+    records: Vec<String>,
+    processes: HashMap<i32, MProcess>,
+}
+
+type Snapshot = Arc<Mutex<Update>>;
+
+/// Clients served at once, the ones over it are turned away.
+const MAX_CLIENTS: usize = 16;
+/// How long a client has to send the handshake and its token.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+/// How long a client may go between requests, well over any sensible refresh rate.
+const IDLE_TIMEOUT: Duration = Duration::from_secs(600);
+/// How long a client may take to read a reply.
+const WRITE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// What clients may do besides reading snapshots.
+struct Control {
+    token: String,
+    /// SIGNAL and NICE, off unless `--agent-allow-control`.
+    allowed: bool,
+    guard: SignalGuard,
+}
+
+enum Listener {
+    Unix(UnixListener),
+    Tcp(TcpListener),
+}
+
+impl Listener {
+    fn bind(address: &Address) -> io::Result<Listener> {
+        match address {
+            Address::Unix(path) => {
+                // a socket left behind by an agent that didn't shut down cleanly
+                if path.exists() && UnixStream::connect(path).is_err() {
+                    fs::remove_file(path)?;
+                }
+                UnixListener::bind(path).map(Listener::Unix)
+            }
+            Address::Tcp(addr) => TcpListener::bind(addr).map(Listener::Tcp),
+        }
+    }
+
+    fn accept(&self) -> io::Result<Stream> {
+        match self {
+            Listener::Unix(l) => l.accept().map(|(s, _)| Stream::Unix(s)),
+            Listener::Tcp(l) => l.accept().map(|(s, _)| Stream::Tcp(s)),
+        }
+    }
+}
+
+/// Why the agent won't signal or renice `pid`, if it won't. Nobody is there to confirm, so
+/// processes protected either way are refused.
+fn refusal(pid: i32, snapshot: &Snapshot, control: &Control) -> Option<String> {
+    let update = snapshot.lock().expect("Snapshot lock poisoned");
+    match update.processes.get(&pid) {
+        Some(p) => control
+            .guard
+            .check(p, &update.processes, true)
+            .map(|(_, reason)| format!("Refused by the agent: {}", reason)),
+        None => Some(format!("PID {} isn't in the agent's latest update", pid)),
+    }
+}
+
+fn handle_request(line: &str, snapshot: &Snapshot, control: &Control) -> Vec<String> {
+    let mut words = line.split_whitespace();
+    let request = (words.next(), words.next(), words.next());
+    // SIGNAL and NICE may name the start time of the process they're meant for
+    let start_time = words.next().map(str::parse::<u64>);
+    if matches!(request.0, Some("SIGNAL") | Some("NICE")) && !control.allowed {
+        return vec![String::from(
+            "ERR Control is disabled, start the agent with --agent-allow-control",
+        )];
+    }
+    match request {
+        (Some("SNAPSHOT"), None, None) => {
+            let update = snapshot.lock().expect("Snapshot lock poisoned");
+            let mut reply = Vec::with_capacity(update.records.len() + 1);
+            reply.push(format!("OK {}", update.records.len()));
+            reply.extend(update.records.iter().cloned());
+            reply
+        }
+        (Some("SIGNAL"), Some(pid), Some(name)) => {
+            match (
+                pid.parse::<i32>(),
+                ProcessSignal::from_name(name),
+                start_time.transpose(),
+            ) {
+                (Ok(pid), Some(signal), Ok(start_time)) => {
+                    // resuming a stopped process does no harm
+                    let refused = match signal {
+                        ProcessSignal::Resume => None,
+                        _ => refusal(pid, snapshot, control),
+                    };
+                    match refused {
+                        Some(reason) => vec![format!("ERR {}", reason)],
+                        None => match block_on(send_signal(pid, signal, start_time)) {
+                            Ok(_) => vec![String::from("OK")],
+                            Err(e) => vec![format!("ERR {}", e)],
+                        },
+                    }
+                }
+                _ => vec![format!("ERR Invalid request: {}", line)],
+            }
+        }
+        (Some("NICE"), Some(pid), Some(nice)) => {
+            match (pid.parse::<i32>(), nice.parse::<i32>(), start_time.transpose()) {
+                (Ok(pid), Ok(nice), Ok(start_time)) => match refusal(pid, snapshot, control) {
+                    Some(reason) => vec![format!("ERR {}", reason)],
+                    None => match renice(pid, nice, start_time) {
+                        Ok(_) => vec![String::from("OK")],
+                        Err(e) => vec![format!("ERR {}", e)],
+                    },
+                },
+                _ => vec![format!("ERR Invalid request: {}", line)],
+            }
+        }
+        _ => vec![format!("ERR Unknown request: {}", line)],
+    }
+}
+
+/// Compares every byte, so the time taken doesn't tell how much of a guess was right.
+fn same_token(a: &str, b: &str) -> bool {
+    a.len() == b.len() && a.bytes().zip(b.bytes()).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+/// The next line without its newline, None once the client hung up. A line longer than
+/// `MAX_REQUEST_LENGTH` is an error rather than buffered whole.
+fn read_request(reader: &mut impl BufRead) -> io::Result<Option<String>> {
+    let mut line = String::new();
+    reader.take(MAX_REQUEST_LENGTH).read_line(&mut line)?;
+    if line.is_empty() {
+        return Ok(None);
+    }
+    if !line.ends_with('\n') && line.len() as u64 == MAX_REQUEST_LENGTH {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "request too long"));
+    }
+    Ok(Some(line.trim_end_matches(&['\r', '\n'][..]).to_string()))
+}
+
+fn serve(stream: Stream, snapshot: Snapshot, control: Arc<Control>) -> io::Result<()> {
+    stream.set_read_timeout(Some(HANDSHAKE_TIMEOUT))?;
+    stream.set_write_timeout(Some(WRITE_TIMEOUT))?;
+    let mut writer = stream.try_clone()?;
+    let mut reader = BufReader::new(stream);
+
+    let hello = match read_request(&mut reader)? {
+        Some(line) => line,
+        None => return Ok(()),
+    };
+    writeln!(writer, "{}", handshake())?;
+    if check_handshake(&hello).is_err() {
+        // the client reports the mismatch, nothing more to talk about
+        return Ok(());
+    }
+    let auth = match read_request(&mut reader)? {
+        Some(line) => line,
+        None => return Ok(()),
+    };
+    match auth.trim().strip_prefix("AUTH ") {
+        Some(token) if same_token(token, &control.token) => writeln!(writer, "OK")?,
+        _ => {
+            writeln!(writer, "ERR Wrong token")?;
+            return Ok(());
+        }
+    }
+
+    reader.get_ref().set_read_timeout(Some(IDLE_TIMEOUT))?;
+    while let Some(line) = read_request(&mut reader)? {
+        for reply in handle_request(line.trim(), &snapshot, &control) {
+            writer.write_all(reply.as_bytes())?;
+            writer.write_all(b"\n")?;
+        }
+        writer.flush()?;
+    }
+    Ok(())
+}
+
+/// The token in `path`, or a new random one written there readable only by us.
+fn load_or_create_token(path: &Path) -> Result<String, String> {
+    if path.exists() {
+        return read_token(path);
+    }
+    let mut random = [0u8; 16];
+    File::open("/dev/urandom")
+        .and_then(|mut f| f.read_exact(&mut random))
+        .map_err(|e| format!("Couldn't generate a token: {}", e))?;
+    let token: String = random.iter().map(|b| format!("{:02x}", b)).collect();
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| format!("Couldn't create {}: {}", dir.display(), e))?;
+    }
+    OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o600)
+        .open(path)
+        .and_then(|mut f| writeln!(f, "{}", token))
+        .map_err(|e| format!("Couldn't write token to {}: {}", path.display(), e))?;
+    Ok(token)
+}
+
+fn is_loopback(address: &Address) -> bool {
+    match address {
+        Address::Unix(_) => true,
+        Address::Tcp(addr) => addr
+            .to_socket_addrs()
+            .map(|mut addrs| addrs.all(|a: SocketAddr| a.ip().is_loopback()))
+            .unwrap_or(false),
+    }
+}
+
+/// Collect like the TUI does but without a terminal, serving every tick's data to clients
+/// connecting to `address` with the token in `token_file` until we get SIGINT or SIGTERM.
+pub async fn run_agent(
+    address: &str,
+    rate: u64,
+    sysfs_root: PathBuf,
+    token_file: &Path,
+    allow_control: bool,
+) -> Result<(), String> {
+    let control = Arc::new(Control {
+        token: load_or_create_token(token_file)?,
+        allowed: allow_control,
+        guard: SignalGuard::load(false, Duration::default())
+            .map_err(|e| format!("Invalid protected processes in {}", e))?,
+    });
+    let addr = Address::parse(address);
+    let listener = Listener::bind(&addr).map_err(|e| format!("Couldn't listen on {}: {}", address, e))?;
+
+    let terminate = Arc::new(AtomicBool::new(false));
+    for sig in &[SIGINT, SIGTERM] {
+        signal_hook::flag::register(*sig, Arc::clone(&terminate))
+            .map_err(|e| format!("Couldn't install signal handler: {}", e))?;
+    }
+
+    let tick = Duration::from_millis(rate);
+    let mut app = CPUTimeApp::new(tick, sysfs_root, None);
+    app.init().await;
+    app.update(false).await;
+    let snapshot: Snapshot = Arc::new(Mutex::new(Update {
+        records: encode_snapshot(&app),
+        processes: app.process_map.clone(),
+    }));
+
+    {
+        let snapshot = Arc::clone(&snapshot);
+        let clients = Arc::new(AtomicUsize::new(0));
+        thread::spawn(move || loop {
+            match listener.accept() {
+                Ok(mut stream) => {
+                    if clients.fetch_add(1, Ordering::SeqCst) >= MAX_CLIENTS {
+                        clients.fetch_sub(1, Ordering::SeqCst);
+                        let _ = stream.set_write_timeout(Some(WRITE_TIMEOUT));
+                        let _ = writeln!(stream, "ERR Too many clients");
+                        continue;
+                    }
+                    let snapshot = Arc::clone(&snapshot);
+                    let control = Arc::clone(&control);
+                    let clients = Arc::clone(&clients);
+                    thread::spawn(move || {
+                        let _ = serve(stream, snapshot, control);
+                        clients.fetch_sub(1, Ordering::SeqCst);
+                    });
+                }
+                Err(e) => eprintln!("Couldn't accept connection: {}", e),
+            }
+        });
+    }
+    println!(
+        "El-Modeer agent (protocol {}) listening on {}, clients need the token in {}",
+        PROTOCOL_VERSION,
+        address,
+        token_file.display()
+    );
+    if !is_loopback(&addr) {
+        eprintln!("Listening beyond this host, the token travels unencrypted. Prefer an SSH tunnel.");
+    }
+
+    while !terminate.load(Ordering::Relaxed) {
+        thread::sleep(tick);
+        app.update(false).await;
+        let update = Update {
+            records: encode_snapshot(&app),
+            processes: app.process_map.clone(),
+        };
+        *snapshot.lock().expect("Snapshot lock poisoned") = update;
+    }
+
+    if let Address::Unix(path) = addr {
+        let _ = fs::remove_file(path);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tokens() {
+        assert!(same_token("0f3a", "0f3a"));
+        assert!(!same_token("0f3a", "0f3b"));
+        assert!(!same_token("0f3a", "0f3"));
+        assert!(!same_token("", "0f3a"));
+    }
+
+    #[test]
+    fn loopback() {
+        assert!(is_loopback(&Address::parse("7070")));
+        assert!(is_loopback(&Address::parse("unix:/run/el-modeer.sock")));
+        assert!(is_loopback(&Address::parse("[::1]:7070")));
+        assert!(!is_loopback(&Address::parse("0.0.0.0:7070")));
+    }
+
+    #[test]
+    fn requests() {
+        let mut reader = io::Cursor::new("EL-MODEER 2\r\nSNAPSHOT\nNICE 1 5");
+        assert_eq!(read_request(&mut reader).unwrap().as_deref(), Some("EL-MODEER 2"));
+        assert_eq!(read_request(&mut reader).unwrap().as_deref(), Some("SNAPSHOT"));
+        assert_eq!(read_request(&mut reader).unwrap().as_deref(), Some("NICE 1 5"));
+        assert_eq!(read_request(&mut reader).unwrap(), None);
+
+        let fits = "A".repeat(MAX_REQUEST_LENGTH as usize - 1) + "\n";
+        assert!(read_request(&mut io::Cursor::new(fits)).unwrap().is_some());
+        let endless = "A".repeat(MAX_REQUEST_LENGTH as usize * 4);
+        let mut reader = io::Cursor::new(endless);
+        assert!(read_request(&mut reader).is_err());
+        assert_eq!(reader.position(), MAX_REQUEST_LENGTH);
+    }
+}
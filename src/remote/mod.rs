@@ -0,0 +1,236 @@
+pub mod agent;
+pub mod protocol;
+
+use crate::metrics::mprocess::ProcessSignal;
+use crate::remote::protocol::{check_handshake, handshake, PROTOCOL_VERSION};
+use crate::util::config_dir;
+use std::fs;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::os::unix::net::UnixStream;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Where an agent listens: `unix:/path` (or anything containing a '/') is a Unix domain
+/// socket, everything else is a TCP `host:port`. A port alone means the loopback interface.
+#[derive(Clone, Debug)]
+pub enum Address {
+    Unix(PathBuf),
+    Tcp(String),
+}
+
+impl Address {
+    pub fn parse(addr: &str) -> Address {
+        if let Some(path) = addr.strip_prefix("unix:") {
+            Address::Unix(PathBuf::from(path))
+        } else if addr.contains('/') {
+            Address::Unix(PathBuf::from(addr))
+        } else if addr.trim_start_matches(':').parse::<u16>().is_ok() {
+            Address::Tcp(format!("127.0.0.1:{}", addr.trim_start_matches(':')))
+        } else {
+            Address::Tcp(addr.to_string())
+        }
+    }
+}
+
+pub enum Stream {
+    Unix(UnixStream),
+    Tcp(TcpStream),
+}
+
+/// How long connecting to an unreachable agent holds up the TUI.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(2);
+
+impl Stream {
+    pub fn connect(address: &Address) -> io::Result<Stream> {
+        match address {
+            Address::Unix(path) => UnixStream::connect(path).map(Stream::Unix),
+            Address::Tcp(addr) => {
+                let mut last_error = io::Error::new(io::ErrorKind::NotFound, "no address to connect to");
+                for addr in addr.to_socket_addrs()? {
+                    match TcpStream::connect_timeout(&addr, CONNECT_TIMEOUT) {
+                        Ok(stream) => return Ok(Stream::Tcp(stream)),
+                        Err(e) => last_error = e,
+                    }
+                }
+                Err(last_error)
+            }
+        }
+    }
+
+    pub fn try_clone(&self) -> io::Result<Stream> {
+        match self {
+            Stream::Unix(s) => s.try_clone().map(Stream::Unix),
+            Stream::Tcp(s) => s.try_clone().map(Stream::Tcp),
+        }
+    }
+
+    pub fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        match self {
+            Stream::Unix(s) => s.set_read_timeout(timeout),
+            Stream::Tcp(s) => s.set_read_timeout(timeout),
+        }
+    }
+
+    pub fn set_write_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        match self {
+            Stream::Unix(s) => s.set_write_timeout(timeout),
+            Stream::Tcp(s) => s.set_write_timeout(timeout),
+        }
+    }
+}
+
+impl Read for Stream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Stream::Unix(s) => s.read(buf),
+            Stream::Tcp(s) => s.read(buf),
+        }
+    }
+}
+
+impl Write for Stream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Stream::Unix(s) => s.write(buf),
+            Stream::Tcp(s) => s.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Stream::Unix(s) => s.flush(),
+            Stream::Tcp(s) => s.flush(),
+        }
+    }
+}
+
+fn protocol_error(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+/// The token clients hand the agent, unless `--token-file` says otherwise.
+pub fn default_token_file() -> PathBuf {
+    config_dir().join("agent-token")
+}
+
+pub fn read_token(path: &Path) -> Result<String, String> {
+    let token = fs::read_to_string(path).map_err(|e| format!("Couldn't read token from {}: {}", path.display(), e))?;
+    let token = token.trim();
+    if token.is_empty() || token.contains(char::is_whitespace) {
+        return Err(format!("{} doesn't hold a token", path.display()));
+    }
+    Ok(token.to_string())
+}
+
+/// Connection of the TUI to an agent started with `--agent`.
+pub struct RemoteClient {
+    pub address: String,
+    token: String,
+    reader: BufReader<Stream>,
+    writer: Stream,
+}
+
+impl RemoteClient {
+    pub fn connect(address: &str, token: &str) -> io::Result<RemoteClient> {
+        let stream = Stream::connect(&Address::parse(address))?;
+        stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+        let mut client = RemoteClient {
+            address: address.to_string(),
+            token: token.to_string(),
+            reader: BufReader::new(stream.try_clone()?),
+            writer: stream,
+        };
+        let reply = client.request(&handshake())?;
+        // e.g. an agent with too many clients already
+        if let Some(reason) = reply.strip_prefix("ERR ") {
+            return Err(protocol_error(reason.to_string()));
+        }
+        check_handshake(&reply).map_err(|v| {
+            protocol_error(format!(
+                "agent speaks protocol version {}, we speak {}",
+                v, PROTOCOL_VERSION
+            ))
+        })?;
+        client.request_ok(&format!("AUTH {}", token))?;
+        Ok(client)
+    }
+
+    /// A new connection to the same agent, e.g. after it restarted.
+    pub fn reconnect(&self) -> io::Result<RemoteClient> {
+        RemoteClient::connect(&self.address, &self.token)
+    }
+
+    fn read_line(&mut self) -> io::Result<String> {
+        let mut line = String::new();
+        if self.reader.read_line(&mut line)? == 0 {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "agent closed the connection"));
+        }
+        Ok(line.trim_end_matches('\n').to_string())
+    }
+
+    fn request(&mut self, request: &str) -> io::Result<String> {
+        self.writer.write_all(request.as_bytes())?;
+        self.writer.write_all(b"\n")?;
+        self.writer.flush()?;
+        self.read_line()
+    }
+
+    /// Expect an "OK <rest>" reply and return the rest.
+    fn request_ok(&mut self, request: &str) -> io::Result<String> {
+        let reply = self.request(request)?;
+        match reply.strip_prefix("OK") {
+            Some(rest) => Ok(rest.trim_start().to_string()),
+            None => Err(protocol_error(reply.trim_start_matches("ERR ").to_string())),
+        }
+    }
+
+    pub fn snapshot(&mut self) -> io::Result<Vec<String>> {
+        let count = self
+            .request_ok("SNAPSHOT")?
+            .parse::<usize>()
+            .map_err(|_| protocol_error(String::from("bad snapshot header")))?;
+        (0..count).map(|_| self.read_line()).collect()
+    }
+
+    /// The agent checks `start_time` against the process it finds with the PID.
+    pub fn signal(
+        &mut self,
+        pid: i32,
+        signal: ProcessSignal,
+        start_time: Option<u64>,
+    ) -> io::Result<()> {
+        let request = match start_time {
+            Some(t) => format!("SIGNAL {} {} {}", pid, signal.as_str(), t),
+            None => format!("SIGNAL {} {}", pid, signal.as_str()),
+        };
+        self.request_ok(&request).map(|_| ())
+    }
+
+    /// Checked against `start_time` like `signal`.
+    pub fn renice(&mut self, pid: i32, nice: i32, start_time: Option<u64>) -> io::Result<()> {
+        let request = match start_time {
+            Some(t) => format!("NICE {} {} {}", pid, nice, t),
+            None => format!("NICE {} {}", pid, nice),
+        };
+        self.request_ok(&request).map(|_| ())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn addresses() {
+        let tcp = |addr| match Address::parse(addr) {
+            Address::Tcp(addr) => Some(addr),
+            Address::Unix(_) => None,
+        };
+        assert_eq!(tcp("7070").as_deref(), Some("127.0.0.1:7070"));
+        assert_eq!(tcp(":7070").as_deref(), Some("127.0.0.1:7070"));
+        assert_eq!(tcp("myhost:7070").as_deref(), Some("myhost:7070"));
+        assert_eq!(tcp("/run/el-modeer.sock"), None);
+        assert_eq!(tcp("unix:el-modeer.sock"), None);
+    }
+}
@@ -0,0 +1,372 @@
+//! Line based protocol between an agent and a TUI started with `--connect`.
+//!
+//! The client opens with `EL-MODEER <version>` and the agent answers with its own version.
+//! The client then sends `AUTH <token>`, and the agent hangs up unless the token is its own.
+//! Requests are single lines (`SNAPSHOT`,
+//! `SIGNAL <pid> <kill|terminate|suspend|resume> [<start time>]`,
+//! `NICE <pid> <value> [<start time>]`), replies start with `OK` or `ERR <reason>`. The
+//! agent leaves a process alone when the start time it's given doesn't match, the PID having
+//! been reused. The agent hangs up on a request longer than `MAX_REQUEST_LENGTH`. A snapshot reply is
+//! `OK <n>` followed by n tab separated records, the first field telling the record type.
+//! Readers ignore record types and trailing fields they don't know so that fields can be
+//! added without bumping the version.
+
+use crate::metrics::mprocess::{
+    status_from_single_char, IoClass, IoPriority, MProcess, ProcessStatusExt, SchedPolicy,
+};
+use crate::metrics::sensors::{Fan, Temperature};
+use crate::metrics::CPUTimeApp;
+use chrono::Local;
+use std::collections::HashMap;
+use std::time::{Duration, SystemTime};
+
+pub const PROTOCOL_VERSION: u32 = 2;
+const MAGIC: &str = "EL-MODEER";
+/// In bytes, the newline included. Far more than the handshake, AUTH or any request needs.
+pub const MAX_REQUEST_LENGTH: u64 = 1024;
+
+pub fn handshake() -> String {
+    format!("{} {}", MAGIC, PROTOCOL_VERSION)
+}
+
+/// Ok if the peer's handshake line matches our version, otherwise the version it speaks.
+pub fn check_handshake(line: &str) -> Result<(), String> {
+    match line.split_once(' ') {
+        Some((MAGIC, v)) if v.trim() == PROTOCOL_VERSION.to_string() => Ok(()),
+        Some((MAGIC, v)) => Err(v.trim().to_string()),
+        _ => Err(String::from("unknown")),
+    }
+}
+
+fn escape(field: &str) -> String {
+    field
+        .replace('\\', "\\\\")
+        .replace('\t', "\\t")
+        .replace('\n', "\\n")
+}
+
+fn unescape(field: &str) -> String {
+    let mut out = String::with_capacity(field.len());
+    let mut chars = field.chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            match chars.next() {
+                Some('t') => out.push('\t'),
+                Some('n') => out.push('\n'),
+                Some(other) => out.push(other),
+                None => {}
+            }
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+fn record(fields: &[String]) -> String {
+    fields.iter().map(|f| escape(f)).collect::<Vec<String>>().join("\t")
+}
+
+fn opt<T: ToString>(value: Option<T>) -> String {
+    value.map_or(String::new(), |v| v.to_string())
+}
+
+fn parse<T: std::str::FromStr + Default>(field: Option<&String>) -> T {
+    field.and_then(|f| f.parse::<T>().ok()).unwrap_or_default()
+}
+
+fn parse_opt<T: std::str::FromStr>(field: Option<&String>) -> Option<T> {
+    field.and_then(|f| f.parse::<T>().ok())
+}
+
+fn encode_process(p: &MProcess) -> String {
+    record(&[
+        String::from("P"),
+        p.pid.to_string(),
+        p.ppid.to_string(),
+        p.uid.to_string(),
+        p.user_name.clone(),
+        p.tty.clone(),
+        p.memory.to_string(),
+        p.cpu_usage.to_string(),
+        p.cum_cpu_usage.to_string(),
+        // arguments are separated by the ASCII unit separator
+        p.command.join("\u{1f}"),
+        p.exe.clone(),
+        p.status.to_single_char().to_string(),
+        p.name.clone(),
+        p.priority.to_string(),
+        p.nice.to_string(),
+        p.virtual_memory.to_string(),
+        p.threads_total.to_string(),
+        p.read_bytes.to_string(),
+        p.write_bytes.to_string(),
+        p.prev_read_bytes.to_string(),
+        p.prev_write_bytes.to_string(),
+        p.start_time.to_string(),
+        p.cpu_time.to_string(),
+        opt(p.last_cpu),
+        opt(p.sched_policy.map(SchedPolicy::to_raw)),
+        p.rt_priority.to_string(),
+        opt(p.io_priority.map(|io| io.class as i32)),
+        opt(p.io_priority.map(|io| io.level)),
+        p.pgid.to_string(),
+        p.sid.to_string(),
+        p.minor_faults.to_string(),
+        p.major_faults.to_string(),
+        p.voluntary_switches.to_string(),
+        p.involuntary_switches.to_string(),
+        opt(p.oom_score),
+        p.wchan.clone(),
+        p.cwd.clone(),
+    ])
+}
+
+/// A process from the fields of a `P` record, also handy to build processes by hand.
+pub fn decode_process(f: &[String]) -> Option<MProcess> {
+    if f.len() < 28 {
+        return None;
+    }
+    let io_priority = match (parse_opt::<i32>(f.get(26)), parse_opt::<i32>(f.get(27))) {
+        (Some(class), Some(level)) => Some(IoPriority {
+            class: IoClass::from_raw(class),
+            level,
+        }),
+        _ => None,
+    };
+    let command: Vec<String> = if f[9].is_empty() {
+        vec![]
+    } else {
+        f[9].split('\u{1f}').map(String::from).collect()
+    };
+    Some(MProcess {
+        pid: f[1].parse().ok()?,
+        ppid: parse(f.get(2)),
+        uid: parse(f.get(3)),
+        user_name: f[4].clone(),
+        tty: f[5].clone(),
+        memory: parse(f.get(6)),
+        peak_memory: parse(f.get(6)),
+        prev_memory: parse(f.get(6)),
+        cpu_usage: parse(f.get(7)),
+        prev_cpu_usage: parse(f.get(7)),
+        cum_cpu_usage: parse(f.get(8)),
+        command,
+        exe: f[10].clone(),
+        status: status_from_single_char(&f[11]),
+        name: f[12].clone(),
+        priority: parse(f.get(13)),
+        nice: parse(f.get(14)),
+        virtual_memory: parse(f.get(15)),
+        threads_total: parse(f.get(16)),
+        // missing from older agents
+        pgid: parse(f.get(28)),
+        sid: parse(f.get(29)),
+        minor_faults: parse(f.get(30)),
+        major_faults: parse(f.get(31)),
+        voluntary_switches: parse(f.get(32)),
+        involuntary_switches: parse(f.get(33)),
+        oom_score: parse_opt(f.get(34)),
+        wchan: f.get(35).cloned().unwrap_or_default(),
+        cwd: f.get(36).cloned().unwrap_or_default(),
+        read_bytes: parse(f.get(17)),
+        write_bytes: parse(f.get(18)),
+        prev_read_bytes: parse(f.get(19)),
+        prev_write_bytes: parse(f.get(20)),
+        last_updated: SystemTime::now(),
+        end_time: None,
+        start_time: parse(f.get(21)),
+        cpu_time: parse(f.get(22)),
+        gpu_usage: 0,
+        fb_utilization: 0,
+        enc_utilization: 0,
+        dec_utilization: 0,
+        sm_utilization: 0,
+        io_delay: Duration::from_nanos(0),
+        swap_delay: Duration::from_nanos(0),
+        prev_io_delay: Duration::from_nanos(0),
+        prev_swap_delay: Duration::from_nanos(0),
+        et: Local::now(),
+        last_cpu: parse_opt(f.get(23)),
+        affinity: None,
+        sched_policy: parse_opt::<i32>(f.get(24)).and_then(SchedPolicy::from_raw),
+        rt_priority: parse(f.get(25)),
+        io_priority,
+        limits: None,
+        open_fds: None,
+    })
+}
+
+/// Serialize everything the TUI renders into snapshot records.
+pub fn encode_snapshot(app: &CPUTimeApp) -> Vec<String> {
+    let system: [(&str, String); 19] = [
+        ("cpu_utilization", app.cpu_utilization.to_string()),
+        ("mem_utilization", app.mem_utilization.to_string()),
+        ("mem_total", app.mem_total.to_string()),
+        ("swap_utilization", app.swap_utilization.to_string()),
+        ("swap_total", app.swap_total.to_string()),
+        ("total_processes", app.total_processes.to_string()),
+        ("running_processes", app.running_processes.to_string()),
+        ("sleeping_processes", app.sleeping_processes.to_string()),
+        ("stopped_processes", app.stopped_processes.to_string()),
+        ("zombie_processes", app.zombie_processes.to_string()),
+        ("threads_total", app.threads_total.to_string()),
+        ("frequency", app.frequency.to_string()),
+        ("osname", app.osname.clone()),
+        ("release", app.release.clone()),
+        ("version", app.version.clone()),
+        ("arch", app.arch.clone()),
+        ("hostname", app.hostname.clone()),
+        ("processor_name", app.processor_name.clone()),
+        ("uptime", app.uptime.as_secs().to_string()),
+    ];
+    let mut records: Vec<String> = system
+        .iter()
+        .map(|(k, v)| record(&[String::from("S"), k.to_string(), v.clone()]))
+        .collect();
+    records.extend(
+        app.cpus
+            .iter()
+            .map(|(label, usage)| record(&[String::from("C"), label.clone(), usage.to_string()])),
+    );
+    records.extend(app.sensors.temperatures.iter().map(|t| {
+        record(&[
+            String::from("T"),
+            t.label.clone(),
+            t.current.to_string(),
+            opt(t.high),
+            opt(t.critical),
+        ])
+    }));
+    records.extend(
+        app.sensors
+            .fans
+            .iter()
+            .map(|fan| record(&[String::from("F"), fan.label.clone(), fan.rpm.to_string()])),
+    );
+    records.extend(app.process_map.values().map(encode_process));
+    records
+}
+
+/// Replace the collected data of `app` with a snapshot received from an agent.
+pub fn apply_snapshot(app: &mut CPUTimeApp, records: &[String]) {
+    let mut process_map: HashMap<i32, MProcess> = HashMap::with_capacity(records.len());
+    app.cpus.clear();
+    app.sensors.temperatures.clear();
+    app.sensors.fans.clear();
+    for line in records {
+        let f: Vec<String> = line.split('\t').map(unescape).collect();
+        match f[0].as_str() {
+            "S" if f.len() >= 3 => {
+                let v = Some(&f[2]);
+                match f[1].as_str() {
+                    "cpu_utilization" => app.cpu_utilization = parse(v),
+                    "mem_utilization" => app.mem_utilization = parse(v),
+                    "mem_total" => app.mem_total = parse(v),
+                    "swap_utilization" => app.swap_utilization = parse(v),
+                    "swap_total" => app.swap_total = parse(v),
+                    "total_processes" => app.total_processes = parse(v),
+                    "running_processes" => app.running_processes = parse(v),
+                    "sleeping_processes" => app.sleeping_processes = parse(v),
+                    "stopped_processes" => app.stopped_processes = parse(v),
+                    "zombie_processes" => app.zombie_processes = parse(v),
+                    "threads_total" => app.threads_total = parse(v),
+                    "frequency" => app.frequency = parse(v),
+                    "osname" => app.osname = f[2].clone(),
+                    "release" => app.release = f[2].clone(),
+                    "version" => app.version = f[2].clone(),
+                    "arch" => app.arch = f[2].clone(),
+                    "hostname" => app.hostname = f[2].clone(),
+                    "processor_name" => app.processor_name = f[2].clone(),
+                    "uptime" => app.uptime = Duration::from_secs(parse(v)),
+                    _ => {}
+                }
+            }
+            "C" if f.len() >= 3 => app.cpus.push((f[1].clone(), parse(f.get(2)))),
+            "T" if f.len() >= 5 => app.sensors.temperatures.push(Temperature {
+                label: f[1].clone(),
+                current: parse(f.get(2)),
+                high: parse_opt(f.get(3)),
+                critical: parse_opt(f.get(4)),
+            }),
+            "F" if f.len() >= 3 => app.sensors.fans.push(Fan {
+                label: f[1].clone(),
+                rpm: parse(f.get(2)),
+            }),
+            "P" => {
+                if let Some(p) = decode_process(&f) {
+                    process_map.insert(p.pid, p);
+                }
+            }
+            _ => {}
+        }
+    }
+    app.process_map = process_map;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fields(line: &str) -> Vec<String> {
+        line.split('\t').map(unescape).collect()
+    }
+
+    fn process_record() -> Vec<String> {
+        [
+            "P", "42", "1", "1000", "alice", "pts/1", "2048", "12.5", "30.25",
+            "vim\u{1f}notes\tand tabs.txt", "/usr/bin/vim", "S", "vim", "20", "5", "8192", "3",
+            "100", "200", "50", "60", "1700000000", "17", "2", "0", "0", "2", "4", "42", "40",
+            "11", "1", "9", "8", "-3", "do_select", "/home/alice\nwith a newline",
+        ]
+        .iter()
+        .map(|f| f.to_string())
+        .collect()
+    }
+
+    #[test]
+    fn escapes_round_trip() {
+        for field in ["plain", "tab\there", "new\nline", "back\\slash\\t", ""] {
+            assert_eq!(unescape(&escape(field)), field);
+        }
+        assert_eq!(fields(&record(&[String::from("a\tb"), String::from("c")])), vec!["a\tb", "c"]);
+    }
+
+    #[test]
+    fn process_round_trip() {
+        let p = decode_process(&process_record()).expect("a full record");
+        assert_eq!(p.pid, 42);
+        assert_eq!(p.user_name, "alice");
+        assert_eq!(p.command, vec!["vim", "notes\tand tabs.txt"]);
+        assert_eq!(p.memory, 2048);
+        assert_eq!(p.cpu_usage, 12.5);
+        assert_eq!(p.start_time, 1700000000);
+        assert_eq!(p.sched_policy, Some(SchedPolicy::Other));
+        assert_eq!(p.io_priority.map(|io| (io.class, io.level)), Some((IoClass::BestEffort, 4)));
+        assert_eq!(p.oom_score, Some(-3));
+        assert_eq!(p.cwd, "/home/alice\nwith a newline");
+
+        let line = encode_process(&p);
+        assert!(!line.contains('\n'));
+        let decoded = decode_process(&fields(&line)).expect("an encoded record");
+        assert_eq!(encode_process(&decoded), line);
+    }
+
+    #[test]
+    fn older_records_leave_new_fields_empty() {
+        let mut record = process_record();
+        record.truncate(28);
+        let p = decode_process(&record).expect("an old record");
+        assert_eq!((p.pgid, p.sid, p.oom_score), (0, 0, None));
+        assert_eq!(p.wchan, "");
+        record.truncate(27);
+        assert!(decode_process(&record).is_none());
+    }
+
+    #[test]
+    fn handshakes() {
+        assert_eq!(check_handshake(&handshake()), Ok(()));
+        assert_eq!(check_handshake("EL-MODEER 99"), Err(String::from("99")));
+        assert_eq!(check_handshake("SSH-2.0-OpenSSH"), Err(String::from("unknown")));
+    }
+}
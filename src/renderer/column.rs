@@ -1,13 +1,16 @@
 use tui::layout::{Alignment, Constraint, Direction, Layout, Rect};
-use tui::style::{Color, Modifier, Style};
+use tui::style::{Modifier, Style};
 use tui::text::{Span, Spans};
 use tui::widgets::{Block, Borders, List, ListItem, ListState, Paragraph, Wrap};
 use tui::Frame;
 
+use super::theme::Theme;
 use super::{FromPrimitive, Render, MBackend};
+use crate::metrics::mprocess::ProcfsReads;
+use std::collections::HashMap;
 use std::fmt;
 
-#[derive(FromPrimitive, PartialEq, Copy, Clone, Debug, Ord, PartialOrd, Eq)]
+#[derive(FromPrimitive, PartialEq, Copy, Clone, Debug, Ord, PartialOrd, Eq, Hash)]
 pub enum Column {
     PID = 0,
     PPID = 1,
@@ -22,7 +25,23 @@ pub enum Column {
     VirtualMemory = 10,
     CPUTime = 11,
     StartTime = 12,
-    CMD = 13,
+    LastCPU = 13,
+    SchedPolicy = 14,
+    IOPriority = 15,
+    PGID = 16,
+    SID = 17,
+    Threads = 18,
+    MinorFaults = 19,
+    MajorFaults = 20,
+    VoluntarySwitches = 21,
+    InvoluntarySwitches = 22,
+    OOMScore = 23,
+    WChan = 24,
+    Exe = 25,
+    Cwd = 26,
+    Elapsed = 27,
+    /// Keep last, the number of columns is derived from it.
+    CMD = 28,
 }
 
 impl fmt::Display for Column {
@@ -41,51 +60,213 @@ impl fmt::Display for Column {
             Column::VirtualMemory => " Virtual Memory",
             Column::CPUTime => " CPU Time",
             Column::StartTime => " Start Time",
+            Column::LastCPU => " Last CPU",
+            Column::SchedPolicy => " Scheduling Policy",
+            Column::IOPriority => " I/O Priority",
+            Column::PGID => " Process Group",
+            Column::SID => " Session",
+            Column::Threads => " Threads",
+            Column::MinorFaults => " Minor Page Faults",
+            Column::MajorFaults => " Major Page Faults",
+            Column::VoluntarySwitches => " Voluntary Context Switches",
+            Column::InvoluntarySwitches => " Involuntary Context Switches",
+            Column::OOMScore => " OOM Score",
+            Column::WChan => " Wait Channel",
+            Column::Exe => " Executable Path",
+            Column::Cwd => " Working Directory",
+            Column::Elapsed => " Elapsed Time",
             Column::CMD => " CMD / Command Name",
         };
         write!(f, "{}", name)
     }
 }
 
+impl Column {
+    pub const COUNT: usize = Column::CMD as usize + 1;
+
+    pub fn all() -> impl Iterator<Item = Column> {
+        (0..Column::COUNT as u32)
+            .map(|i| FromPrimitive::from_u32(i).expect("Index not in range for Column enum"))
+    }
+
+    /// What showing or sorting by the column needs read from procfs on every update.
+    pub fn procfs_reads(self) -> ProcfsReads {
+        let mut reads = ProcfsReads::default();
+        match self {
+            Column::PGID
+            | Column::SID
+            | Column::MinorFaults
+            | Column::MajorFaults
+            | Column::LastCPU
+            | Column::SchedPolicy => reads.stat = true,
+            Column::VoluntarySwitches | Column::InvoluntarySwitches => reads.status = true,
+            Column::OOMScore => reads.oom_score = true,
+            Column::WChan => reads.wchan = true,
+            Column::Cwd => reads.cwd = true,
+            Column::IOPriority => reads.io_priority = true,
+            _ => {}
+        }
+        reads
+    }
+
+    /// Name of the column in the saved layout.
+    pub fn key(self) -> &'static str {
+        match self {
+            Column::PID => "pid",
+            Column::PPID => "ppid",
+            Column::User => "user",
+            Column::Priority => "priority",
+            Column::Nice => "nice",
+            Column::Status => "status",
+            Column::TTY => "tty",
+            Column::CPUPercentage => "cpu_percent",
+            Column::MemoryPercentage => "mem_percent",
+            Column::Memory => "mem",
+            Column::VirtualMemory => "virt",
+            Column::CPUTime => "cpu_time",
+            Column::StartTime => "start_time",
+            Column::LastCPU => "last_cpu",
+            Column::SchedPolicy => "sched",
+            Column::IOPriority => "io",
+            Column::PGID => "pgid",
+            Column::SID => "sid",
+            Column::Threads => "threads",
+            Column::MinorFaults => "minor_faults",
+            Column::MajorFaults => "major_faults",
+            Column::VoluntarySwitches => "voluntary_switches",
+            Column::InvoluntarySwitches => "involuntary_switches",
+            Column::OOMScore => "oom_score",
+            Column::WChan => "wchan",
+            Column::Exe => "exe",
+            Column::Cwd => "cwd",
+            Column::Elapsed => "elapsed",
+            Column::CMD => "cmd",
+        }
+    }
+
+    pub fn from_key(key: &str) -> Option<Column> {
+        Column::all().find(|c| c.key() == key)
+    }
+}
+
+/// How wide a column of the process table is drawn. CMD always takes the rest of the line.
+#[derive(PartialEq, Eq, Copy, Clone, Debug, Default)]
+pub enum ColumnWidth {
+    /// Wide enough for the usual values.
+    #[default]
+    Default,
+    /// As wide as the widest value on screen.
+    Auto,
+    Fixed(u16),
+}
+
+impl fmt::Display for ColumnWidth {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ColumnWidth::Default => write!(f, "default"),
+            ColumnWidth::Auto => write!(f, "auto"),
+            ColumnWidth::Fixed(w) => write!(f, "{}", w),
+        }
+    }
+}
+
+/// `KEY [WIDTH]` of a column in a saved layout.
+pub fn parse_column_line(line: &str) -> Option<(Column, ColumnWidth)> {
+    let mut words = line.split_whitespace();
+    let column = words.next().and_then(Column::from_key)?;
+    let width = match words.next() {
+        Some("auto") => ColumnWidth::Auto,
+        Some(w) => w.parse().map_or(ColumnWidth::Default, ColumnWidth::Fixed),
+        None => ColumnWidth::Default,
+    };
+    Some((column, width))
+}
+
+pub fn column_line(column: Column, widths: &HashMap<Column, ColumnWidth>) -> String {
+    format!("{} {}", column.key(), widths.get(&column).copied().unwrap_or_default())
+}
+
 pub struct ColumnMGRList<'a> {
     pub items: Vec<(Column, ListItem<'a>)>,
     pub state: ListState,
+    /// Shown under the key help, e.g. when saving the layout failed.
+    pub message: Option<String>,
+    /// The first item shown, as of the last draw.
+    pub offset: usize,
 }
 
 impl<'a> ColumnMGRList<'a> {
-    pub fn with_cols(cols: Vec<Column>) -> ColumnMGRList<'a> {
+    /// Shown columns first in table order, then the hidden ones.
+    pub fn with_cols(cols: &[Column], widths: &HashMap<Column, ColumnWidth>) -> ColumnMGRList<'a> {
         let mut state = ListState::default();
-        let items: Vec<(Column, ListItem)> = [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13]
+        let hidden = Column::all().filter(|c| !cols.contains(c));
+        let items: Vec<(Column, ListItem)> = cols
             .iter()
-            .map(|i| {
-                let column: Column = FromPrimitive::from_u32(*i as u32)
-                    .expect("Index not in range for Column enum");
+            .copied()
+            .chain(hidden)
+            .map(|column| {
                 let c: String = format!("{}", column);
-                // default is first 6
                 if cols.contains(&column) {
+                    let width = if column == Column::CMD {
+                        String::from("rest")
+                    } else {
+                        widths.get(&column).copied().unwrap_or_default().to_string()
+                    };
                     (
                         column,
                         Span::styled(
-                            format!("*{}", c),
+                            format!("*{:<24}{:>8}", c, width),
                             Style::default().add_modifier(Modifier::BOLD),
                         ),
                     )
                 } else {
                     (column, Span::styled(format!(" {}", c), Style::default()))
-                }      
+                }
             })
             .map(|(c, span)| (c, ListItem::new(span)))
             .collect();
         state.select(Some(0));
-        ColumnMGRList { items, state }
+        ColumnMGRList {
+            items,
+            state,
+            message: None,
+            offset: 0,
+        }
+    }
+
+    pub fn select_column(&mut self, column: Column) {
+        let idx = self.items.iter().position(|(c, _)| *c == column);
+        self.state.select(idx.or(Some(0)));
     }
 
     pub fn selected(&self) -> Option<Column> {
         self.state.selected().map(|s| self.items[s].0)
     }
+
+    /// The item on row `row` of the list, counting from the first one shown.
+    pub fn item_at(&self, row: usize) -> Option<usize> {
+        Some(self.offset + row).filter(|idx| *idx < self.items.len())
+    }
 }
 
-pub fn render_column_mgr(list: &mut ColumnMGRList<'_>, area: Rect, f: &mut Frame<'_, MBackend>) {
+/// Scrolls the way `List` does for one line items, just far enough to show the selection.
+/// `List` keeps its offset to itself, so we work it out alongside for mouse clicks.
+fn scroll_offset(offset: usize, selected: Option<usize>, len: usize, height: usize) -> usize {
+    let offset = offset.min(len.saturating_sub(1));
+    match selected {
+        Some(s) if s < offset => s,
+        Some(s) if height > 0 && s >= offset + height => s + 1 - height,
+        _ => offset,
+    }
+}
+
+/// Returns where the list went, for mouse clicks.
+pub fn render_column_mgr(
+    list: &mut ColumnMGRList<'_>,
+    theme: &Theme,
+    area: Rect,
+    f: &mut Frame<'_, MBackend>,
+) -> Rect {
     let layout = Layout::default()
         .margin(5)
         .direction(Direction::Vertical)
@@ -98,24 +279,61 @@ pub fn render_column_mgr(list: &mut ColumnMGRList<'_>, area: Rect, f: &mut Frame
             .as_ref(),
         )
         .split(area);
-    let header_style = Style::default().fg(Color::Green);
+    let header_style = theme.heading;
     let t = vec![Span::styled("Options", header_style)];
-    let help = vec![Span::styled(
-        "Navigate [↑/↓] Toggle [Space] Return [o]",
+    let mut help = vec![Spans::from(Span::styled(
+        "Navigate [↑/↓] Toggle [Space] Move [[/]] Width [+/-] Auto-fit [a] Default [d] Return [o]",
         header_style,
-    )];
+    ))];
+    if let Some(message) = &list.message {
+        help.push(Spans::from(Span::styled(message.clone(), theme.warn)));
+    }
     Paragraph::new(Spans::from(t))
         .wrap(Wrap { trim: false })
         .alignment(Alignment::Center)
         .render(f, layout[0]);
-    Paragraph::new(Spans::from(help))
+    Paragraph::new(help)
         .wrap(Wrap { trim: false })
         .alignment(Alignment:: Center)
         .render(f, layout[2]);
     let list_items: Vec<ListItem> = list.items.iter().map(|i| i.1.clone()).collect();
+    list.offset = scroll_offset(
+        list.offset,
+        list.state.selected(),
+        list.items.len(),
+        layout[1].height.saturating_sub(2).into(),
+    );
     let list_widget = List::new(list_items)
         .block(Block::default().title("Columns").borders(Borders::ALL))
-        .highlight_style(Style::default().bg(Color::Green))
+        .highlight_style(theme.menu_highlight)
         .highlight_symbol("➡ ");
     f.render_stateful_widget(list_widget, layout[1], &mut list.state);
-}
\ No newline at end of file
+    layout[1]
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn count_covers_every_column() {
+        // a variant added after CMD would be left out of `all()`
+        let from_count: Option<Column> = FromPrimitive::from_usize(Column::COUNT);
+        assert_eq!(from_count, None);
+        let all: Vec<Column> = Column::all().collect();
+        assert_eq!(all.len(), Column::COUNT);
+        assert_eq!(all.last(), Some(&Column::CMD));
+    }
+
+    #[test]
+    fn scrolls_like_list() {
+        // fits, or the selection is already shown
+        assert_eq!(scroll_offset(0, Some(3), 10, 10), 0);
+        assert_eq!(scroll_offset(2, Some(4), 10, 3), 2);
+        // moving down past the bottom row and up past the top one
+        assert_eq!(scroll_offset(0, Some(5), 10, 3), 3);
+        assert_eq!(scroll_offset(3, Some(1), 10, 3), 1);
+        // the list shrank under the offset
+        assert_eq!(scroll_offset(8, None, 4, 3), 3);
+        assert_eq!(scroll_offset(0, Some(2), 10, 0), 0);
+    }
+}
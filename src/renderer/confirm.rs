@@ -0,0 +1,307 @@
+//! Asking before a signal that stops or ends a process, and processes it shouldn't reach by
+//! accident. Besides init and El-Modeer itself, protected processes are listed in
+//! `~/.config/el-modeer/protected`:
+//!
+//! ```text
+//! # refuse: never signal them, confirm: type the process name to go ahead
+//! refuse name postgres
+//! confirm name sshd
+//! confirm user root
+//! confirm pid 4242
+//! ```
+
+use super::prompt::LineEditor;
+use super::theme::Theme;
+use super::{MBackend, Render};
+use crate::metrics::mprocess::{MProcess, ProcessSignal};
+use crate::util::config_dir;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::time::Duration;
+use tui::layout::Rect;
+use tui::text::{Span, Spans};
+use tui::widgets::{Block, Borders, Clear, Paragraph};
+use tui::Frame;
+
+/// Parents followed up from El-Modeer, in case they changed between reads and form a loop.
+const MAX_ANCESTORS: usize = 64;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Protection {
+    /// The process name has to be typed to send the signal.
+    Confirm,
+    Refuse,
+}
+
+#[derive(Clone, Debug)]
+enum Target {
+    Pid(i32),
+    Name(String),
+    User(String),
+}
+
+impl Target {
+    fn matches(&self, p: &MProcess) -> bool {
+        match self {
+            Target::Pid(pid) => p.pid == *pid,
+            Target::Name(name) => p.name == *name,
+            Target::User(user) => p.user_name == *user,
+        }
+    }
+}
+
+impl fmt::Display for Target {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Target::Pid(pid) => write!(f, "protected PID {}", pid),
+            Target::Name(name) => write!(f, "protected name {}", name),
+            Target::User(user) => write!(f, "protected user {}", user),
+        }
+    }
+}
+
+/// Which signals need confirming and which processes are protected.
+pub struct SignalGuard {
+    rules: Vec<(Protection, Target)>,
+    /// Ask before every kill, terminate and suspend, not only for protected processes.
+    pub confirm: bool,
+    /// How long a stop waits after SIGTERM before SIGKILL.
+    pub stop_timeout: Duration,
+}
+
+impl SignalGuard {
+    /// The protected processes from the config directory, if there are any.
+    pub fn load(confirm: bool, stop_timeout: Duration) -> Result<SignalGuard, String> {
+        let path = config_dir().join("protected");
+        let rules = match fs::read_to_string(&path) {
+            Ok(text) => parse_rules(&text).map_err(|e| format!("{}:{}", path.display(), e))?,
+            Err(_) => vec![],
+        };
+        Ok(SignalGuard {
+            rules,
+            confirm,
+            stop_timeout,
+        })
+    }
+
+    /// How `p` is protected and why, refusing wins over confirming. `local` is false when
+    /// `processes` come from an agent, our own PID means nothing on its machine.
+    pub fn check(
+        &self,
+        p: &MProcess,
+        processes: &HashMap<i32, MProcess>,
+        local: bool,
+    ) -> Option<(Protection, String)> {
+        let mut found = vec![];
+        if p.pid == 1 {
+            found.push((Protection::Refuse, String::from("init")));
+        }
+        if local {
+            let own = std::process::id() as i32;
+            if p.pid == own {
+                found.push((Protection::Refuse, String::from("El-Modeer itself")));
+            } else if is_ancestor(processes, p.pid, own) {
+                found.push((Protection::Confirm, String::from("El-Modeer runs under it")));
+            }
+        }
+        for (protection, target) in &self.rules {
+            if target.matches(p) {
+                found.push((*protection, target.to_string()));
+            }
+        }
+        match found.iter().position(|(protection, _)| *protection == Protection::Refuse) {
+            Some(i) => Some(found.swap_remove(i)),
+            None => found.into_iter().next(),
+        }
+    }
+}
+
+/// Whether `ancestor` is the parent, grandparent, ... of `pid`.
+fn is_ancestor(processes: &HashMap<i32, MProcess>, ancestor: i32, pid: i32) -> bool {
+    let mut pid = pid;
+    for _ in 0..MAX_ANCESTORS {
+        match processes.get(&pid) {
+            Some(p) if p.ppid == ancestor => return true,
+            Some(p) if p.ppid > 1 => pid = p.ppid,
+            _ => return false,
+        }
+    }
+    false
+}
+
+/// `confirm|refuse pid|name|user VALUE` lines, errors start with the line number.
+fn parse_rules(text: &str) -> Result<Vec<(Protection, Target)>, String> {
+    let mut rules = vec![];
+    for (n, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let error = |msg: String| format!("{}: {}", n + 1, msg);
+        let (action, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let (kind, value) = match rest.trim().split_once(char::is_whitespace) {
+            Some((kind, value)) => (kind, value.trim()),
+            None => (rest.trim(), ""),
+        };
+        let protection = match action {
+            "confirm" => Protection::Confirm,
+            "refuse" => Protection::Refuse,
+            _ => {
+                return Err(error(format!(
+                    "unknown action '{}', expected confirm or refuse",
+                    action
+                )))
+            }
+        };
+        let target = match kind {
+            "pid" => Target::Pid(
+                value
+                    .parse()
+                    .map_err(|_| error(format!("'{}' is not a PID", value)))?,
+            ),
+            "name" if !value.is_empty() => Target::Name(String::from(value)),
+            "user" if !value.is_empty() => Target::User(String::from(value)),
+            _ => {
+                return Err(error(String::from(
+                    "expected \"pid PID\", \"name NAME\" or \"user USER\"",
+                )))
+            }
+        };
+        rules.push((protection, target));
+    }
+    Ok(rules)
+}
+
+/// A signal waiting for a yes, or for the process name when the process is protected.
+pub struct Confirmation {
+    pub pid: i32,
+    pub name: String,
+    pub user: String,
+    pub signal: ProcessSignal,
+    /// Why the process is protected, with what's typed of its name.
+    pub protected: Option<(String, LineEditor)>,
+    /// The typed name didn't match.
+    pub mismatch: bool,
+    /// SIGKILL follows after this long if SIGTERM doesn't end the process.
+    pub stop: Option<Duration>,
+}
+
+impl Confirmation {
+    pub fn new(pid: i32, p: Option<&MProcess>, signal: ProcessSignal, protected: Option<String>) -> Confirmation {
+        Confirmation {
+            pid,
+            name: p.map_or_else(|| String::from("?"), |p| p.name.clone()),
+            user: p.map_or_else(|| String::from("?"), |p| p.user_name.clone()),
+            signal,
+            protected: protected.map(|reason| (reason, LineEditor::default())),
+            mismatch: false,
+            stop: None,
+        }
+    }
+}
+
+/// The dialog in the middle of `area`.
+pub fn render_confirmation(
+    confirmation: &Confirmation,
+    theme: &Theme,
+    area: Rect,
+    f: &mut Frame<'_, MBackend>,
+) {
+    let c = confirmation;
+    let mut text = vec![
+        Spans::from(format!(
+            "Send {} ({}) to",
+            c.signal.signal_name(),
+            c.signal.as_str()
+        )),
+        Spans::from(vec![Span::raw("  PID   "), Span::styled(c.pid.to_string(), theme.value)]),
+        Spans::from(vec![Span::raw("  Name  "), Span::styled(c.name.as_str(), theme.value)]),
+        Spans::from(vec![Span::raw("  User  "), Span::styled(c.user.as_str(), theme.value)]),
+    ];
+    if let Some(timeout) = c.stop {
+        text.push(Spans::from(format!(
+            "then SIGKILL if it's still running after {}s",
+            timeout.as_secs()
+        )));
+    }
+    text.push(Spans::from(""));
+    match &c.protected {
+        Some((reason, editor)) => {
+            text.push(Spans::from(Span::styled(format!("Protected: {}", reason), theme.warn)));
+            let mut typed = vec![Span::raw("Type its name to go ahead: ")];
+            typed.extend(editor.spans(theme.text));
+            text.push(Spans::from(typed));
+            if c.mismatch {
+                text.push(Spans::from(Span::styled("The name doesn't match.", theme.bad)));
+            }
+            text.push(Spans::from("[Enter] Send  [Esc] Cancel"));
+        }
+        None => text.push(Spans::from("[y] Send  [n] Cancel")),
+    }
+    let action = match c.stop {
+        Some(_) => String::from("Stop"),
+        None => capitalize(c.signal.as_str()),
+    };
+    let width = 52.min(area.width);
+    let height = (text.len() as u16 + 2).min(area.height);
+    let dialog = Rect::new(
+        area.x + (area.width - width) / 2,
+        area.y + (area.height - height) / 2,
+        width,
+        height,
+    );
+    Clear.render(f, dialog);
+    Paragraph::new(text)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(theme.accent)
+                .title(format!("{} {}?", action, c.name)),
+        )
+        .render(f, dialog);
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_rules() {
+        let rules = parse_rules(
+            "# refuse these\n\nrefuse name postgres\n  confirm  user   root  \nconfirm pid 4242\nconfirm name my server\n",
+        )
+        .expect("valid rules");
+        let parsed: Vec<(Protection, String)> = rules
+            .iter()
+            .map(|(protection, target)| (*protection, target.to_string()))
+            .collect();
+        assert_eq!(
+            parsed,
+            vec![
+                (Protection::Refuse, String::from("protected name postgres")),
+                (Protection::Confirm, String::from("protected user root")),
+                (Protection::Confirm, String::from("protected PID 4242")),
+                (Protection::Confirm, String::from("protected name my server")),
+            ]
+        );
+    }
+
+    #[test]
+    fn rule_errors_give_the_line() {
+        let error = |text| parse_rules(text).expect_err("an error");
+        assert_eq!(error("ignore name sshd"), "1: unknown action 'ignore', expected confirm or refuse");
+        assert_eq!(error("confirm name sshd\nrefuse pid init"), "2: 'init' is not a PID");
+        assert!(error("confirm name").starts_with("1: expected"));
+        assert!(error("refuse").starts_with("1: expected"));
+        assert!(error("refuse group wheel").starts_with("1: expected"));
+    }
+}
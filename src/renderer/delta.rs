@@ -0,0 +1,176 @@
+use super::theme::Theme;
+use super::{MBackend, Render};
+use crate::float_to_byte_string;
+use crate::metrics::baseline::{sort_deltas, Baseline, DeltaKind, DeltaSort, ProcessDelta};
+use crate::metrics::{CPUTimeApp, ProcessTableSortOrder};
+use byte_unit::{Byte, ByteUnit};
+use chrono::Local;
+use tui::layout::{Constraint, Rect};
+use tui::style::Style;
+use tui::widgets::{Block, Borders, Cell, Row, Table};
+use tui::Frame;
+
+/// The baseline, and how the delta view against it is sorted and scrolled.
+pub struct DeltaView {
+    pub baseline: Option<Baseline>,
+    /// Drawn in place of the process table.
+    pub shown: bool,
+    pub scroll: usize,
+    pub sort: DeltaSort,
+    pub order: ProcessTableSortOrder,
+}
+
+impl Default for DeltaView {
+    fn default() -> Self {
+        DeltaView {
+            baseline: None,
+            shown: false,
+            scroll: 0,
+            sort: DeltaSort::CpuTime,
+            order: ProcessTableSortOrder::Descending,
+        }
+    }
+}
+
+impl DeltaView {
+    pub fn visible(&self, app: &CPUTimeApp) -> Vec<ProcessDelta> {
+        let mut deltas = match &self.baseline {
+            Some(baseline) => baseline.deltas(&app.process_map),
+            None => vec![],
+        };
+        sort_deltas(&mut deltas, self.sort, self.order);
+        deltas
+    }
+
+    pub fn scroll_up(&mut self, delta: usize) {
+        self.scroll = self.scroll.saturating_sub(delta);
+    }
+
+    pub fn scroll_down(&mut self, delta: usize, app: &CPUTimeApp) {
+        let max = self.visible(app).len().saturating_sub(1);
+        self.scroll = (self.scroll + delta).min(max);
+    }
+}
+
+fn kind_style(kind: DeltaKind, theme: &Theme) -> Style {
+    match kind {
+        DeltaKind::Running => Style::default(),
+        DeltaKind::Appeared => theme.good,
+        DeltaKind::Disappeared => theme.bad,
+    }
+}
+
+fn bytes(b: u64) -> String {
+    float_to_byte_string!(b as f64, ByteUnit::B).replace('B', "")
+}
+
+/// Memory is counted in KiB.
+fn kilobytes(kb: u64) -> String {
+    float_to_byte_string!(kb as f64, ByteUnit::KB).replace('B', "")
+}
+
+/// Shrinking shows as a minus, growing as a plus.
+fn signed(value: i64, format: impl Fn(u64) -> String) -> String {
+    match value {
+        0 => String::from("0"),
+        v if v < 0 => format!("-{}", format(v.unsigned_abs())),
+        v => format!("+{}", format(v as u64)),
+    }
+}
+
+pub fn render_delta(
+    app: &CPUTimeApp,
+    theme: &Theme,
+    view: &DeltaView,
+    area: Rect,
+    f: &mut Frame<'_, MBackend>,
+) {
+    let deltas = view.visible(app);
+    let rows: Vec<Row> = deltas
+        .iter()
+        .skip(view.scroll)
+        .map(|d| {
+            let mut cells = vec![
+                Cell::from(format!("{:>7}", d.pid)),
+                Cell::from(d.kind.as_str()).style(kind_style(d.kind, theme)),
+                Cell::from(d.name.clone()),
+                Cell::from(d.user.clone()),
+            ];
+            match d.change {
+                Some(c) => cells.extend(vec![
+                    Cell::from(format!(
+                        "{:>9}",
+                        format!("{}:{:0>2}:{:0>2}", c.cpu_time / 3600, (c.cpu_time / 60) % 60, c.cpu_time % 60)
+                    )),
+                    Cell::from(format!("{:>9}", signed(c.memory, kilobytes))),
+                    Cell::from(format!("{:>9}", bytes(c.read_bytes))),
+                    Cell::from(format!("{:>9}", bytes(c.write_bytes))),
+                    Cell::from(format!("{:>8}", signed(c.threads, |t| t.to_string()))),
+                ]),
+                None => cells.extend([9, 9, 9, 9, 8].iter().map(|w| Cell::from(format!("{:>w$}", "-", w = w)))),
+            }
+            Row::new(cells)
+        })
+        .collect();
+
+    let mut header = vec![
+        String::from("    PID"),
+        String::from("STATE"),
+        String::from("NAME"),
+        String::from("USER"),
+        String::from(" CPU TIME"),
+        String::from("   MEM +-"),
+        String::from("     READ"),
+        String::from("  WRITTEN"),
+        String::from("  THR +-"),
+    ];
+    let sorted = 4 + DeltaSort::ALL.iter().position(|s| *s == view.sort).unwrap_or(0);
+    let sort_ind = match view.order {
+        ProcessTableSortOrder::Ascending => '↑',
+        ProcessTableSortOrder::Descending => '↓',
+    };
+    header[sorted].remove(0);
+    header[sorted].insert(0, sort_ind);
+
+    let title = match &view.baseline {
+        Some(baseline) => {
+            let since = Local::now() - baseline.taken;
+            let count = |kind| deltas.iter().filter(|d| d.kind == kind).count();
+            format!(
+                "Since {} ({}:{:0>2}:{:0>2} ago), {} new, {} gone. Sort [,/.] Order [;] Scroll [↑/↓] New baseline [B] Leave [D]",
+                baseline.taken.format("%H:%M:%S"),
+                since.num_hours(),
+                since.num_minutes() % 60,
+                since.num_seconds() % 60,
+                count(DeltaKind::Appeared),
+                count(DeltaKind::Disappeared),
+            )
+        }
+        None => String::from("No baseline, [B] to take one"),
+    };
+
+    Table::new(rows)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(theme.accent)
+                .title(title),
+        )
+        .header(Row::new(header).style(theme.header))
+        .widths(
+            [
+                Constraint::Length(7),
+                Constraint::Length(5),
+                Constraint::Min(16),
+                Constraint::Length(12),
+                Constraint::Length(9),
+                Constraint::Length(9),
+                Constraint::Length(9),
+                Constraint::Length(9),
+                Constraint::Length(8),
+            ]
+            .as_ref(),
+        )
+        .column_spacing(1)
+        .render(f, area);
+}
@@ -0,0 +1,133 @@
+use super::prompt::Prompt;
+use super::theme::Theme;
+use super::{MBackend, Render};
+use crate::float_to_byte_string;
+use crate::metrics::lifecycle::{LifecycleEvent, LifecycleKind};
+use crate::metrics::*;
+use byte_unit::{Byte, ByteUnit};
+use tui::layout::{Constraint, Rect};
+use tui::style::Style;
+use tui::text::Spans;
+use tui::widgets::{Block, Borders, Cell, Row, Table};
+use tui::Frame;
+
+/// Scroll position and filter of the Events section.
+#[derive(Default)]
+pub struct EventLogView {
+    pub focused: bool,
+    /// Rows scrolled away from the newest event.
+    pub scroll: usize,
+    pub filter: String,
+}
+
+impl EventLogView {
+    pub fn visible<'a>(&self, app: &'a CPUTimeApp) -> Vec<&'a LifecycleEvent> {
+        app.lifecycle
+            .events
+            .iter()
+            .rev()
+            .filter(|e| self.filter.is_empty() || e.matches(&self.filter))
+            .collect()
+    }
+
+    pub fn scroll_up(&mut self, delta: usize) {
+        self.scroll = self.scroll.saturating_sub(delta);
+    }
+
+    pub fn scroll_down(&mut self, delta: usize, app: &CPUTimeApp) {
+        let max = self.visible(app).len().saturating_sub(1);
+        self.scroll = (self.scroll + delta).min(max);
+    }
+}
+
+fn kind_style(kind: LifecycleKind, theme: &Theme) -> Style {
+    match kind {
+        LifecycleKind::Started => theme.good,
+        LifecycleKind::Exited => theme.bad,
+        LifecycleKind::PidReused => theme.warn,
+    }
+}
+
+pub fn render_events(
+    app: &CPUTimeApp,
+    theme: &Theme,
+    view: &EventLogView,
+    prompt: Option<&Prompt>,
+    area: Rect,
+    f: &mut Frame<'_, MBackend>,
+) {
+    let events = view.visible(app);
+    let rows: Vec<Row> = events
+        .iter()
+        .skip(view.scroll)
+        .map(|e| {
+            let runtime = match e.runtime {
+                Some(d) => format!(
+                    "{}:{:0>2}:{:0>2}",
+                    d.num_hours(),
+                    d.num_minutes() % 60,
+                    d.num_seconds() % 60
+                ),
+                None => String::from("-"),
+            };
+            let peak = match e.peak_memory {
+                Some(m) => float_to_byte_string!(m as f64, ByteUnit::KB),
+                None => String::from("-"),
+            };
+            Row::new(vec![
+                Cell::from(e.time.format("%H:%M:%S").to_string()),
+                Cell::from(e.kind.as_str()).style(kind_style(e.kind, theme)),
+                Cell::from(format!("{:>7}", e.pid)),
+                Cell::from(e.name.clone()),
+                Cell::from(e.user.clone()),
+                Cell::from(format!("{:>10}", runtime)),
+                Cell::from(format!("{:>9}", peak)),
+            ])
+        })
+        .collect();
+
+    let title = if let Some(prompt) = prompt {
+        prompt.title(&prompt.kind.label(), theme)
+    } else if view.focused {
+        Spans::from(format!(
+            "Process Events ({}) Scroll [↑/↓] Filter [/] {} Leave [v]",
+            events.len(),
+            view.filter
+        ))
+    } else if !view.filter.is_empty() {
+        Spans::from(format!("Process Events ({}) filtered by: {} [v] to focus", events.len(), view.filter))
+    } else {
+        Spans::from(format!("Process Events ({}) [v] to focus", events.len()))
+    };
+    let border_style = if view.focused {
+        theme.accent
+    } else {
+        Style::default()
+    };
+
+    Table::new(rows)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(border_style)
+                .title(title),
+        )
+        .header(
+            Row::new(vec!["TIME", "EVENT", "    PID", "NAME", "USER", "   RUNTIME", " PEAK MEM"])
+                .style(theme.header),
+        )
+        .widths(
+            [
+                Constraint::Length(9),
+                Constraint::Length(11),
+                Constraint::Length(8),
+                Constraint::Min(16),
+                Constraint::Length(12),
+                Constraint::Length(11),
+                Constraint::Length(10),
+            ]
+            .as_ref(),
+        )
+        .column_spacing(1)
+        .render(f, area);
+}
@@ -0,0 +1,123 @@
+use super::theme::Theme;
+use super::{MBackend, Render};
+use crate::metrics::proc_connector::ExitStatus;
+use crate::metrics::*;
+use tui::layout::{Constraint, Direction, Layout, Rect};
+use tui::style::Style;
+use tui::widgets::{Block, Borders, Cell, Paragraph, Row, Table, Wrap};
+use tui::Frame;
+
+fn status_style(status: ExitStatus, theme: &Theme) -> Style {
+    match status {
+        ExitStatus::Code(0) => theme.good,
+        ExitStatus::Code(_) => theme.warn,
+        ExitStatus::Signal(_) => theme.bad,
+    }
+}
+
+pub fn render_exits(app: &CPUTimeApp, theme: &Theme, area: Rect, f: &mut Frame<'_, MBackend>) {
+    let block = Block::default().borders(Borders::ALL).title("Recently Exited");
+    let connector = match (&app.proc_connector, &app.proc_connector_error) {
+        (Some(c), _) => c,
+        (None, message) => {
+            let message = message.clone().unwrap_or_else(|| {
+                String::from("Start El-Modeer with --proc-connector to record short-lived processes.")
+            });
+            Paragraph::new(message)
+                .block(block)
+                .wrap(Wrap { trim: true })
+                .render(f, area);
+            return;
+        }
+    };
+    let state = connector.state.lock().expect("Connector lock poisoned");
+    if let Some(e) = &state.error {
+        Paragraph::new(e.clone())
+            .block(block)
+            .wrap(Wrap { trim: true })
+            .render(f, area);
+        return;
+    }
+
+    let h_sections = Layout::default()
+        .direction(Direction::Horizontal)
+        .margin(0)
+        .constraints([Constraint::Percentage(65), Constraint::Percentage(35)].as_ref())
+        .split(area);
+    let header_style = theme.header;
+
+    let height = h_sections[0].height.saturating_sub(3) as usize;
+    let exit_rows: Vec<Row> = state
+        .recent
+        .iter()
+        .rev()
+        .take(height)
+        .map(|e| {
+            let lifetime = match e.lifetime {
+                Some(d) if d.as_secs() < 60 => format!("{:>9.3}s", d.as_secs_f64()),
+                Some(d) => format!("{:>9}m", d.as_secs() / 60),
+                None => String::from("         -"),
+            };
+            Row::new(vec![
+                Cell::from(e.time.format("%H:%M:%S").to_string()),
+                Cell::from(format!("{:>7}", e.pid)),
+                Cell::from(format!("{:>7}", e.ppid)),
+                Cell::from(e.name.clone()),
+                Cell::from(e.status.describe()).style(status_style(e.status, theme)),
+                Cell::from(lifetime),
+            ])
+        })
+        .collect();
+    Table::new(exit_rows)
+        .block(block.title(format!("Recently Exited ({})", state.recent.len())))
+        .header(
+            Row::new(vec!["TIME", "    PID", "   PPID", "NAME", "STATUS", "  LIFETIME"])
+                .style(header_style),
+        )
+        .widths(
+            [
+                Constraint::Length(9),
+                Constraint::Length(8),
+                Constraint::Length(8),
+                Constraint::Min(16),
+                Constraint::Length(10),
+                Constraint::Length(11),
+            ]
+            .as_ref(),
+        )
+        .column_spacing(1)
+        .render(f, h_sections[0]);
+
+    let mut counts: Vec<_> = state.counts.iter().collect();
+    counts.sort_by(|(a_name, a), (b_name, b)| b.execs.cmp(&a.execs).then(a_name.cmp(b_name)));
+    let count_rows: Vec<Row> = counts
+        .iter()
+        .map(|(name, c)| {
+            let failed_style = if c.failures > 0 {
+                theme.warn
+            } else {
+                Style::default()
+            };
+            Row::new(vec![
+                Cell::from(name.to_string()),
+                Cell::from(format!("{:>6}", c.execs)),
+                Cell::from(format!("{:>6}", c.exits)),
+                Cell::from(format!("{:>6}", c.failures)).style(failed_style),
+            ])
+        })
+        .collect();
+    Table::new(count_rows)
+        .block(Block::default().borders(Borders::ALL).title("Per Command"))
+        .header(Row::new(vec!["COMMAND", " EXECS", " EXITS", "FAILED"]).style(header_style))
+        .widths(
+            [
+                Constraint::Min(10),
+                Constraint::Length(6),
+                Constraint::Length(6),
+                Constraint::Length(6),
+            ]
+            .as_ref(),
+        )
+        .column_spacing(1)
+        .render(f, h_sections[1]);
+}
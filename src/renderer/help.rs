@@ -1,19 +1,21 @@
 use crate::metrics::*;
+use crate::renderer::theme::Theme;
 use crate::renderer::{Render, MBackend};
 use tui::layout::{Alignment, Constraint, Direction, Layout, Rect};
-use tui::style::{Color, Style};
+use tui::style::Style;
 use tui::text::{Span, Spans};
 use tui::widgets::{Block, Borders, Paragraph, Wrap};
 use tui::Frame;
 
 pub fn render_help(
     _app: &CPUTimeApp,
+    theme: &Theme,
     area: Rect,
     f: &mut Frame<'_, MBackend>,
 ) {
-    let header_style = Style::default().fg(Color::Green);
+    let header_style = theme.heading;
     let main_style = Style::default();
-    let key_style = main_style.fg(Color::Cyan);
+    let key_style = theme.accent;
 
     static GLOBAL_KEYS: &[[&str; 2]] = &[
         ["h    ", "    Toggle this help screen\n"],
@@ -21,6 +23,7 @@ pub fn render_help(
         ["f    ", "    Freeze refreshing\n"],
         ["i    ", "    Show Section Selection Menu\n"],
         ["o    ", "    Show Column Selection Menu of the Process Table\n"],
+        ["v    ", "    Focus the Events log (↑/↓ scroll, / filter, v or <ESC> to leave)\n"],
     ];
 
     static PROCESS_TABLE_KEYS: &[[&str; 2]] = &[
@@ -35,15 +38,45 @@ pub fn render_help(
         [",     ", "    Cycle columns left\n"],
         [".     ", "    Cycle columns right\n"],
         ["p     ", "    Toggle paths on/off\n"],
+        ["g     ", "    Toggle heat colours and trend arrows on CPU%, MEM% and MEM\n"],
+        ["←/→   ", "    Scroll the table columns left/right\n"],
+        ["{/}   ", "    Scroll the command lines left/right (also Shift+←/→)\n"],
+        ["w     ", "    Show the full command line and executable of the highlighted process\n"],
+        ["1-9   ", "    Switch to the tab with that number (Tab/Shift+Tab for the next/previous)\n"],
+        ["N     ", "    New tab, a copy of the current one (the previous match while searching, see n/N)\n"],
+        ["R     ", "    Rename the current tab\n"],
+        ["X     ", "    Close the current tab\n"],
         ["/     ", "    Enter filter mode\n"],
+        ["?     ", "    Search, highlights the next match without hiding the other rows\n"],
+        ["n/N   ", "    Next/previous search match (also F3/Shift+F3), <ESC> clears the search\n"],
         ["c     ", "    Enter filter by Category mode\n"],
         ["k     ", "    Kill a process using its PID\n"],
         ["s     ", "    Suspend (stop) a process using its PID\n"],
         ["r     ", "    Resume a (stopped) process using its PID\n"],
-        ["n     ", "    Nice a process (change its priority) using its PID and the new nice value\n"],
+        ["n     ", "    Nice a process (change its priority) using its PID and the new nice value (the next match while searching)\n"],
+        ["x     ", "    Run a command (e.g. nice=10 cpus=1-2 cwd=~/src FOO=bar make) and focus it\n"],
+        ["u     ", "    Change the refresh rate\n"],
+        ["B     ", "    Take a baseline of the processes and show what changed since (the delta view)\n"],
+        ["D     ", "    Show/hide the delta view, sorted with ,/. and ; like the table\n"],
+        ["a     ", "    Edit CPU affinity of the focused process\n"],
+        ["e     ", "    Edit scheduling policy and I/O priority of the focused process\n"],
+        ["l     ", "    Show and edit resource limits of the focused process\n"],
+        ["Click ", "    Highlight a process, click a column header to sort by it\n"],
+        ["2Click", "    Focus the double-clicked process\n"],
+        ["Wheel ", "    Move three lines up/down\n"],
+        ["T     ", "    Stop the focused process: SIGTERM, then SIGKILL if it outlasts --stop-timeout\n"],
+        ["y/n   ", "    Send/cancel a kill, terminate or suspend, protected processes need their name\n"],
         ["<ESC> ", "    Leave any action mode\n"],
     ];
 
+    static PROMPT_KEYS: &[[&str; 2]] = &[
+        ["←/→   ", "    Move the cursor, by words with Ctrl or Alt (also ^B/^F, M-b/M-f)\n"],
+        ["^A/^E ", "    Move to the start/end of the line (also Home/End)\n"],
+        ["^W/M-d", "    Delete the word before/after the cursor\n"],
+        ["^U/^K ", "    Delete to the start/end of the line\n"],
+        ["↑/↓   ", "    Recall earlier answers to the same kind of prompt\n"],
+    ];
+
     let mut t = vec![Spans::from(vec![Span::styled(
         "Primary Interface",
         header_style,
@@ -69,6 +102,19 @@ pub fn render_help(
         ]));
     }
 
+    t.push(Spans::from(vec![Span::styled("", header_style)]));
+    t.push(Spans::from(vec![Span::styled(
+        "Prompts\n",
+        header_style,
+    )]));
+
+    for [key, text] in PROMPT_KEYS {
+        t.push(Spans::from(vec![
+            Span::styled(*key, key_style),
+            Span::styled(*text, main_style),
+        ]));
+    }
+
     let help_height = t.len() as u16;
 
     let help_layout = Layout::default()
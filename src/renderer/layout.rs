@@ -0,0 +1,545 @@
+//! Where the sections go on the screen. A layout is a list of rows from top to bottom, each
+//! row holding one or more sections side by side.
+//!
+//! Written out, rows are separated by `;` or new lines and sections in a row by `,`:
+//! `30% min=8: system_info 60%, sensors; process` puts system info and sensors next to each
+//! other in a row 30% of the screen high (at least 8 rows) above the process table. The size
+//! before the `:` is the height of the row, the one after a section its width. A row of a
+//! single section may leave out the `:`, its size is then the height: `events 20%`.
+
+use super::section::Section;
+use crate::util::config_dir;
+use std::fmt;
+use std::fs;
+use std::path::PathBuf;
+use tui::layout::Rect;
+
+/// Percent of the screen a section grows or shrinks by per key press, at least one cell.
+const RESIZE_PERCENT: f64 = 2.0;
+
+/// How much of the screen height (rows) or of the row width (sections) something takes.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Extent {
+    Percent(f64),
+    Cells(u16),
+    /// What the others leave, shared equally.
+    Rest,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Size {
+    pub extent: Extent,
+    /// Bounds in cells, whatever the extent.
+    pub min: u16,
+    pub max: u16,
+}
+
+impl Size {
+    pub fn new(extent: Extent) -> Size {
+        Size {
+            extent,
+            min: 0,
+            max: u16::MAX,
+        }
+    }
+
+    fn with_min(self, min: u16) -> Size {
+        Size { min, ..self }
+    }
+
+    /// `N%`, `N` cells or `rest`, and optionally `min=N` and `max=N`. Nothing means rest.
+    fn parse(words: &[&str]) -> Result<Size, String> {
+        let cells = |v: &str| {
+            v.parse::<u16>()
+                .map_err(|_| format!("invalid number of cells '{}'", v))
+        };
+        let mut size = Size::new(Extent::Rest);
+        for word in words {
+            if let Some(v) = word.strip_prefix("min=") {
+                size.min = cells(v)?;
+            } else if let Some(v) = word.strip_prefix("max=") {
+                size.max = cells(v)?;
+            } else if *word == "rest" {
+                size.extent = Extent::Rest;
+            } else if let Some(p) = word.strip_suffix('%') {
+                size.extent = match p.parse::<f64>() {
+                    Ok(p) if p > 0.0 && p <= 100.0 => Extent::Percent(p),
+                    _ => return Err(format!("invalid percentage '{}'", word)),
+                };
+            } else if let Ok(n) = word.parse::<u16>() {
+                size.extent = Extent::Cells(n);
+            } else {
+                return Err(format!(
+                    "invalid size '{}', expected N%, N, rest, min=N or max=N",
+                    word
+                ));
+            }
+        }
+        if size.min > size.max {
+            return Err(format!("min={} is more than max={}", size.min, size.max));
+        }
+        Ok(size)
+    }
+
+    fn clamp(&self, cells: f64) -> u16 {
+        cells.round().max(self.min as f64).min(self.max as f64) as u16
+    }
+
+    /// Grow by `cells`, or shrink for a negative number. `current` is what it was last drawn
+    /// with out of `total`, a size taking the rest becomes a percentage.
+    fn grow(&mut self, cells: i32, current: u16, total: u16) {
+        let percent = |cells: f64| (cells * 100.0 / total.max(1) as f64).clamp(1.0, 100.0);
+        self.extent = match self.extent {
+            Extent::Cells(n) => Extent::Cells((n as i32 + cells).clamp(1, total.max(1) as i32) as u16),
+            Extent::Percent(p) => Extent::Percent(percent(p * total as f64 / 100.0 + cells as f64)),
+            Extent::Rest => Extent::Percent(percent((current as i32 + cells) as f64)),
+        };
+    }
+}
+
+/// Move the border between `sizes[index]` and its neighbour (the next one, the previous one
+/// for the last) by one step, growing the first for a positive `delta`. `drawn` is what they
+/// were last drawn with out of `total`.
+fn move_border(sizes: &mut [Size], index: usize, delta: i32, drawn: &[u16], total: u16) {
+    let cells = delta * ((total as f64 * RESIZE_PERCENT / 100.0).round() as i32).max(1);
+    let neighbour = if index + 1 < sizes.len() {
+        Some(index + 1)
+    } else {
+        index.checked_sub(1)
+    };
+    match neighbour {
+        // one taking the rest grows as the neighbour shrinks
+        Some(n) if sizes[index].extent == Extent::Rest && sizes[n].extent != Extent::Rest => {
+            sizes[n].grow(-cells, drawn[n], total);
+        }
+        Some(n) if sizes[n].extent != Extent::Rest => {
+            sizes[index].grow(cells, drawn[index], total);
+            sizes[n].grow(-cells, drawn[n], total);
+        }
+        // the neighbour takes whatever is left anyway
+        _ => sizes[index].grow(cells, drawn[index], total),
+    }
+}
+
+impl fmt::Display for Size {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.extent {
+            Extent::Percent(p) => write!(f, "{:.0}%", p)?,
+            Extent::Cells(n) => write!(f, "{}", n)?,
+            Extent::Rest => write!(f, "rest")?,
+        }
+        if self.min > 0 {
+            write!(f, " min={}", self.min)?;
+        }
+        if self.max < u16::MAX {
+            write!(f, " max={}", self.max)?;
+        }
+        Ok(())
+    }
+}
+
+/// Split `total` cells among `sizes`. Sizes taking the rest share what the others leave, when
+/// there are none the percentages grow in proportion to fill `total`. When it doesn't fit,
+/// the last ones shrink first.
+fn distribute(sizes: &[Size], total: u16) -> Vec<u16> {
+    let mut cells: Vec<Option<u16>> = sizes
+        .iter()
+        .map(|s| match s.extent {
+            Extent::Percent(p) => Some(s.clamp(p * total as f64 / 100.0)),
+            Extent::Cells(n) => Some(s.clamp(n as f64)),
+            Extent::Rest => None,
+        })
+        .collect();
+    let used: u32 = cells.iter().flatten().map(|c| *c as u32).sum();
+    let left = (total as u32).saturating_sub(used);
+    let rest: Vec<usize> = (0..sizes.len()).filter(|i| cells[*i].is_none()).collect();
+    if let Some(last) = rest.last() {
+        let share = left / rest.len() as u32;
+        for i in &rest {
+            // the last one gets what's left after rounding
+            let mine = if i == last {
+                left - share * (rest.len() as u32 - 1)
+            } else {
+                share
+            };
+            cells[*i] = Some(sizes[*i].clamp(mine as f64));
+        }
+    } else if left > 0 {
+        let weight: f64 = sizes
+            .iter()
+            .map(|s| match s.extent {
+                Extent::Percent(p) => p,
+                _ => 0.0,
+            })
+            .sum();
+        if weight > 0.0 {
+            for (size, c) in sizes.iter().zip(cells.iter_mut()) {
+                if let (Extent::Percent(p), Some(c)) = (size.extent, c.as_mut()) {
+                    *c = size.clamp(*c as f64 + (left as f64 * p / weight).floor());
+                }
+            }
+            let used: u32 = cells.iter().flatten().map(|c| *c as u32).sum();
+            let last = sizes
+                .iter()
+                .rposition(|s| matches!(s.extent, Extent::Percent(_)));
+            if let (Some(last), true) = (last, used < total as u32) {
+                let c = cells[last].get_or_insert(0);
+                *c = sizes[last].clamp(*c as f64 + (total as u32 - used) as f64);
+            }
+        }
+    }
+
+    let mut cells: Vec<u16> = cells.into_iter().map(|c| c.unwrap_or(0)).collect();
+    let mut over = cells.iter().map(|c| *c as u32).sum::<u32>().saturating_sub(total as u32);
+    // shrink down to the minimums first, then cut whatever still doesn't fit
+    for floor in [true, false] {
+        for (size, c) in sizes.iter().zip(cells.iter_mut()).rev() {
+            let keep = if floor { size.min.min(*c) } else { 0 };
+            let take = over.min((*c - keep) as u32);
+            *c -= take as u16;
+            over -= take;
+        }
+    }
+    cells
+}
+
+#[derive(Clone, Debug)]
+pub struct LayoutRow {
+    pub height: Size,
+    pub sections: Vec<(Section, Size)>,
+}
+
+#[derive(Clone, Debug)]
+pub struct SectionLayout {
+    pub rows: Vec<LayoutRow>,
+}
+
+impl SectionLayout {
+    /// Sections on top of one another with percent heights, the process table taking the rest.
+    pub fn stacked(geometry: &[(Section, f64)]) -> SectionLayout {
+        let rows = geometry
+            .iter()
+            .map(|(section, height)| LayoutRow {
+                height: section_height(*section, Extent::Percent(*height)),
+                sections: vec![(*section, Size::new(Extent::Rest))],
+            })
+            .collect();
+        SectionLayout { rows }
+    }
+
+    pub fn parse(spec: &str) -> Result<SectionLayout, String> {
+        let mut rows = vec![];
+        let mut seen = vec![];
+        for line in spec.lines() {
+            let line = line.split('#').next().unwrap_or("");
+            for row in line.split(';').map(str::trim).filter(|r| !r.is_empty()) {
+                let (height, items) = match row.split_once(':') {
+                    Some((h, items)) => (Some(h), items),
+                    None => (None, row),
+                };
+                let mut sections = vec![];
+                for item in items.split(',') {
+                    let words: Vec<&str> = item.split_whitespace().collect();
+                    let section = match words.first() {
+                        Some(key) => Section::from_key(key).ok_or_else(|| {
+                            format!(
+                                "unknown section '{}', expected one of {}",
+                                key,
+                                Section::KEYS.join(", ")
+                            )
+                        })?,
+                        None => return Err(format!("empty section in '{}'", row)),
+                    };
+                    if seen.contains(&section) {
+                        return Err(format!("section '{}' is used twice", section.key()));
+                    }
+                    seen.push(section);
+                    sections.push((section, Size::parse(&words[1..])?));
+                }
+                let height = match height {
+                    Some(h) => Size::parse(&h.split_whitespace().collect::<Vec<_>>())?,
+                    // the size of a lone section is the height of its row
+                    None if sections.len() == 1 => {
+                        std::mem::replace(&mut sections[0].1, Size::new(Extent::Rest))
+                    }
+                    None => Size::new(Extent::Rest),
+                };
+                rows.push(LayoutRow { height, sections });
+            }
+        }
+        if rows.is_empty() {
+            return Err(String::from("the layout has no sections"));
+        }
+        Ok(SectionLayout { rows })
+    }
+
+    /// `spec` from the command line, otherwise the layout file if there is one.
+    pub fn load(spec: Option<&str>) -> Result<Option<SectionLayout>, String> {
+        if let Some(spec) = spec {
+            return SectionLayout::parse(spec)
+                .map(Some)
+                .map_err(|e| format!("Invalid layout: {}", e));
+        }
+        let path = layout_path();
+        match fs::read_to_string(&path) {
+            Ok(text) => SectionLayout::parse(&text)
+                .map(Some)
+                .map_err(|e| format!("Invalid layout in {}: {}", path.display(), e)),
+            Err(_) => Ok(None),
+        }
+    }
+
+    /// Top to bottom and left to right.
+    pub fn sections(&self) -> Vec<Section> {
+        self.rows
+            .iter()
+            .flat_map(|r| r.sections.iter().map(|(s, _)| *s))
+            .collect()
+    }
+
+    pub fn contains(&self, section: Section) -> bool {
+        self.rows.iter().any(|r| r.sections.iter().any(|(s, _)| *s == section))
+    }
+
+    /// Row and size of a section in it.
+    pub fn find(&self, section: Section) -> Option<(&LayoutRow, &Size)> {
+        self.rows.iter().find_map(|r| {
+            r.sections
+                .iter()
+                .find(|(s, _)| *s == section)
+                .map(|(_, size)| (r, size))
+        })
+    }
+
+    pub fn remove(&mut self, section: Section) {
+        for row in self.rows.iter_mut() {
+            row.sections.retain(|(s, _)| *s != section);
+        }
+        self.rows.retain(|r| !r.sections.is_empty());
+    }
+
+    /// A row of its own above the process table, which goes at the bottom.
+    pub fn insert(&mut self, section: Section) {
+        let row = LayoutRow {
+            height: section_height(section, Extent::Percent(18.0)),
+            sections: vec![(section, Size::new(Extent::Rest))],
+        };
+        let process_row = self
+            .rows
+            .iter()
+            .position(|r| r.sections.iter().any(|(s, _)| *s == Section::Process));
+        match process_row {
+            Some(i) if section != Section::Process => self.rows.insert(i, row),
+            _ => self.rows.push(row),
+        }
+    }
+
+    /// Make the row of `section` taller (or its width in the row wider when `!vertical`) by
+    /// one step, shorter or narrower for a negative `delta`. `area` is where the sections go.
+    pub fn resize(&mut self, section: Section, vertical: bool, delta: i32, area: Rect) {
+        let drawn = self.split(area);
+        let drawn_size = |s: Section| drawn.iter().find(|(d, _)| *d == s).map(|(_, r)| *r);
+        let row = match self.rows.iter().position(|r| r.sections.iter().any(|(s, _)| *s == section)) {
+            Some(r) => r,
+            None => return,
+        };
+        if vertical {
+            let heights: Vec<u16> = self
+                .rows
+                .iter()
+                .map(|r| drawn_size(r.sections[0].0).map_or(0, |d| d.height))
+                .collect();
+            let mut sizes: Vec<Size> = self.rows.iter().map(|r| r.height).collect();
+            move_border(&mut sizes, row, delta, &heights, area.height);
+            for (r, size) in self.rows.iter_mut().zip(sizes) {
+                r.height = size;
+            }
+        } else {
+            let sections = &mut self.rows[row].sections;
+            let index = sections.iter().position(|(s, _)| *s == section).unwrap_or(0);
+            let widths: Vec<u16> = sections
+                .iter()
+                .map(|(s, _)| drawn_size(*s).map_or(0, |d| d.width))
+                .collect();
+            let mut sizes: Vec<Size> = sections.iter().map(|(_, s)| *s).collect();
+            move_border(&mut sizes, index, delta, &widths, area.width);
+            for ((_, s), size) in sections.iter_mut().zip(sizes) {
+                *s = size;
+            }
+        }
+    }
+
+    /// Where each section goes in `area`. Sections that got no room have an empty area.
+    pub fn split(&self, area: Rect) -> Vec<(Section, Rect)> {
+        let heights: Vec<Size> = self.rows.iter().map(|r| r.height).collect();
+        let mut areas = vec![];
+        let mut y = area.y;
+        for (row, height) in self.rows.iter().zip(distribute(&heights, area.height)) {
+            let sizes: Vec<Size> = row.sections.iter().map(|(_, s)| *s).collect();
+            let mut x = area.x;
+            for ((section, _), width) in row.sections.iter().zip(distribute(&sizes, area.width)) {
+                areas.push((*section, Rect::new(x, y, width, height)));
+                x += width;
+            }
+            y += height;
+        }
+        areas
+    }
+}
+
+/// Borders and a line of content need two rows, the process table four.
+fn section_height(section: Section, extent: Extent) -> Size {
+    if section == Section::Process {
+        Size::new(Extent::Rest).with_min(4)
+    } else {
+        Size::new(extent).with_min(2)
+    }
+}
+
+fn layout_path() -> PathBuf {
+    config_dir().join("layout")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn percent(p: f64) -> Size {
+        Size::new(Extent::Percent(p))
+    }
+
+    fn cells(n: u16) -> Size {
+        Size::new(Extent::Cells(n))
+    }
+
+    fn rest() -> Size {
+        Size::new(Extent::Rest)
+    }
+
+    #[test]
+    fn parses_sizes() {
+        assert_eq!(Size::parse(&[]), Ok(rest()));
+        assert_eq!(Size::parse(&["50%", "min=8"]), Ok(percent(50.0).with_min(8)));
+        assert_eq!(
+            Size::parse(&["12", "max=20"]),
+            Ok(Size {
+                max: 20,
+                ..cells(12)
+            })
+        );
+        assert_eq!(
+            Size::parse(&["rows"]),
+            Err(String::from("invalid size 'rows', expected N%, N, rest, min=N or max=N"))
+        );
+        assert_eq!(Size::parse(&["0%"]), Err(String::from("invalid percentage '0%'")));
+        assert_eq!(Size::parse(&["120%"]), Err(String::from("invalid percentage '120%'")));
+        assert_eq!(Size::parse(&["min=x"]), Err(String::from("invalid number of cells 'x'")));
+        assert_eq!(
+            Size::parse(&["min=9", "max=3"]),
+            Err(String::from("min=9 is more than max=3"))
+        );
+    }
+
+    #[test]
+    fn parses_layouts() {
+        let layout = SectionLayout::parse("30% min=8: system_info 60%, sensors\nprocess # the rest").unwrap();
+        assert_eq!(layout.rows.len(), 2);
+        assert_eq!(layout.rows[0].height, percent(30.0).with_min(8));
+        assert_eq!(
+            layout.rows[0].sections,
+            vec![(Section::SystemInfo, percent(60.0)), (Section::Sensors, rest())]
+        );
+        assert_eq!(layout.rows[1].height, rest());
+
+        // a lone section's size is its row's height
+        let layout = SectionLayout::parse("events 20%; process").unwrap();
+        assert_eq!(layout.rows[0].height, percent(20.0));
+        assert_eq!(layout.rows[0].sections, vec![(Section::Events, rest())]);
+    }
+
+    #[test]
+    fn layout_errors() {
+        let error = |spec| SectionLayout::parse(spec).map(|_| ()).unwrap_err();
+        assert_eq!(error(" # nothing"), "the layout has no sections");
+        assert_eq!(
+            error("cpu 20%; process"),
+            "unknown section 'cpu', expected one of system_info, sensors, events, exits, process"
+        );
+        assert_eq!(error("process; process"), "section 'process' is used twice");
+        assert_eq!(error("30%: sensors,; process"), "empty section in '30%: sensors,'");
+        assert_eq!(
+            error("sensors 20% max; process"),
+            "invalid size 'max', expected N%, N, rest, min=N or max=N"
+        );
+    }
+
+    #[test]
+    fn percentages_that_dont_add_up() {
+        // short of 100%, they grow in proportion, the last one taking what rounding leaves
+        assert_eq!(distribute(&[percent(20.0), percent(30.0)], 100), vec![40, 60]);
+        assert_eq!(distribute(&[percent(10.0), percent(10.0), percent(10.0)], 10), vec![3, 3, 4]);
+        // past 100%, the last ones shrink
+        assert_eq!(distribute(&[percent(60.0), percent(60.0)], 100), vec![60, 40]);
+        assert_eq!(distribute(&[percent(80.0), percent(30.0), rest()], 100), vec![80, 20, 0]);
+        // the rest is shared, the last one getting what rounding leaves
+        assert_eq!(distribute(&[percent(30.0), rest(), rest()], 51), vec![15, 18, 18]);
+    }
+
+    #[test]
+    fn min_and_max() {
+        assert_eq!(distribute(&[percent(10.0).with_min(5), rest()], 20), vec![5, 15]);
+        let capped = Size {
+            max: 10,
+            ..percent(90.0)
+        };
+        assert_eq!(distribute(&[capped, rest()], 100), vec![10, 90]);
+        // not enough room: down to the minimums from the last one up, then below them
+        assert_eq!(distribute(&[cells(10).with_min(8), cells(10).with_min(8)], 20), vec![10, 10]);
+        assert_eq!(distribute(&[cells(10).with_min(8), cells(10).with_min(8)], 12), vec![8, 4]);
+        assert_eq!(distribute(&[cells(10), rest().with_min(4)], 12), vec![8, 4]);
+    }
+
+    #[test]
+    fn moves_borders() {
+        // the neighbour takes the rest, only the resized one changes
+        let mut sizes = [percent(30.0), rest()];
+        move_border(&mut sizes, 0, 1, &[30, 70], 100);
+        assert_eq!(sizes, [percent(32.0), rest()]);
+
+        // a size taking the rest grows as its neighbour shrinks, the last one's being the
+        // previous one
+        let mut sizes = [percent(30.0), rest()];
+        move_border(&mut sizes, 1, 1, &[30, 70], 100);
+        assert_eq!(sizes, [percent(28.0), rest()]);
+        assert_eq!(distribute(&sizes, 100), vec![28, 72]);
+
+        // next to another rest, the resized one becomes a percentage of what it was drawn with
+        let mut sizes = [rest(), rest()];
+        move_border(&mut sizes, 0, -1, &[50, 50], 100);
+        assert_eq!(sizes, [percent(48.0), rest()]);
+
+        let mut sizes = [cells(10), cells(20)];
+        move_border(&mut sizes, 0, -1, &[10, 20], 50);
+        assert_eq!(sizes, [cells(9), cells(21)]);
+
+        let mut sizes = [percent(99.0), percent(1.0)];
+        move_border(&mut sizes, 0, 1, &[99, 1], 100);
+        assert_eq!(sizes, [percent(100.0), percent(1.0)]);
+    }
+
+    #[test]
+    fn resizes_sections() {
+        let area = Rect::new(0, 0, 80, 100);
+        let mut layout = SectionLayout::parse("30%: system_info 50%, sensors; process").unwrap();
+        layout.resize(Section::Process, true, 1, area);
+        layout.resize(Section::SystemInfo, false, -1, area);
+        assert_eq!(
+            layout.split(area),
+            vec![
+                (Section::SystemInfo, Rect::new(0, 0, 38, 28)),
+                (Section::Sensors, Rect::new(38, 0, 42, 28)),
+                (Section::Process, Rect::new(0, 28, 80, 72)),
+            ]
+        );
+    }
+}
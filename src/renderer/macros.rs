@@ -8,16 +8,6 @@ macro_rules! float_to_byte_string {
     };
 }
 
-#[macro_export]
-macro_rules! convert_result_to_string {
-    ($x:expr) => {
-        match $x {
-            Ok(_r) => String::from("Signal Sent."),
-            Err(e) => convert_error_to_string!(e),
-        }
-    };
-}
-
 #[macro_export]
 macro_rules! convert_error_to_string {
     ($x:expr) => {
@@ -1,38 +1,74 @@
 mod system_info;
+pub mod confirm;
+mod delta;
+mod sensors;
+mod events;
+mod exits;
 mod help;
 pub mod macros;
 mod process;
+mod prompt;
+mod stop;
+pub mod layout;
 pub mod section;
 pub mod column;
-use crate::constants::NUMBER_OF_COLUMNS;
+mod tabs;
+pub mod theme;
 use crate::metrics::mprocess::*;
 use crate::metrics::*;
+use crate::renderer::layout::SectionLayout;
 use crate::renderer::section::{Section, SectionMGRList};
-use crate::renderer::column::{Column, ColumnMGRList};
+use crate::renderer::confirm::{Confirmation, Protection, SignalGuard};
+use crate::renderer::column::{Column, ColumnMGRList, ColumnWidth};
+use crate::renderer::delta::DeltaView;
+use crate::renderer::events::EventLogView;
+use crate::renderer::theme::Theme;
+use crate::renderer::process::{SchedEditor, TableView};
+use crate::renderer::prompt::{Edit, Prompt, PromptHistory, PromptKind};
+use crate::renderer::stop::{Stop, StopState};
+use crate::renderer::tabs::{load_tabs, save_tabs, Tab, MAX_TABS};
+use crate::metrics::baseline::Baseline;
+use crate::metrics::launch::LaunchSpec;
+use crate::metrics::limits::{format_limit_value, parse_limit_value};
+use crate::remote::RemoteClient;
 use crate::util::*;
-use crate::{convert_result_to_string, convert_error_to_string};
 use crossterm::{
-    event::{KeyCode as Key, KeyEvent, KeyModifiers},
+    event::{
+        EnableBracketedPaste, EnableMouseCapture, KeyCode as Key, KeyEvent, KeyModifiers, MouseButton, MouseEvent,
+        MouseEventKind,
+    },
     execute,
     terminal::EnterAlternateScreen,
 };
 use num_traits::FromPrimitive;
-use sysinfo::SystemExt;
+use std::collections::HashMap;
 use std::io;
 use std::io::Stdout;
-use std::time::{Duration, Instant};
+use std::path::PathBuf;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use tui::{backend::CrosstermBackend, Terminal};
 use tui::backend::Backend;
 use tui::layout::{Constraint, Direction, Layout, Rect};
 use tui::Frame;
-use heim::process as hproc;
-use heim::process::ProcessError;
 
 
 const PROCESS_SELECTION_GRACE: Duration = Duration::from_millis(2000); //TODO: check this
+const NOT_AVAILABLE_REMOTELY: &str = "Not available when connected to an agent.";
+const DOUBLE_CLICK_INTERVAL: Duration = Duration::from_millis(400);
+const MOUSE_SCROLL_LINES: usize = 3;
+/// Updates a started command gets to show up before we stop trying to focus it.
+const PENDING_SELECT_UPDATES: u32 = 3;
 
 type MBackend = CrosstermBackend<Stdout>;
 
+/// A command started with `x`, focused once an update shows it.
+struct PendingSelect {
+    pid: i32,
+    /// Seconds since the epoch, a process with the PID started earlier isn't it.
+    spawned: u64,
+    updates_left: u32,
+}
+
 /// Compatibility trait, that preserves an older method from tui 0.6.5
 /// Exists mostly to keep the caller code idiomatic for the use cases in this file
 /// May be refactored out later if the widget usage patterns change
@@ -53,83 +89,35 @@ where
     }
 }
 
+fn contains(area: Rect, x: u16, y: u16) -> bool {
+    x >= area.x && x < area.x + area.width && y >= area.y && y < area.y + area.height
+}
+
 /// current size of the terminal returned as (columns, rows)
 fn terminal_size() -> (u16, u16) {
     crossterm::terminal::size().expect("Failed to get terminal size")
 }
 
-/// ceil to nearest upper even number
-macro_rules! ceil_even {
-    ($x:expr) => {
-        ($x + 1) / 2 * 2
-    };
+/// The process table if it is shown, otherwise the last section.
+fn default_section_index(layout: &SectionLayout) -> usize {
+    let sections = layout.sections();
+    sections
+        .iter()
+        .position(|s| *s == Section::Process)
+        .unwrap_or(sections.len() - 1)
 }
 
-/// Convert percentage heights to length constraints. This is done since sections other
-/// than process have two sub-parts and should be of even height.
-fn eval_constraints(
-    section_geometry: &[(Section, f64)],
-    height: u16,
-    borrowed: &mut bool,
-) -> Vec<Constraint> {
-    let mut constraints = vec![Constraint::Length(1)];
-    let avail_height = height as i32 - 1;
-    let mut process_index = -1;
-    let mut max_others = 0;
-    let mut max_others_index = -1;
-    let mut sum_others = 0;
-    // each section should have a height of at least 2 rows
-    let mut max_section_height = avail_height - section_geometry.len() as i32 * 2;
-    // process section is at least 4 rows high
-    if section_geometry.iter().any(|s| s.0 == Section::Process) {
-        max_section_height -= 2;
-    }
-    // convert percentage heights to length constraints and apply additional
-    // criteria that height should be even number for non-process sections
-    for (section_index, section) in section_geometry.iter().enumerate() {
-        let required_height = section.1 * avail_height as f64 / 100.0;
-        // ensure max_section_height is at least 2 after every recalculation
-        max_section_height = max_section_height.max(2);
-        if section.0 == Section::Process {
-            process_index = section_index as i32;
-            constraints.push(Constraint::Min(4));
-        } else {
-            // round to nearest even size for the two sub-parts in each section display
-            let section_height =
-                max_section_height.min(ceil_even!(required_height.floor().max(1.0) as i32));
-            sum_others += section_height;
-            // adjust max_section_height for subsequent sections
-            max_section_height -= section_height - 2;
-            if section_height >= max_others {
-                max_others = section_height;
-                max_others_index = section_index as i32;
-            }
-            constraints.push(Constraint::Length(section_height as u16));
-        }
-    }
-    // remaining is what will be actually used for process section but if its too small (due to
-    // rounding to even heights for other sections), then borrow rows from the largest section
-    if process_index != -1 {
-        let process_height = avail_height - sum_others;
-        if process_height < 4 && max_others > 4 {
-            let borrow = ceil_even!(4 - process_height).min(max_others - 4);
-            // (max_others - borrow) will be >= 4 due to the min() above so cast to u16 is safe
-            constraints[max_others_index as usize + 1] =
-                Constraint::Length((max_others - borrow) as u16);
-            constraints[process_index as usize + 1] =
-                Constraint::Min((process_height + borrow) as u16);
-            *borrowed = true;
-        } else {
-            constraints[process_index as usize + 1] = Constraint::Min(process_height as u16);
-        }
-    }
-
-    constraints
+/// Where the sections go, below the blank top line.
+fn sections_area(size: Rect) -> Rect {
+    Rect::new(size.x, size.y + 1, size.width, size.height.saturating_sub(1))
 }
 
-fn get_constraints(section_geometry: &[(Section, f64)], height: u16) -> Vec<Constraint> {
-    let mut borrowed = false;
-    eval_constraints(section_geometry, height, &mut borrowed)
+/// What to tell the user after sending a signal.
+fn signal_message(result: Result<(), String>) -> String {
+    match result {
+        Ok(_) => String::from("Signal Sent."),
+        Err(e) => e,
+    }
 }
 
 pub struct TerminalRenderer<'a> {
@@ -138,67 +126,84 @@ pub struct TerminalRenderer<'a> {
     events: Events,
     process_table_row_start: usize,
 
-    /// Index in the vector below is "order" on the screen starting from the top
-    /// (usually CPU) while value is the section it belongs to and its current height (as %).
-    /// Currently all sections are stacked on top of one another horizontally and
-    /// occupy entire width of the screen but this may change going forward. For the case
-    /// where there are multiple sections stacked vertically, the "order" can have the
-    /// convention of top-bottom and left-right in each horizontal layer and the width of
-    /// each section be tracked below. For more generic positioning (e.g. sections cutting
-    /// across others vertically), this mapping needs to also include the position of
-    /// top-left corner of the section. In that case the only significance that the
-    /// "order" will have is the sequence in which the TAB key will shift focus
-    /// among the sections.
-    section_geometry: Vec<(Section, f64)>,
+    /// Rows of sections from the top, side by side in each row. The "order" of the
+    /// sections, in which `selected_section_index` counts, is top-bottom and left-right.
+    layout: SectionLayout,
     proc_columns: Vec<Column>,
+    column_widths: HashMap<Column, ColumnWidth>,
+    /// Columns and their widths as last drawn, left to right.
+    column_spans: Vec<(Column, u16)>,
     zoom_factor: u32,
     update_number: u32,
     selected_section_index: usize,
-    constraints: Vec<Constraint>,
     process_message: Option<String>,
-    process_table_message: String,
     show_help: bool,
     show_paths: bool,
-    show_find: bool,
-    show_find_cat: bool,
-    show_kill: bool,
-    show_suspend: bool,
-    show_resume: bool,
-    show_nice: bool,
-    show_rate: bool,
+    show_heat: bool,
+    /// The open prompt, it gets the keys until Enter or Esc.
+    prompt: Option<Prompt>,
+    prompt_history: PromptHistory,
+    signal_guard: SignalGuard,
+    /// A signal waiting to be confirmed, it gets the keys before the prompt.
+    confirmation: Option<Confirmation>,
+    /// Processes sent SIGTERM by a stop, SIGKILL follows when they outlast the timeout.
+    stops: Vec<Stop>,
     show_section_mgr: bool,
     show_column_mgr: bool,
+    show_affinity: bool,
+    affinity_selection: Vec<bool>,
+    affinity_cursor: usize,
+    sched_editor: Option<SchedEditor>,
+    show_limits: bool,
+    limits_cursor: usize,
+    event_log: EventLogView,
+    delta_view: DeltaView,
+    table_view: TableView,
+    /// The active tab is stored when switching away, its state is in the fields above.
+    tabs: Vec<Tab>,
+    active_tab: usize,
+    tab_message: Option<String>,
     freeze: bool,
     filter: String,
-    action_pid: String,
-    action_input: String,
-    new_rate: String,
+    /// The filter only looks at the process status.
+    filter_category: bool,
+    pending_select: Option<PendingSelect>,
     highlighted_row: usize,
     selection_grace_start: Option<Instant>,
+    /// Where the process table and the manager lists were last drawn, for mouse clicks.
+    process_table_area: Option<Rect>,
+    mgr_list_area: Option<Rect>,
+    /// When and on which row of the process table the last click was.
+    last_click: Option<(Instant, usize)>,
     section_manager_options: SectionMGRList<'a>,
     column_manager_options: ColumnMGRList<'a>,
-    recompute_constraints_on_start_up: bool,
     tick_rate: u64,
+    theme: Theme,
 }
 
 impl<'a> TerminalRenderer<'_> {
     pub fn new(
         tick_rate: u64,
-        section_geometry: &[(Section, f64)],
-    ) -> TerminalRenderer {
-        let app = CPUTimeApp::new(Duration::from_millis(tick_rate));
+        layout: SectionLayout,
+        sysfs_root: PathBuf,
+        remote: Option<RemoteClient>,
+        dead_grace: Duration,
+        theme: Theme,
+        signal_guard: SignalGuard,
+    ) -> TerminalRenderer<'a> {
+        let mut app = CPUTimeApp::new(Duration::from_millis(tick_rate), sysfs_root, remote);
+        app.dead_grace = dead_grace;
         let events = Events::new(Duration::from_millis(tick_rate));
 
         let mut stdout = io::stdout();
         execute!(stdout, EnterAlternateScreen).expect("Unable to enter alternate screen");
+        execute!(stdout, EnableMouseCapture).expect("Unable to enable mouse capture");
+        execute!(stdout, EnableBracketedPaste).expect("Unable to enable bracketed paste");
         let backend = CrosstermBackend::new(stdout);
         let mut terminal =
             Terminal::new(backend).expect("Couldn't create new terminal with backend");
         terminal.hide_cursor().ok();
 
-        let constraints = get_constraints(section_geometry, terminal_size().1);
-        let section_geometry = section_geometry.to_vec();
-        let recompute_constraints_on_start_up = false;
         let mut default_cols = vec![
             Column::PID, 
             Column::PPID, 
@@ -210,86 +215,131 @@ impl<'a> TerminalRenderer<'_> {
             Column::CMD
         ];
         default_cols.sort();
+        let mut tabs = load_tabs(&default_cols);
+        if tabs.is_empty() {
+            tabs.push(Tab::new("all", default_cols, HashMap::new()));
+        }
+        let first = tabs[0].clone();
+        app.psortby = first.sort_by;
+        app.psortorder = first.sort_order;
+        let (proc_columns, column_widths) = (first.columns, first.column_widths);
 
         TerminalRenderer {
             terminal,
             app,
             events,
             process_table_row_start: 0,
-            section_geometry: section_geometry.clone(),
-            proc_columns: default_cols.clone(),
+            section_manager_options: SectionMGRList::with_layout(&layout),
+            selected_section_index: default_section_index(&layout),
+            layout,
+            column_manager_options: ColumnMGRList::with_cols(&proc_columns, &column_widths),
+            proc_columns,
+            column_widths,
+            column_spans: vec![],
             zoom_factor: 1,
             update_number: 0,
-            // select the last section by default (normally should be Process)
-            selected_section_index: section_geometry.len() - 1,
-            constraints,
             process_message: None,
-            process_table_message: String::from(""),
             show_help: false,
             show_paths: false,
-            show_find: false,
-            show_find_cat: false,
-            show_kill: false,
-            show_suspend: false,
-            show_resume: false,
-            show_nice: false,
+            show_heat: false,
+            prompt: None,
+            prompt_history: PromptHistory::default(),
+            signal_guard,
+            confirmation: None,
+            stops: vec![],
             show_section_mgr: false,
             show_column_mgr: false,
-            show_rate: false,
+            show_affinity: false,
+            affinity_selection: vec![],
+            affinity_cursor: 0,
+            sched_editor: None,
+            show_limits: false,
+            limits_cursor: 0,
+            event_log: EventLogView::default(),
+            delta_view: DeltaView::default(),
+            table_view: TableView::default(),
+            tabs,
+            active_tab: 0,
+            tab_message: None,
             freeze: false,
-            filter: String::from(""),
-            action_pid: String::from(""),
-            action_input: String::from(""),
-            new_rate: String::from(""),
+            filter: first.filter,
+            filter_category: false,
+            pending_select: None,
             highlighted_row: 0,
             selection_grace_start: None,
-            section_manager_options: SectionMGRList::with_geometry(section_geometry),
-            column_manager_options: ColumnMGRList::with_cols(default_cols),
-            recompute_constraints_on_start_up,
+            process_table_area: None,
+            mgr_list_area: None,
+            last_click: None,
             tick_rate,
+            theme,
         }
     }
 
     fn selected_section(&self) -> Section {
-        self.section_geometry[self.selected_section_index].0
+        self.layout.sections()[self.selected_section_index]
     }
 
-    pub async fn start(&mut self) {
-        if self.recompute_constraints_on_start_up {
-            self.recompute_constraints();
-            self.recompute_constraints_on_start_up = false;
+    /// Runs until quit. Failing to save the tabs then is returned to report once the
+    /// terminal is restored.
+    pub async fn start(&mut self, proc_connector: bool) -> Result<(), String> {
+        self.app.init().await;
+        if proc_connector {
+            self.app.start_proc_connector();
         }
         loop {
             let app = &self.app;
             let pst = &self.process_table_row_start;
             let mut width: u16 = 0;
             let mut process_table_height: u16 = 0;
+            let mut process_table_area: Option<Rect> = None;
+            let mut mgr_list_area: Option<Rect> = None;
             let proc_columns = &self.proc_columns;
-            let constraints = &self.constraints;
-            let geometry = &self.section_geometry.to_vec();
+            let column_widths = &self.column_widths;
+            let column_spans = &mut self.column_spans;
+            let layout = &self.layout;
             let section_manager_options = &mut self.section_manager_options;
             let column_manager_options = &mut self.column_manager_options;
-            // let selected = self.section_geometry[self.selected_section_index].0;
+            // a stop of the process counts down in place of the message
             let process_message = &self.process_message;
-            let process_table_message = &self.process_table_message;
+            let stops = &self.stops;
+            let process_message = &app
+                .selected_process
+                .as_ref()
+                .and_then(|p| stops.iter().find(|stop| stop.pid == p.pid))
+                .map(Stop::countdown)
+                .or_else(|| process_message.clone());
             let show_help = self.show_help;
             let show_section_mgr = self.show_section_mgr;
             let show_column_mgr = self.show_column_mgr;
             let show_paths = self.show_paths;
+            let show_heat = self.show_heat;
             let freeze = self.freeze;
             let filter = &self.filter;
-            let show_find = self.show_find;
-            let show_find_cat = self.show_find_cat;
-            let show_kill = self.show_kill;
-            let show_suspend = self.show_suspend;
-            let show_resume = self.show_resume;
-            let show_rate = self.show_rate;
-            let show_nice = self.show_nice;
-            let action_pid = &self.action_pid;
-            let action_input = &self.action_input;
-            let new_rate = &self.new_rate;
+            let filter_category = self.filter_category;
+            let prompt = self.prompt.as_ref();
+            let table_prompt = prompt.filter(|p| p.kind.in_table());
+            let tab_prompt = prompt.filter(|p| p.kind == PromptKind::TabName);
+            let event_prompt = prompt.filter(|p| p.kind == PromptKind::EventFilter);
+            let confirmation = self.confirmation.as_ref();
+            let affinity_editor = if self.show_affinity {
+                Some((self.affinity_selection.as_slice(), self.affinity_cursor))
+            } else {
+                None
+            };
+            let sched_editor = self.sched_editor.as_ref();
+            let event_log = &self.event_log;
+            let delta_view = &self.delta_view;
+            let table_view = &mut self.table_view;
+            let tabs = &self.tabs;
+            let active_tab = self.active_tab;
+            let tab_message = self.tab_message.as_deref();
+            let limits_view = if self.show_limits {
+                Some((self.limits_cursor, prompt.filter(|p| p.kind == PromptKind::Limit)))
+            } else {
+                None
+            };
             let mut highlighted_process: Option<Box<MProcess>> = None;
-            let process_table = process::filter_process_table(app, &self.filter, self.show_find_cat);
+            let process_table = process::filter_process_table(app, &self.filter, self.filter_category);
 
             if !process_table.is_empty() && self.highlighted_row >= process_table.len() {
                 self.highlighted_row = process_table.len() - 1;
@@ -297,6 +347,7 @@ impl<'a> TerminalRenderer<'_> {
             let highlighted_row = self.highlighted_row;
 
             let tick_rate = self.tick_rate;
+            let theme = &self.theme;
             self.terminal
                 .draw(|f| {
                     width = f.size().width;
@@ -307,37 +358,73 @@ impl<'a> TerminalRenderer<'_> {
                             .constraints([Constraint::Length(1), Constraint::Length(40)].as_ref())
                             .split(f.size());
 
-                        help::render_help(app, v_sections[1], f);
+                        help::render_help(app, theme, v_sections[1], f);
                     } else if show_section_mgr {
                         let v_sections = Layout::default()
                             .direction(Direction::Vertical)
                             .margin(0)
                             .constraints([Constraint::Length(1), Constraint::Length(40)].as_ref())
                             .split(f.size());
-                        section::render_section_mgr(section_manager_options, v_sections[1], f);
+                        mgr_list_area = Some(section::render_section_mgr(
+                            section_manager_options,
+                            theme,
+                            v_sections[1],
+                            f,
+                        ));
                     } else if show_column_mgr {
                         let v_columns = Layout::default()
                         .direction(Direction::Vertical)
                         .margin(0)
                         .constraints([Constraint::Length(1), Constraint::Length(40)].as_ref())
                             .split(f.size());
-                        column::render_column_mgr(column_manager_options, v_columns[1], f);
+                        mgr_list_area = Some(column::render_column_mgr(
+                            column_manager_options,
+                            theme,
+                            v_columns[1],
+                            f,
+                        ));
                     } else {
-                        // create layouts (primary vertical)
-                        let v_sections = Layout::default()
-                            .direction(Direction::Vertical)
-                            .margin(0)
-                            .constraints(constraints.as_slice())
-                            .split(f.size());
-                        
-                        for section_index in 0..geometry.len() {
-                            let v_section = v_sections[section_index + 1];
-                            let current_section = geometry[section_index].0;
-                            
+                        if tabs.len() > 1 || tab_prompt.is_some() || tab_message.is_some() {
+                            let bar = Rect::new(0, 0, f.size().width, 1);
+                            tabs::render_tab_bar(tabs, active_tab, tab_prompt, tab_message, theme, bar, f);
+                        }
+                        for (current_section, v_section) in layout.split(sections_area(f.size())) {
+                            // squeezed out by the others
+                            if v_section.width == 0 || v_section.height == 0 {
+                                continue;
+                            }
+
                             match current_section {
                                 Section::SystemInfo => {
                                     system_info::render_system_info(
                                         app,
+                                        theme,
+                                        v_section,
+                                        f,
+                                    );
+                                }
+                                Section::Sensors => {
+                                    sensors::render_sensors(
+                                        app,
+                                        theme,
+                                        v_section,
+                                        f,
+                                    );
+                                }
+                                Section::Events => {
+                                    events::render_events(
+                                        app,
+                                        theme,
+                                        event_log,
+                                        event_prompt,
+                                        v_section,
+                                        f,
+                                    );
+                                }
+                                Section::Exits => {
+                                    exits::render_exits(
+                                        app,
+                                        theme,
                                         v_section,
                                         f,
                                     );
@@ -346,50 +433,75 @@ impl<'a> TerminalRenderer<'_> {
                                     if let Some(p) = app.selected_process.as_ref() {
                                         process::render_process(
                                             app,
+                                            theme,
                                             v_section,
                                             f,
                                             process_message,
                                             p,
                                             freeze, 
-                                            tick_rate
+                                            tick_rate,
+                                            affinity_editor,
+                                            sched_editor,
+                                            limits_view,
                                         );
 
+                                    } else if delta_view.shown {
+                                        delta::render_delta(app, theme, delta_view, v_section, f);
+                                        // for paging, less the borders and the header
+                                        process_table_height = v_section.height.saturating_sub(3);
                                     } else {
+                                        // the expanded command line takes the bottom of the section
+                                        let (table_section, detail_section) = if table_view.expanded && v_section.height >= 12 {
+                                            let detail_height = (v_section.height / 2).min(7);
+                                            let parts = Layout::default()
+                                                .direction(Direction::Vertical)
+                                                .constraints([Constraint::Min(1), Constraint::Length(detail_height)].as_ref())
+                                                .split(v_section);
+                                            (parts[0], Some(parts[1]))
+                                        } else {
+                                            (v_section, None)
+                                        };
+                                        let v_section = table_section;
                                         highlighted_process = process::render_process_table(
                                             app,
+                                            theme,
                                             &process_table,
                                             v_section,
                                             *pst,
                                             f,
                                             proc_columns,
+                                            column_widths,
+                                            column_spans,
+                                            table_view,
                                             show_paths,
-                                            show_find,
-                                            show_find_cat,
-                                            show_kill,
-                                            show_suspend,
-                                            show_resume,
-                                            show_nice,
-                                            show_rate,
+                                            show_heat,
+                                            table_prompt,
                                             filter,
-                                            action_pid,
-                                            action_input,
-                                            new_rate,
-                                            process_table_message,
+                                            filter_category,
                                             highlighted_row,
                                             freeze,
                                             tick_rate,
                                         );
+                                        if let Some(area) = detail_section {
+                                            process::render_command_detail(theme, highlighted_process.as_deref(), area, f);
+                                        }
                                         if v_section.height > 4 {
                                             // account for table border & margins.
                                             process_table_height = v_section.height - 5;
+                                            process_table_area = Some(v_section);
                                         }
                                     }
                                 }
                             }
                         }
+                        if let Some(c) = confirmation {
+                            confirm::render_confirmation(c, theme, f.size(), f);
+                        }
                     }
                 })
                 .expect("Could not draw frame.");
+            self.process_table_area = process_table_area;
+            self.mgr_list_area = mgr_list_area;
 
             let event = self.events.next().expect("No new event.");
             let action = match event {
@@ -403,8 +515,14 @@ impl<'a> TerminalRenderer<'_> {
                     )
                     .await
                 }
-                Event::Resize(_, height) => {
-                    self.constraints = get_constraints(&self.section_geometry, height);
+                Event::Paste(text) => {
+                    let process_table = process_table.into_owned();
+                    self.paste(&text, &process_table, process_table_height);
+                    Action::Continue
+                }
+                Event::Mouse(mouse) => {
+                    let process_table = process_table.into_owned();
+                    self.process_mouse_event(mouse, &process_table, process_table_height);
                     Action::Continue
                 }
                 Event::Tick => {
@@ -417,7 +535,10 @@ impl<'a> TerminalRenderer<'_> {
                 _ => Action::Continue,
             };
             match action {
-                Action::Quit => break,
+                Action::Quit => {
+                    self.store_tab();
+                    return save_tabs(&self.tabs);
+                }
                 Action::Continue => {}
             }
         }
@@ -436,13 +557,19 @@ impl<'a> TerminalRenderer<'_> {
             self.app.selected_process.is_some() || self.selection_grace_start.is_some();
 
         if !self.freeze {
+            self.app.procfs_reads = self
+                .proc_columns
+                .iter()
+                .fold(ProcfsReads::default(), |reads, c| reads.union(c.procfs_reads()));
             self.app.update(keep_order).await;
             self.update_number += 1;
             if self.update_number == self.zoom_factor {
                 self.update_number = 0;
             }
+            // frozen, there's no fresh update to tell whether a stopped process is still running
+            self.check_stops().await;
+            self.retry_select();
         }
-       
     }
 
     async fn process_key_event(
@@ -452,6 +579,22 @@ impl<'a> TerminalRenderer<'_> {
         process_table_height: u16,
         highlighted_process: Option<Box<MProcess>>,
     ) -> Action {
+        if input.code == Key::Char('c') && input.modifiers.contains(KeyModifiers::CONTROL) {
+            return Action::Quit;
+        }
+        if self.confirmation.is_some() {
+            self.process_confirmation_input(input).await;
+            return Action::Continue;
+        }
+        match input.code {
+            // the table still pages under a prompt
+            Key::PageUp | Key::PageDown => {}
+            _ if self.prompt.is_some() => {
+                self.process_prompt_input(input, process_table, process_table_height).await;
+                return Action::Continue;
+            }
+            _ => {}
+        }
         match input.code {
             Key::Up => self.view_up(process_table, 1),
             Key::PageUp => self.view_up(process_table, process_table_height.into()),
@@ -468,126 +611,39 @@ impl<'a> TerminalRenderer<'_> {
                 process_table.len(),
             ),
             Key::Enter => {
-                if self.show_kill {
-                    if self.action_pid.chars().all(|c| c.is_digit(10)) && !self.action_pid.is_empty() {
-                        self.process_table_message = match hproc::get(self.action_pid.parse().unwrap()).await {
-                            Ok(p) => convert_result_to_string!(p.kill().await),
-                            Err(e) => convert_error_to_string!(e),
-                        };
-                    } else {
-                        self.process_table_message = "Invalid PID".to_string();
-                    }
-                    self.action_pid = String::new();
-                } else if self.show_suspend {
-                    if self.action_pid.chars().all(|c| c.is_digit(10)) && !self.action_pid.is_empty() {
-                        self.process_table_message = match hproc::get(self.action_pid.parse().unwrap()).await {
-                            Ok(p) => convert_result_to_string!(p.suspend().await),
-                            Err(e) => convert_error_to_string!(e),
-                        };
-                    } else {
-                        self.process_table_message = "Invalid PID".to_string();
-                    }
-                    self.action_pid = String::new();
-
-                } else if self.show_resume {
-                    if self.action_pid.chars().all(|c| c.is_digit(10)) && !self.action_pid.is_empty() {
-                        self.process_table_message = match hproc::get(self.action_pid.parse().unwrap()).await {
-                            Ok(p) => convert_result_to_string!(p.resume().await),
-                            Err(e) => convert_error_to_string!(e),
-                        };
-                    } else {
-                        self.process_table_message = "Invalid PID".to_string();
-                    }
-                    self.action_pid = String::new();
-
-                } else if self.show_nice && self.process_table_message == " Choose a nice value (-20 to 19): ".to_string() {
-                    // Set the priority of the process to the specified value
-                    let nice_value: i32 = self.action_input.parse().unwrap();
-                    if nice_value < -20 || nice_value > 19 {
-                        self.process_table_message = "Invalid nice value".to_string();  
-                    } else {
-                        let result = unsafe { libc::setpriority(libc::PRIO_PROCESS, self.action_pid.parse().unwrap(), self.action_input.parse().unwrap()) };
-                        if result == -1 {
-                            self.process_table_message = "Failed to set process priority".to_string();
-                        } else {
-                            self.process_table_message = "Process priority set successfully".to_string();
-                        }  
-                    }
-
-                    self.action_pid = String::new();
-                    self.action_input = String::new();                  
-                } else if self.show_nice {
-                    if self.action_pid.is_empty() || !self.action_pid.chars().all(|c| c.is_digit(10)) {
-                        self.process_table_message = "Invalid PID".to_string();
-                        self.action_pid = String::new();
-                    } else {
-                        // Check if the process exists
-                        let process = self.app.system.get_process(self.action_pid.parse().unwrap()).is_some(); 
-                        if process {
-                            self.process_table_message = " Choose a nice value (-20 to 19): ".to_string();
-                        } else {
-                            self.process_table_message = "No Such Process".to_string();
-                            self.action_pid = String::new();
-                        }
-                    }
-                } else if self.show_rate {
-                    if self.new_rate.chars().all(|c| c.is_digit(10)) && !self.new_rate.is_empty(){
-                        let r: u64 = self.new_rate.parse().unwrap();
-                        if r < 1000{
-                            self.process_table_message = "The rate must be at least 1000 millis".to_string();
-                        }
-                        else{
-                            self.tick_rate = r;
-                            self.app.change_tick(Duration::from_millis(self.tick_rate));
-                            self.process_table_message = "The rate has been set".to_string();
-                        }
-                    }
-                    else{
-                        self.process_table_message = "Invalid rate".to_string();
-                    }
-                    self.new_rate = String::new();
-                } 
-                else {
+                if self.show_affinity {
+                    self.apply_affinity();
+                } else if self.sched_editor.is_some() {
+                    self.apply_sched_editor();
+                } else if self.show_limits {
+                    self.edit_limit();
+                } else {
                     self.select(highlighted_process);
                 }
             }
-            Key::Char('c') => {
-                if input.modifiers.contains(KeyModifiers::CONTROL) {
-                    return Action::Quit;
-                } else if self.show_find || self.show_find_cat{
-                    self.process_find_input(input);
-                } else if self.show_kill {
-                    self.process_kill_input(input);
-                } else if self.show_suspend {
-                    self.process_suspend_input(input);
-                } else if self.show_resume {
-                    self.process_resume_input(input);
-                } else if self.show_nice {
-                    self.process_nice_input(input);
-                } else if self.show_rate{
-                    self.process_rate_input(input);
-                }
-                else{
-                    self.show_find_cat = true;
-                    self.highlighted_row = 0;
-                    self.process_table_row_start = 0;
-                }
-            }
             _other => {
-                if self.show_find || self.show_find_cat{
-                    self.process_find_input(input);
-                } else if self.show_kill {
-                    self.process_kill_input(input);
-                } else if self.show_suspend {
-                    self.process_suspend_input(input);
-                } else if self.show_resume {
-                    self.process_resume_input(input);
-                } else if self.show_nice && self.process_table_message == " Choose a nice value (-20 to 19): ".to_string() {
-                    self.process_nice_value_input(input);
-                } else if self.show_nice {
-                    self.process_nice_input(input);
-                } else if self.show_rate{
-                    self.process_rate_input(input);
+                if self.show_affinity {
+                    self.process_affinity_input(input);
+                } else if self.sched_editor.is_some() {
+                    self.process_sched_input(input);
+                } else if self.show_limits {
+                    self.process_limits_input(input);
+                } else if self.event_log.focused && self.process_event_log_input(input) {
+                    // handled by the event log
+                } else if self.delta_view.shown
+                    && self.app.selected_process.is_none()
+                    && self.process_delta_input(input)
+                {
+                    // handled by the delta view
+                } else if self.show_section_mgr && self.process_section_mgr_input(input) {
+                    // handled by the section manager
+                } else if self.show_column_mgr && self.process_column_mgr_input(input) {
+                    // handled by the column manager
+                } else if !self.table_view.search.is_empty()
+                    && self.app.selected_process.is_none()
+                    && self.process_search_result_input(input, process_table, process_table_height)
+                {
+                    // moved between the search matches
                 } else {
                     return self.process_toplevel_input(input).await;
                 }
@@ -601,18 +657,82 @@ impl<'a> TerminalRenderer<'_> {
         if selected == Section::Process {
             self.app.select_process(highlighted_process);
             self.process_message = None;
-            self.process_table_message = String::from("");
-            self.show_find = false;
-            self.show_find_cat = false;
-            self.show_kill = false;
-            self.show_suspend = false;
-            self.show_resume = false;
-            self.show_nice = false;
-            self.show_rate = false;
+            self.prompt = None;
             self.process_table_row_start = 0;
         }
     }
 
+    fn process_mouse_event(
+        &mut self,
+        event: MouseEvent,
+        process_table: &[i32],
+        process_table_height: u16,
+    ) {
+        match event.kind {
+            MouseEventKind::ScrollUp => self.view_up(process_table, MOUSE_SCROLL_LINES),
+            MouseEventKind::ScrollDown => {
+                self.view_down(process_table, process_table_height.into(), MOUSE_SCROLL_LINES)
+            }
+            MouseEventKind::Down(MouseButton::Left) => {
+                self.click(event.column, event.row, process_table)
+            }
+            _ => {}
+        }
+    }
+
+    fn click(&mut self, x: u16, y: u16, process_table: &[i32]) {
+        if self.show_help || self.confirmation.is_some() {
+            return;
+        }
+        if self.show_section_mgr || self.show_column_mgr {
+            // inside the borders
+            let area = match self.mgr_list_area {
+                Some(area) if contains(area, x, y) && y > area.y && y + 1 < area.y + area.height => area,
+                _ => return,
+            };
+            let row = (y - area.y - 1) as usize;
+            // the section list is short enough to never scroll
+            if self.show_section_mgr && row < self.section_manager_options.items.len() {
+                self.section_manager_options.state.select(Some(row));
+                self.toggle_section();
+            } else if let Some(idx) = self.column_manager_options.item_at(row).filter(|_| self.show_column_mgr) {
+                self.column_manager_options.state.select(Some(idx));
+                self.toggle_section();
+            }
+            return;
+        }
+
+        let area = match self.process_table_area {
+            Some(area) if contains(area, x, y) => area,
+            _ => return,
+        };
+        // below the top border comes the header, then the rows
+        if y == area.y + 1 {
+            let x = x.saturating_sub(area.x + 1);
+            if let Some(column) = process::column_at(&self.column_spans, x) {
+                self.sort_by_column(column);
+            }
+        } else if y > area.y + 1 && y < area.y + area.height - 1 {
+            let row = self.process_table_row_start + (y - area.y - 2) as usize;
+            if row >= process_table.len() {
+                return;
+            }
+            self.selection_grace_start = Some(Instant::now());
+            self.highlighted_row = row;
+            let double_click = matches!(
+                self.last_click,
+                Some((at, r)) if r == row && at.elapsed() < DOUBLE_CLICK_INTERVAL
+            );
+            if double_click {
+                self.last_click = None;
+                let p = self.app.process_map.get(&process_table[row]).map(|p| Box::new(p.clone()));
+                self.select(p);
+            } else {
+                self.last_click = Some((Instant::now(), row));
+            }
+        }
+    }
+
     fn view_up(&mut self, process_table: &[i32], delta: usize) {
         let selected = self.selected_section();
         if self.show_section_mgr {
@@ -637,6 +757,18 @@ impl<'a> TerminalRenderer<'_> {
                 }
                 None => self.section_manager_options.state.select(Some(0)),
             }
+        } else if self.event_log.focused {
+            self.event_log.scroll_up(delta);
+        } else if self.delta_view.shown && self.app.selected_process.is_none() {
+            self.delta_view.scroll_up(delta);
+        } else if self.show_affinity {
+            self.affinity_cursor = self.affinity_cursor.saturating_sub(delta);
+        } else if let Some(editor) = self.sched_editor.as_mut() {
+            editor.cursor = editor.cursor.saturating_sub(delta);
+        } else if self.show_limits {
+            if self.prompt.is_none() {
+                self.limits_cursor = self.limits_cursor.saturating_sub(delta);
+            }
         } else if selected == Section::Process {
             if self.app.selected_process.is_some() || process_table.is_empty() {
                 return;
@@ -679,6 +811,27 @@ impl<'a> TerminalRenderer<'_> {
                 }
                 None => self.column_manager_options.state.select(Some(0)),
             }
+        } else if self.event_log.focused {
+            self.event_log.scroll_down(delta, &self.app);
+        } else if self.delta_view.shown && self.app.selected_process.is_none() {
+            self.delta_view.scroll_down(delta, &self.app);
+        } else if self.show_affinity {
+            self.affinity_cursor = min(
+                self.affinity_cursor + delta,
+                self.affinity_selection.len().saturating_sub(1),
+            );
+        } else if let Some(editor) = self.sched_editor.as_mut() {
+            editor.cursor = min(editor.cursor + delta, SchedEditor::ROWS - 1);
+        } else if self.show_limits {
+            let count = self
+                .app
+                .selected_process
+                .as_ref()
+                .and_then(|p| p.limits.as_ref())
+                .map_or(0, |l| l.len());
+            if self.prompt.is_none() {
+                self.limits_cursor = min(self.limits_cursor + delta, count.saturating_sub(1));
+            }
         } else if selected == Section::Process {
             if self.app.selected_process.is_some() || process_table.is_empty() {
                 return;
@@ -699,215 +852,719 @@ impl<'a> TerminalRenderer<'_> {
         }
     }
 
-    fn process_find_input(&mut self, input: KeyEvent) {
-        match input.code {
-            Key::Esc => {
-                self.show_find = false;
-                self.show_find_cat = false;
-                self.filter = String::from("");
+    /// Keys while a prompt is open, only the page keys get past it.
+    async fn process_prompt_input(&mut self, input: KeyEvent, process_table: &[i32], process_table_height: u16) {
+        let prompt = match self.prompt.as_mut() {
+            Some(p) => p,
+            None => return,
+        };
+        match prompt.editor.handle_key(input) {
+            Edit::Changed => self.prompt_changed(process_table, process_table_height),
+            edit @ (Edit::Older | Edit::Newer) => {
+                prompt.recall(&self.prompt_history, edit == Edit::Older);
+                self.prompt_changed(process_table, process_table_height);
             }
-            Key::Char(c) if c != '\n' => {
-                self.selection_grace_start = Some(Instant::now());
-                self.filter.push(c)
+            Edit::Submit => self.submit_prompt().await,
+            Edit::Cancel => self.cancel_prompt(),
+            Edit::Ignored if input.code == Key::F(3) && prompt.kind == PromptKind::Search => {
+                self.jump_to_match(process_table, process_table_height, self.highlighted_row + 1, true);
             }
-            Key::Delete => match self.filter.pop() {
-                Some(_c) => {}
-                None => {self.show_find = false; self.show_find_cat = false;},
-            },
-            Key::Backspace => match self.filter.pop() {
-                Some(_c) => {}
-                None => {self.show_find = false; self.show_find_cat = false;},
-            },
-            _ => {}
+            Edit::Moved | Edit::Ignored => {}
         }
     }
 
-    fn process_kill_input(&mut self, input: KeyEvent) {
-        match input.code {
-            Key::Esc => {
-                self.show_kill = false;
-                self.action_pid = String::from("");
-                self.process_table_message = String::from("");
+    fn paste(&mut self, text: &str, process_table: &[i32], process_table_height: u16) {
+        if let Some(prompt) = self.prompt.as_mut() {
+            prompt.editor.insert_str(text);
+            self.prompt_changed(process_table, process_table_height);
+        }
+    }
+
+    /// The filters and the search follow the text as it's typed.
+    fn prompt_changed(&mut self, process_table: &[i32], process_table_height: u16) {
+        let prompt = match self.prompt.as_mut() {
+            Some(p) => p,
+            None => return,
+        };
+        prompt.message = None;
+        prompt.error = None;
+        let text = String::from(prompt.text());
+        match prompt.kind {
+            PromptKind::Search => {
+                self.table_view.search = text;
+                // stays on the highlighted process as long as it matches
+                self.jump_to_match(process_table, process_table_height, self.highlighted_row, true);
+            }
+            PromptKind::EventFilter => {
+                self.event_log.filter = text;
+                self.event_log.scroll = 0;
             }
-            Key::Char(c) if c != '\n' => {
+            PromptKind::Filter | PromptKind::Category => {
                 self.selection_grace_start = Some(Instant::now());
-                self.process_table_message = String::from("");
-                self.action_pid.push(c)
+                self.filter = text;
             }
-            Key::Delete => match self.action_pid.pop() {
-                Some(_c) => {}
-                None => self.show_kill = false,
-            },
-            Key::Backspace => match self.action_pid.pop() {
-                Some(_c) => {}
-                None => self.show_kill = false,
-            },
+            _ => self.selection_grace_start = Some(Instant::now()),
+        }
+    }
+
+    /// Esc takes back the filter and the search, the other prompts just close.
+    fn cancel_prompt(&mut self) {
+        match self.prompt.take().map(|p| p.kind) {
+            Some(PromptKind::Filter) | Some(PromptKind::Category) => {
+                self.filter = String::new();
+                self.filter_category = false;
+                self.save_tab();
+            }
+            Some(PromptKind::Search) => self.table_view.search = String::new(),
             _ => {}
         }
     }
 
-    fn process_suspend_input(&mut self, input: KeyEvent) {
+    /// Enter at the open prompt. An answer that can't be used leaves the prompt open with
+    /// the reason, the PID prompts stay open for the next PID.
+    async fn submit_prompt(&mut self) {
+        let mut prompt = match self.prompt.take() {
+            Some(p) => p,
+            None => return,
+        };
+        let text = String::from(prompt.text());
+        if let Some(error) = prompt.kind.check(&text) {
+            prompt.error = Some(error);
+            self.prompt = Some(prompt);
+            return;
+        }
+        self.prompt_history.add(prompt.kind, &text);
+        let number = text.trim().parse::<i64>().unwrap_or_default();
+        match prompt.kind {
+            // applied while typing, the tab keeps the filter
+            PromptKind::Filter | PromptKind::Category => self.save_tab(),
+            PromptKind::Search | PromptKind::EventFilter => {}
+            PromptKind::Kill | PromptKind::Suspend | PromptKind::Resume => {
+                let signal = match prompt.kind {
+                    PromptKind::Kill => ProcessSignal::Kill,
+                    PromptKind::Suspend => ProcessSignal::Suspend,
+                    _ => ProcessSignal::Resume,
+                };
+                // the outcome is shown at the prompt
+                self.prompt = Some(prompt);
+                self.request_signal(number as i32, signal).await;
+            }
+            PromptKind::NicePid => {
+                if self.app.process_map.contains_key(&(number as i32)) {
+                    self.prompt = Some(Prompt::new(PromptKind::NiceValue(number as i32)));
+                } else {
+                    prompt.error = Some("No Such Process");
+                    self.prompt = Some(prompt);
+                }
+            }
+            PromptKind::NiceValue(pid) => {
+                let message = match self.app.renice_process(pid, number as i32) {
+                    Ok(_) => String::from("Process priority set successfully"),
+                    Err(e) => format!("Failed to set process priority: {}", e),
+                };
+                let mut next = Prompt::new(PromptKind::NicePid);
+                next.answered(message);
+                self.prompt = Some(next);
+            }
+            PromptKind::Rate => {
+                self.tick_rate = number as u64;
+                self.app.change_tick(Duration::from_millis(self.tick_rate));
+            }
+            PromptKind::Run => {
+                if let Err(e) = self.run_command(&text).await {
+                    prompt.message = Some(e);
+                    self.prompt = Some(prompt);
+                }
+            }
+            PromptKind::TabName => self.rename_tab(&text),
+            PromptKind::Limit => self.apply_limit(&text),
+        }
+    }
+
+    /// Keys of the process table while search matches are shown, false if `input` isn't one.
+    fn process_search_result_input(&mut self, input: KeyEvent, process_table: &[i32], process_table_height: u16) -> bool {
         match input.code {
+            Key::Char('N') | Key::F(3) if input.code == Key::Char('N') || input.modifiers.contains(KeyModifiers::SHIFT) => {
+                let previous = (self.highlighted_row + process_table.len()).saturating_sub(1);
+                self.jump_to_match(process_table, process_table_height, previous, false);
+            }
+            Key::Char('n') | Key::F(3) => {
+                self.jump_to_match(process_table, process_table_height, self.highlighted_row + 1, true);
+            }
             Key::Esc => {
-                self.show_suspend = false;
-                self.action_pid = String::from("");
-                self.process_table_message = String::from("");
+                self.table_view.search = String::new();
             }
-            Key::Char(c) if c != '\n' => {
-                self.selection_grace_start = Some(Instant::now());
-                self.process_table_message = String::from("");
-                self.action_pid.push(c)
+            _ => return false,
+        }
+        true
+    }
+
+    /// Highlight the first process matching the search from the row `from` on.
+    fn jump_to_match(&mut self, process_table: &[i32], process_table_height: u16, from: usize, forward: bool) {
+        let row = match process::find_match(&self.app, process_table, &self.table_view.search, from, forward) {
+            Some(row) => row,
+            None => return,
+        };
+        // keep the order while moving between matches, like the arrow keys
+        self.selection_grace_start = Some(Instant::now());
+        self.highlighted_row = row;
+        let height = process_table_height as usize;
+        if row < self.process_table_row_start {
+            self.process_table_row_start = row;
+        } else if row > self.process_table_row_start + height {
+            self.process_table_row_start = row - height;
+        }
+    }
+
+    fn open_affinity_editor(&mut self) {
+        let cpus = self.app.cpus.len();
+        match self.app.selected_process.as_ref().and_then(|p| p.affinity.as_ref()) {
+            Some(allowed) => {
+                self.affinity_selection = (0..cpus).map(|i| allowed.contains(&i)).collect();
+                self.affinity_cursor = 0;
+                self.show_affinity = true;
             }
-            Key::Delete => match self.action_pid.pop() {
-                Some(_c) => {}
-                None => self.show_suspend = false,
-            },
-            Key::Backspace => match self.action_pid.pop() {
-                Some(_c) => {}
-                None => self.show_suspend = false,
-            },
-            _ => {}
+            None => self.process_message = Some(String::from("Couldn't read affinity.")),
         }
     }
 
-    fn process_resume_input(&mut self, input: KeyEvent) {
+    fn apply_affinity(&mut self) {
+        let cpus: Vec<usize> = self
+            .affinity_selection
+            .iter()
+            .enumerate()
+            .filter(|(_, allowed)| **allowed)
+            .map(|(i, _)| i)
+            .collect();
+        self.process_message = self
+            .app
+            .selected_process
+            .as_mut()
+            .map(|p| p.set_affinity(&cpus));
+        self.show_affinity = false;
+    }
+
+    fn process_affinity_input(&mut self, input: KeyEvent) {
         match input.code {
-            Key::Esc => {
-                self.show_resume = false;
-                self.action_pid = String::from("");
-                self.process_table_message = String::from("");
+            Key::Esc => self.show_affinity = false,
+            Key::Char(' ') => {
+                if let Some(allowed) = self.affinity_selection.get_mut(self.affinity_cursor) {
+                    *allowed = !*allowed;
+                }
             }
-            Key::Char(c) if c != '\n' => {
-                self.selection_grace_start = Some(Instant::now());
-                self.process_table_message = String::from("");
-                self.action_pid.push(c)
+            Key::Char('a') => {
+                let all = self.affinity_selection.iter().all(|a| *a);
+                self.affinity_selection.iter_mut().for_each(|a| *a = !all);
             }
-            Key::Delete => match self.action_pid.pop() {
-                Some(_c) => {}
-                None => self.show_resume = false,
-            },
-            Key::Backspace => match self.action_pid.pop() {
-                Some(_c) => {}
-                None => self.show_resume = false,
-            },
             _ => {}
         }
     }
 
-    fn process_nice_input(&mut self, input: KeyEvent) {
+    fn apply_sched_editor(&mut self) {
+        let editor = match self.sched_editor.take() {
+            Some(e) => e,
+            None => return,
+        };
+        if let Some(p) = self.app.selected_process.as_mut() {
+            let mut messages = vec![];
+            if p.sched_policy != Some(editor.policy)
+                || (editor.policy.is_realtime() && p.rt_priority != editor.rt_priority)
+            {
+                messages.push(p.set_scheduler(editor.policy, editor.rt_priority));
+            }
+            let io = p.io_priority.map(|io| (io.class, io.level));
+            if io != Some((editor.io_class, editor.io_level)) {
+                messages.push(p.set_io_priority(editor.io_class, editor.io_level));
+            }
+            self.process_message = if messages.is_empty() {
+                Some(String::from("Nothing changed."))
+            } else {
+                Some(messages.join(" "))
+            };
+        }
+    }
+
+    fn process_sched_input(&mut self, input: KeyEvent) {
         match input.code {
-            Key::Esc => {
-                self.show_nice = false;
-                self.action_pid = String::from("");
-                self.process_table_message = String::from("");
+            Key::Esc => self.sched_editor = None,
+            Key::Left => {
+                if let Some(e) = self.sched_editor.as_mut() {
+                    e.change(-1);
+                }
             }
-            Key::Char(c) if c != '\n' => {
-                self.selection_grace_start = Some(Instant::now());
-                self.process_table_message = String::from("");
-                self.action_pid.push(c)
+            Key::Right => {
+                if let Some(e) = self.sched_editor.as_mut() {
+                    e.change(1);
+                }
             }
-            Key::Delete => match self.action_pid.pop() {
-                Some(_c) => {}
-                None => self.show_nice = false,
-            },
-            Key::Backspace => match self.action_pid.pop() {
-                Some(_c) => {}
-                None => self.show_nice = false,
-            },
             _ => {}
         }
     }
 
-    fn process_nice_value_input(&mut self, input: KeyEvent) {
+    /// Enter edits the highlighted limit, prefilled with its current values.
+    fn edit_limit(&mut self) {
+        let cursor = self.limits_cursor;
+        let current = self
+            .app
+            .selected_process
+            .as_ref()
+            .and_then(|p| p.limits.as_ref())
+            .and_then(|l| l.get(cursor))
+            .map(|l| format!("{} {}", format_limit_value(l.soft), format_limit_value(l.hard)));
+        if let Some(current) = current {
+            self.prompt = Some(Prompt::with_text(PromptKind::Limit, &current));
+        }
+    }
+
+    fn apply_limit(&mut self, input: &str) {
+        let cursor = self.limits_cursor;
+        let p = match self.app.selected_process.as_mut() {
+            Some(p) => p,
+            None => return,
+        };
+        let values: Vec<Option<Option<u64>>> =
+            input.split_whitespace().map(parse_limit_value).collect();
+        self.process_message = Some(match values.as_slice() {
+            [Some(soft), Some(hard)] => p.set_limit(cursor, *soft, *hard),
+            // a single value only changes the soft limit
+            [Some(soft)] => {
+                let hard = p
+                    .limits
+                    .as_ref()
+                    .and_then(|l| l.get(cursor))
+                    .and_then(|l| l.hard);
+                p.set_limit(cursor, *soft, hard)
+            }
+            _ => String::from("Invalid limit, enter \"<soft> <hard>\" or \"unlimited\"."),
+        });
+    }
+
+    fn process_limits_input(&mut self, input: KeyEvent) {
+        match input.code {
+            Key::Esc | Key::Char('l') => self.show_limits = false,
+            _ => {}
+        }
+    }
+
+    /// Keys for the focused event log, returns false for keys it leaves to the top level.
+    fn process_event_log_input(&mut self, input: KeyEvent) -> bool {
+        let view = &mut self.event_log;
         match input.code {
-            Key::Esc => {
-                self.show_nice = false;
-                self.action_pid = String::from("");
-                self.action_input = String::from("");
+            Key::Char('/') => {
+                self.prompt = Some(Prompt::with_text(PromptKind::EventFilter, &view.filter));
             }
-            Key::Char(c) if c != '\n' => {
-                self.selection_grace_start = Some(Instant::now());
-                self.action_input.push(c)
+            Key::Esc | Key::Char('v') => view.focused = false,
+            _ => return false,
+        }
+        true
+    }
+
+    /// Sort keys for the delta view, returns false for keys it leaves to the top level.
+    fn process_delta_input(&mut self, input: KeyEvent) -> bool {
+        let view = &mut self.delta_view;
+        match input.code {
+            Key::Char('.') | Key::Char('>') => view.sort = view.sort.cycle(false),
+            Key::Char(',') | Key::Char('<') => view.sort = view.sort.cycle(true),
+            Key::Char(';') => {
+                view.order = match view.order {
+                    ProcessTableSortOrder::Ascending => ProcessTableSortOrder::Descending,
+                    ProcessTableSortOrder::Descending => ProcessTableSortOrder::Ascending,
+                };
+            }
+            Key::Esc => view.shown = false,
+            _ => return false,
+        }
+        true
+    }
+
+    /// Send `signal`, after asking when it stops or ends a process. Protected processes are
+    /// refused or need their name typed, even when confirming is turned off.
+    async fn request_signal(&mut self, pid: i32, signal: ProcessSignal) {
+        self.request_signal_or_stop(pid, signal, None).await;
+    }
+
+    /// SIGTERM, then SIGKILL if the process is still running after the stop timeout.
+    async fn request_stop(&mut self, pid: i32) {
+        let timeout = self.signal_guard.stop_timeout;
+        self.request_signal_or_stop(pid, ProcessSignal::Terminate, Some(timeout)).await;
+    }
+
+    async fn request_signal_or_stop(&mut self, pid: i32, signal: ProcessSignal, stop: Option<Duration>) {
+        if signal == ProcessSignal::Resume {
+            let start_time = self.process(pid).map(|p| p.start_time);
+            let result = self.app.signal_process(pid, signal, start_time).await;
+            self.signal_sent(signal_message(result));
+            return;
+        }
+        let p = self.process(pid);
+        match p.and_then(|p| self
+            .signal_guard
+            .check(p, &self.app.process_map, self.app.remote.is_none())) {
+            Some((Protection::Refuse, reason)) => {
+                let name = p.map_or("", |p| p.name.as_str());
+                let action = if stop.is_some() { "stop" } else { signal.as_str() };
+                self.signal_sent(format!("Refused to {} PID {} {}: {}.", action, pid, name, reason));
+            }
+            Some((Protection::Confirm, reason)) => {
+                self.confirmation = Some(Confirmation {
+                    stop,
+                    ..Confirmation::new(pid, p, signal, Some(reason))
+                });
+            }
+            None if self.signal_guard.confirm => {
+                self.confirmation = Some(Confirmation {
+                    stop,
+                    ..Confirmation::new(pid, p, signal, None)
+                });
+            }
+            None => self.send_signal(pid, signal, stop).await,
+        }
+    }
+
+    /// A stop waits for the process to exit when SIGTERM went out.
+    async fn send_signal(&mut self, pid: i32, signal: ProcessSignal, stop: Option<Duration>) {
+        let start_time = self.process(pid).map(|p| p.start_time);
+        let result = self.app.signal_process(pid, signal, start_time).await;
+        match (stop, self.process(pid), result) {
+            (Some(timeout), Some(p), Ok(_)) => {
+                let stop = Stop::new(p, timeout);
+                self.stops.retain(|s| s.pid != pid);
+                self.stops.push(stop);
+                // the countdown shows in the process view
+                if self.app.selected_process.as_ref().map(|p| p.pid) != Some(pid) {
+                    self.signal_sent(signal_message(Ok(())));
+                }
+            }
+            (_, _, result) => self.signal_sent(signal_message(result)),
+        }
+    }
+
+    /// The selected process if it's `pid`, otherwise the one in the latest update.
+    fn process(&self, pid: i32) -> Option<&MProcess> {
+        self.app
+            .selected_process
+            .as_deref()
+            .filter(|p| p.pid == pid)
+            .or_else(|| self.app.process_map.get(&pid))
+    }
+
+    /// After an update, report the stopped processes that exited and kill the ones still
+    /// running past the timeout.
+    async fn check_stops(&mut self) {
+        for stop in std::mem::take(&mut self.stops) {
+            let message = match stop.state(&self.app) {
+                StopState::Waiting => {
+                    self.stops.push(stop);
+                    continue;
+                }
+                StopState::Exited => stop.exited_message(),
+                // the update may be a tick old, the PID is checked again right before SIGKILL
+                StopState::Overdue => {
+                    let result = self
+                        .app
+                        .signal_process(stop.pid, ProcessSignal::Kill, Some(stop.start_time()))
+                        .await;
+                    stop.killed_message(&result)
+                }
+            };
+            if self.app.selected_process.as_ref().map(|p| p.pid) == Some(stop.pid) {
+                self.process_message = Some(message);
+            } else {
+                self.tab_message = Some(message);
             }
-            Key::Delete => match self.action_input.pop() {
-                Some(_c) => {}
-                None => self.show_nice = false,
+        }
+    }
+
+    /// The outcome goes to the prompt the PID was typed at, or the process view.
+    fn signal_sent(&mut self, message: String) {
+        match self.prompt.as_mut() {
+            Some(prompt) => prompt.answered(message),
+            None => self.process_message = Some(message),
+        }
+    }
+
+    /// `y`/`n`, or the name of a protected process and Enter.
+    async fn process_confirmation_input(&mut self, input: KeyEvent) {
+        let c = match self.confirmation.as_mut() {
+            Some(c) => c,
+            None => return,
+        };
+        let confirmed = match c.protected.as_mut() {
+            Some((_, editor)) => match editor.handle_key(input) {
+                Edit::Submit if editor.text().trim() == c.name => true,
+                Edit::Submit => {
+                    c.mismatch = true;
+                    return;
+                }
+                Edit::Cancel => false,
+                _ => {
+                    c.mismatch = false;
+                    return;
+                }
             },
-            Key::Backspace => match self.action_input.pop() {
-                Some(_c) => {}
-                None => self.show_nice = false,
+            None => match input.code {
+                Key::Char('y') | Key::Char('Y') => true,
+                Key::Char('n') | Key::Char('N') | Key::Esc => false,
+                _ => return,
             },
-            _ => {}
+        };
+        let (pid, signal, stop) = (c.pid, c.signal, c.stop);
+        self.confirmation = None;
+        if confirmed {
+            self.send_signal(pid, signal, stop).await;
+        } else {
+            let action = if stop.is_some() { "stop" } else { signal.as_str() };
+            self.signal_sent(format!("Didn't {} PID {}.", action, pid));
         }
     }
 
-    fn process_rate_input(&mut self, input: KeyEvent) {
-        match input.code {
-           Key::Esc => {
-               self.show_rate = false;
-               self.process_table_message = String::from("");
-               self.new_rate = String::from("");
-           }
-           Key::Char(c) if c != '\n' => {
-               self.selection_grace_start = Some(Instant::now());
-               self.process_table_message = String::from("");
-               self.new_rate.push(c)
-           }
-           Key::Delete => match self.new_rate.pop() {
-               Some(_c) => {}
-               None => self.show_rate = false,
-           },
-           Key::Backspace => match self.new_rate.pop() {
-               Some(_c) => {}
-               None => self.show_rate = false,
-           },
-           _ => {}
-       }
-   }
-
-    fn recompute_constraints(&mut self) {
-        self.selected_section_index = self.section_geometry.len()-1;
-        if self.section_geometry.len() == 1 {
-            self.section_geometry[0].1 = 100.0;
+    fn renice_message(&mut self, pid: i32, nice: i32) -> String {
+        match self.app.renice_process(pid, nice) {
+            Ok(_) => String::from("Priority Set."),
+            Err(e) => e,
+        }
+    }
+
+    async fn run_command(&mut self, command: &str) -> Result<(), String> {
+        if self.app.remote.is_some() {
+            return Err(String::from(NOT_AVAILABLE_REMOTELY));
+        }
+        let spawned = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs());
+        let pid = LaunchSpec::parse(command).and_then(|spec| spec.spawn())?;
+        self.pending_select = Some(PendingSelect {
+            pid,
+            spawned,
+            updates_left: PENDING_SELECT_UPDATES,
+        });
+        // refresh now so the new process can be focused straight away
+        self.app.update(true).await;
+        self.retry_select();
+        Ok(())
+    }
+
+    /// Focus the started command once it's in an update, giving up after a few updates
+    /// in case it exited before the first.
+    fn retry_select(&mut self) {
+        let pending = match self.pending_select.as_mut() {
+            Some(pending) => pending,
+            None => return,
+        };
+        // start times are whole seconds, rounded either way
+        let started = self
+            .app
+            .process_map
+            .get(&pending.pid)
+            .filter(|p| p.start_time + 1 >= pending.spawned);
+        if let Some(p) = started {
+            let p = Box::new(p.clone());
+            let pid = pending.pid;
+            self.pending_select = None;
+            self.select(Some(p));
+            self.process_message = Some(format!("Started PID {}.", pid));
+        } else if pending.updates_left <= 1 {
+            let message = format!("PID {} exited before it showed up.", pending.pid);
+            self.pending_select = None;
+            // the table is still shown, not the process view
+            match self.prompt.as_mut() {
+                Some(prompt) => prompt.answered(message),
+                None => self.tab_message = Some(message),
+            }
         } else {
-            self.section_geometry[0].1 = 18.0;
-            self.section_geometry[1].1 = 82.0;
+            pending.updates_left -= 1;
         }
-        let new_geometry = self.section_geometry.clone();
+    }
+
+    /// Rebuild the section manager after the layout changed, keeping its selection.
+    fn update_sections(&mut self) {
+        self.selected_section_index = default_section_index(&self.layout);
         let selected = self.section_manager_options.state.selected();
-        self.section_manager_options = SectionMGRList::with_geometry(new_geometry);
+        self.section_manager_options = SectionMGRList::with_layout(&self.layout);
         self.section_manager_options.state.select(selected);
-        self.constraints = get_constraints(self.section_geometry.as_slice(), terminal_size().1);
     }
 
+    /// Resize keys of the section manager, false for the others.
+    fn process_section_mgr_input(&mut self, input: KeyEvent) -> bool {
+        let section = match self.section_manager_options.selected() {
+            Some(s) if self.layout.contains(s) => s,
+            _ => return false,
+        };
+        let (vertical, delta) = match input.code {
+            Key::Char('+') => (true, 1),
+            Key::Char('-') => (true, -1),
+            Key::Char('>') => (false, 1),
+            Key::Char('<') => (false, -1),
+            _ => return false,
+        };
+        let (width, height) = terminal_size();
+        let area = sections_area(Rect::new(0, 0, width, height));
+        self.layout.resize(section, vertical, delta, area);
+        self.update_sections();
+        true
+    }
+
+    /// Rebuild the column manager, keeping the same column selected, and save the layout.
     fn update_columns(&mut self) {
-        let new_proc_cols = self.proc_columns.clone();
-        let selected = self.column_manager_options.state.selected();
-        self.column_manager_options = ColumnMGRList::with_cols(new_proc_cols);
-        self.column_manager_options.state.select(selected);
+        let selected = self.column_manager_options.selected();
+        self.column_manager_options = ColumnMGRList::with_cols(&self.proc_columns, &self.column_widths);
+        if let Some(c) = selected {
+            self.column_manager_options.select_column(c);
+        }
+        self.store_tab();
+        self.column_manager_options.message = save_tabs(&self.tabs).err();
+    }
+
+    /// Store the active tab and save the tabs, after changing its filter, sort or columns.
+    fn save_tab(&mut self) {
+        self.store_tab();
+        self.tab_message = save_tabs(&self.tabs).err();
+    }
+
+    /// Put the state of the process table into the active tab.
+    fn store_tab(&mut self) {
+        let highlighted_pid =
+            process::filter_process_table(&self.app, &self.filter, self.filter_category)
+                .get(self.highlighted_row)
+                .copied();
+        let tab = &mut self.tabs[self.active_tab];
+        tab.filter = self.filter.clone();
+        tab.filter_category = self.filter_category;
+        tab.sort_by = self.app.psortby;
+        tab.sort_order = self.app.psortorder;
+        tab.columns = self.proc_columns.clone();
+        tab.column_widths = self.column_widths.clone();
+        tab.highlighted_pid = highlighted_pid;
+        tab.row_start = self.process_table_row_start;
+    }
+
+    /// Make `index` the active tab, the current one must have been stored.
+    fn load_tab(&mut self, index: usize) {
+        let tab = self.tabs[index].clone();
+        self.active_tab = index;
+        self.filter = tab.filter;
+        self.prompt = None;
+        self.app.psortby = tab.sort_by;
+        self.app.psortorder = tab.sort_order;
+        self.app.sort_process_table();
+        self.proc_columns = tab.columns;
+        self.column_widths = tab.column_widths;
+        self.column_manager_options = ColumnMGRList::with_cols(&self.proc_columns, &self.column_widths);
+        self.table_view.column_offset = 0;
+        self.table_view.command_offset = 0;
+        self.table_view.search = String::new();
+        self.filter_category = tab.filter_category;
+
+        let table = process::filter_process_table(&self.app, &self.filter, self.filter_category);
+        self.highlighted_row = tab
+            .highlighted_pid
+            .and_then(|pid| table.iter().position(|p| *p == pid))
+            .unwrap_or(0);
+        // keep the highlighted row on screen
+        let height = self.process_table_area.map_or(1, |a| a.height.saturating_sub(4).max(1));
+        self.process_table_row_start = tab
+            .row_start
+            .min(self.highlighted_row)
+            .max((self.highlighted_row + 1).saturating_sub(height as usize));
+    }
+
+    fn switch_tab(&mut self, index: usize) {
+        if index >= self.tabs.len() || index == self.active_tab {
+            return;
+        }
+        self.store_tab();
+        self.load_tab(index);
+        self.tab_message = save_tabs(&self.tabs).err();
+    }
+
+    /// A copy of the active tab, named right away.
+    fn new_tab(&mut self) {
+        if self.tabs.len() >= MAX_TABS {
+            self.tab_message = Some(format!("At most {} tabs.", MAX_TABS));
+            return;
+        }
+        self.store_tab();
+        let mut tab = self.tabs[self.active_tab].clone();
+        tab.name = format!("tab{}", self.tabs.len() + 1);
+        let prompt = Prompt::with_text(PromptKind::TabName, &tab.name);
+        self.tabs.push(tab);
+        self.load_tab(self.tabs.len() - 1);
+        self.prompt = Some(prompt);
+        self.tab_message = save_tabs(&self.tabs).err();
+    }
+
+    fn close_tab(&mut self) {
+        if self.tabs.len() == 1 {
+            self.tab_message = Some(String::from("The last tab can't be closed."));
+            return;
+        }
+        self.tabs.remove(self.active_tab);
+        self.load_tab(self.active_tab.min(self.tabs.len() - 1));
+        self.tab_message = save_tabs(&self.tabs).err();
+    }
+
+    fn rename_tab(&mut self, name: &str) {
+        let name = name.trim();
+        if !name.is_empty() {
+            self.tabs[self.active_tab].name = String::from(name);
+            self.tab_message = save_tabs(&self.tabs).err();
+        }
+    }
+
+    /// Keys of the column manager on top of navigating and toggling, false for the others.
+    fn process_column_mgr_input(&mut self, input: KeyEvent) -> bool {
+        let column = match self.column_manager_options.selected() {
+            Some(c) => c,
+            None => return false,
+        };
+        let position = self.proc_columns.iter().position(|c| *c == column);
+        match (input.code, position) {
+            (Key::Char('['), Some(i)) if i > 0 => self.proc_columns.swap(i, i - 1),
+            (Key::Char(']'), Some(i)) if i + 1 < self.proc_columns.len() => {
+                self.proc_columns.swap(i, i + 1)
+            }
+            (Key::Char('+'), Some(_)) | (Key::Char('-'), Some(_)) if column != Column::CMD => {
+                // start from the width it is drawn with
+                let current = self
+                    .column_spans
+                    .iter()
+                    .find(|(c, _)| *c == column)
+                    .map_or(10, |(_, w)| *w);
+                let width = if input.code == Key::Char('+') {
+                    current.saturating_add(1)
+                } else {
+                    current.saturating_sub(1).max(3)
+                };
+                self.column_widths.insert(column, ColumnWidth::Fixed(width));
+            }
+            (Key::Char('a'), Some(_)) if column != Column::CMD => {
+                self.column_widths.insert(column, ColumnWidth::Auto);
+            }
+            (Key::Char('d'), Some(_)) => {
+                self.column_widths.remove(&column);
+            }
+            (Key::Char('[' | ']' | '+' | '-' | 'a' | 'd'), _) => {}
+            _ => return false,
+        }
+        self.update_columns();
+        true
     }
 
     fn toggle_section(&mut self) {
         if self.show_section_mgr {
             if let Some(s) = self.section_manager_options.selected() {
                 // The section is there and needs to be removed but at least one section should remain
-                if self.section_geometry.len() > 1
-                    && self.section_geometry.iter().any(|(gs, _)| *gs == s)
-                {
-                    self.section_geometry.retain(|(section, _)| *section != s);
-                    self.recompute_constraints();
+                if self.layout.sections().len() > 1 && self.layout.contains(s) {
+                    self.layout.remove(s);
+                    if s == Section::Events {
+                        self.event_log.focused = false;
+                    }
+                    self.update_sections();
                 } 
                 // The section is not there and needs to be added
-                else if !self.section_geometry.iter().any(|(gs, _)| *gs == s) {
-                    let idx = 0;
-                    self.section_geometry.insert(idx, (s, 1.0));
-                    self.section_geometry
-                        .sort_by(|(a_section, _), (b_section, _)| {
-                            a_section
-                                .partial_cmp(b_section)
-                                .expect("Can't compare sections. Shouldn't happen.")
-                        });
-                    self.recompute_constraints();
+                else if !self.layout.contains(s) {
+                    self.layout.insert(s);
+                    self.update_sections();
                 }
             }
         }
@@ -920,9 +1577,12 @@ impl<'a> TerminalRenderer<'_> {
                     self.proc_columns.retain(|section| *section != c);
                     self.update_columns();
                 } else if !self.proc_columns.iter().any(|gc| *gc == c) {
-                    let idx = 0;
+                    // new columns go before the command, which is usually last
+                    let idx = match self.proc_columns.iter().position(|gc| *gc == Column::CMD) {
+                        Some(cmd) if c != Column::CMD => cmd,
+                        _ => self.proc_columns.len(),
+                    };
                     self.proc_columns.insert(idx, c);
-                    self.proc_columns.sort();
                     self.update_columns();
                 }
             }
@@ -943,17 +1603,12 @@ impl<'a> TerminalRenderer<'_> {
             return;
         }
         
-        let mut next_column = self.app.psortby;
-        let mut found = false;
-        while !found {
-            next_column = FromPrimitive::from_u32((next_column as u32 + 1) % NUMBER_OF_COLUMNS) // TODO: Add num of cols
-                .expect("invalid value to set psortby");
-            if self.proc_columns.contains(&next_column) {
-                found = true;
-            }
-        }
-        self.app.psortby = next_column;
+        // the column right of the sorted one, in the order they are shown
+        let len = self.proc_columns.len();
+        let idx = self.proc_columns.iter().position(|c| *c == self.app.psortby).unwrap_or(0);
+        self.app.psortby = self.proc_columns[(idx + 1) % len];
         self.app.sort_process_table();
+        self.save_tab();
     }
 
     fn sort_by_prev_column(&mut self) {
@@ -961,19 +1616,35 @@ impl<'a> TerminalRenderer<'_> {
             return;
         }
         
-        let mut prev_column = self.app.psortby;
-        let mut found = false;
-        while !found {
-            prev_column = FromPrimitive::from_u32((prev_column as i32 - 1 + NUMBER_OF_COLUMNS as i32) as u32 % NUMBER_OF_COLUMNS) 
-                .expect("invalid value to set psortby");
-            if self.proc_columns.contains(&prev_column) {
-                found = true;
+        let len = self.proc_columns.len();
+        let idx = self.proc_columns.iter().position(|c| *c == self.app.psortby).unwrap_or(0);
+        self.app.psortby = self.proc_columns[(idx + len - 1) % len];
+        self.app.sort_process_table();
+        self.save_tab();
+    }
+  
+    fn flip_sort_order(&mut self) {
+        match self.app.psortorder {
+            ProcessTableSortOrder::Ascending => {
+                self.app.psortorder = ProcessTableSortOrder::Descending
             }
+            ProcessTableSortOrder::Descending => {
+                self.app.psortorder = ProcessTableSortOrder::Ascending
+            }
+        }
+    }
+
+    /// Sort by `column`, or flip the order when the table is sorted by it already.
+    fn sort_by_column(&mut self, column: Column) {
+        if self.app.psortby == column {
+            self.flip_sort_order();
+        } else {
+            self.app.psortby = column;
         }
-        self.app.psortby = prev_column;
         self.app.sort_process_table();
+        self.save_tab();
     }
-  
+
     async fn process_toplevel_input(&mut self, input: KeyEvent) -> Action {
         match input.code {
             Key::Char('q') => {
@@ -986,15 +1657,24 @@ impl<'a> TerminalRenderer<'_> {
                 self.sort_by_prev_column();
             }
             Key::Char(';') => {
-                match self.app.psortorder {
-                    ProcessTableSortOrder::Ascending => {
-                        self.app.psortorder = ProcessTableSortOrder::Descending
-                    }
-                    ProcessTableSortOrder::Descending => {
-                        self.app.psortorder = ProcessTableSortOrder::Ascending
-                    }
-                }
+                self.flip_sort_order();
                 self.app.sort_process_table();
+                self.save_tab();
+            }
+            Key::Char('a') | Key::Char('l') | Key::Char('e')
+                if self.app.remote.is_some() && self.app.selected_process.is_some() =>
+            {
+                self.process_message = Some(String::from(NOT_AVAILABLE_REMOTELY));
+            }
+            Key::Char('a') if self.app.selected_process.is_some() => {
+                self.open_affinity_editor();
+            }
+            Key::Char('l') if self.app.selected_process.is_some() => {
+                self.show_limits = true;
+                self.limits_cursor = 0;
+            }
+            Key::Char('e') => {
+                self.sched_editor = self.app.selected_process.as_ref().map(|p| SchedEditor::for_process(p));
             }
             Key::Esc | Key::Char('b') => {
                 self.app.selected_process = None;
@@ -1002,49 +1682,62 @@ impl<'a> TerminalRenderer<'_> {
             }
             Key::Char('s') => {
                 if self.app.selected_process.is_none() {
-                    self.show_suspend = true;
+                    self.prompt = Some(Prompt::new(PromptKind::Suspend));
+                }
+                if let Some(pid) = self.app.selected_process.as_ref().map(|p| p.pid) {
+                    self.request_signal(pid, ProcessSignal::Suspend).await;
                 }
-                self.process_message = match &self.app.selected_process {
-                    Some(p) => Some(p.suspend().await),
-                    None => None,
-                };
             }
             Key::Char('r') => {
                 if self.app.selected_process.is_none() {
-                    self.show_resume = true;
+                    self.prompt = Some(Prompt::new(PromptKind::Resume));
+                }
+                if let Some(pid) = self.app.selected_process.as_ref().map(|p| p.pid) {
+                    self.request_signal(pid, ProcessSignal::Resume).await;
                 }
-                self.process_message = match &self.app.selected_process {
-                    Some(p) => Some(p.resume().await),
-                    None => None,
-                };
             }
             Key::Char('k') => {
                 if self.app.selected_process.is_none() {
-                    self.show_kill = true;
+                    self.prompt = Some(Prompt::new(PromptKind::Kill));
+                }
+                if let Some(pid) = self.app.selected_process.as_ref().map(|p| p.pid) {
+                    self.request_signal(pid, ProcessSignal::Kill).await;
                 }
-                self.process_message = match &self.app.selected_process {
-                    Some(p) => Some(p.kill().await),
-                    None => None,
-                };
             }
             Key::Char('t') => {
-                self.process_message = match &self.app.selected_process {
-                    Some(p) => Some(p.terminate().await),
-                    None => None,
-                };
+                if let Some(pid) = self.app.selected_process.as_ref().map(|p| p.pid) {
+                    self.request_signal(pid, ProcessSignal::Terminate).await;
+                }
+            }
+            Key::Char('B') if self.app.selected_process.is_none() => {
+                self.delta_view.baseline = Some(Baseline::capture(&self.app.process_map));
+                self.delta_view.shown = true;
+                self.delta_view.scroll = 0;
+            }
+            Key::Char('D') if self.app.selected_process.is_none() => {
+                if self.delta_view.baseline.is_none() {
+                    self.delta_view.baseline = Some(Baseline::capture(&self.app.process_map));
+                }
+                self.delta_view.shown = !self.delta_view.shown;
+                self.delta_view.scroll = 0;
+            }
+            Key::Char('T') => {
+                if let Some(pid) = self.app.selected_process.as_ref().map(|p| p.pid) {
+                    self.request_stop(pid).await;
+                }
             }
             Key::Char('n') => {
                 if self.app.selected_process.is_none() {
-                    self.show_nice = true;
+                    self.prompt = Some(Prompt::new(PromptKind::NicePid));
+                }
+                if let Some(pid) = self.app.selected_process.as_ref().map(|p| p.pid) {
+                    self.process_message = Some(self.renice_message(pid, 19));
                 }
-                self.process_message = self.app.selected_process.as_mut().map(|p| p.nice());
             }
             Key::Char('p') if self.app.selected_process.is_some() => {
-                self.process_message = self
-                    .app
-                    .selected_process
-                    .as_mut()
-                    .map(|p| p.set_priority(0));
+                if let Some(pid) = self.app.selected_process.as_ref().map(|p| p.pid) {
+                    self.process_message = Some(self.renice_message(pid, 0));
+                }
             }
             Key::Char(' ') => {
                 self.toggle_section();
@@ -1064,8 +1757,70 @@ impl<'a> TerminalRenderer<'_> {
             Key::Char('p') => {
                 self.show_paths = !self.show_paths;
             }
-            Key::Char('/') => {
-                self.show_find = true;
+            Key::Char('g') => {
+                self.show_heat = !self.show_heat;
+            }
+            Key::Char(c @ '1'..='9') if self.app.selected_process.is_none() => {
+                self.switch_tab(c as usize - '1' as usize);
+            }
+            Key::Tab if self.app.selected_process.is_none() => {
+                self.switch_tab((self.active_tab + 1) % self.tabs.len());
+            }
+            Key::BackTab if self.app.selected_process.is_none() => {
+                self.switch_tab((self.active_tab + self.tabs.len() - 1) % self.tabs.len());
+            }
+            Key::Char('N') if self.app.selected_process.is_none() => {
+                self.new_tab();
+            }
+            Key::Char('R') if self.app.selected_process.is_none() => {
+                self.prompt = Some(Prompt::with_text(PromptKind::TabName, &self.tabs[self.active_tab].name));
+            }
+            Key::Char('X') if self.app.selected_process.is_none() => {
+                self.close_tab();
+            }
+            Key::Char('w') if self.app.selected_process.is_none() => {
+                self.table_view.expanded = !self.table_view.expanded;
+            }
+            Key::Char('{') => {
+                self.table_view.scroll_command_left();
+            }
+            Key::Char('}') => {
+                self.table_view.scroll_command_right();
+            }
+            Key::Left if input.modifiers.contains(KeyModifiers::SHIFT) => {
+                self.table_view.scroll_command_left();
+            }
+            Key::Right if input.modifiers.contains(KeyModifiers::SHIFT) => {
+                self.table_view.scroll_command_right();
+            }
+            Key::Left if self.app.selected_process.is_none() => {
+                self.table_view.scroll_left();
+            }
+            Key::Right if self.app.selected_process.is_none() => {
+                self.table_view.scroll_right();
+            }
+            Key::Char('v') if self.layout.contains(Section::Events) => {
+                self.event_log.focused = true;
+            }
+            Key::Char('x') if self.app.selected_process.is_none() => {
+                self.prompt = Some(Prompt::new(PromptKind::Run));
+            }
+            Key::Char('u') if self.app.selected_process.is_none() => {
+                self.prompt = Some(Prompt::with_text(PromptKind::Rate, &self.tick_rate.to_string()));
+            }
+            Key::Char('?') | Key::F(3) if self.app.selected_process.is_none() => {
+                self.table_view.search = String::new();
+                self.prompt = Some(Prompt::new(PromptKind::Search));
+            }
+            Key::Char('/') if self.app.selected_process.is_none() => {
+                self.filter_category = false;
+                self.prompt = Some(Prompt::with_text(PromptKind::Filter, &self.filter));
+                self.highlighted_row = 0;
+                self.process_table_row_start = 0;
+            }
+            Key::Char('c') if self.app.selected_process.is_none() => {
+                self.filter_category = true;
+                self.prompt = Some(Prompt::with_text(PromptKind::Category, &self.filter));
                 self.highlighted_row = 0;
                 self.process_table_row_start = 0;
             }
@@ -1,41 +1,276 @@
 use super::{percent_of, Render, MBackend};
+use super::prompt::{Prompt, PromptKind};
+use super::theme::Theme;
 use crate::float_to_byte_string;
-use crate::constants::NUMBER_OF_COLUMNS;
-use crate::metrics::mprocess::{ProcessStatusExt, MProcess};
+use crate::metrics::limits::format_limit_value;
+use crate::metrics::mprocess::{format_cpu_list, IoClass, MProcess, ProcessStatusExt, SchedPolicy};
 use crate::metrics::{CPUTimeApp, ProcessTableSortOrder};
-use crate::renderer::column::Column;
+use crate::renderer::column::{Column, ColumnWidth};
 use byte_unit::{Byte, ByteUnit};
 use chrono::prelude::DateTime;
 use chrono::Local;
-use num_traits::FromPrimitive;
 use std::borrow::Cow;
+use std::collections::HashMap;
 use std::time::{Duration, UNIX_EPOCH};
 use tui::layout::{Constraint, Direction, Layout, Rect};
-use tui::style::{Color, Modifier, Style};
+use tui::style::Style;
 use tui::text::{Span, Spans};
-use tui::widgets::{Block, Borders, Cell, Paragraph, Row, Table, Wrap};
+use tui::widgets::{Block, Borders, Cell, Clear, List, ListItem, ListState, Paragraph, Row, Table, Wrap};
 use tui::Frame;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+fn format_sched_policy(p: &MProcess) -> String {
+    match p.sched_policy {
+        Some(policy) if policy.is_realtime() => format!("{}/{}", policy.as_str(), p.rt_priority),
+        Some(policy) => String::from(policy.as_str()),
+        None => String::from("-"),
+    }
+}
+
+/// State of the scheduling policy / I/O priority editor of the process view.
+pub struct SchedEditor {
+    pub policy: SchedPolicy,
+    pub rt_priority: i32,
+    pub io_class: IoClass,
+    pub io_level: i32,
+    pub cursor: usize,
+}
+
+impl SchedEditor {
+    pub const ROWS: usize = 4;
+
+    pub fn for_process(p: &MProcess) -> SchedEditor {
+        let policy = match p.sched_policy {
+            Some(policy) if SchedPolicy::SETTABLE.contains(&policy) => policy,
+            _ => SchedPolicy::Other,
+        };
+        // "none" means the kernel derives a best-effort level from the nice value
+        let (io_class, io_level) = match p.io_priority {
+            Some(io) if io.class != IoClass::None => (io.class, io.level),
+            _ => (IoClass::BestEffort, ((p.nice + 20) / 5).clamp(0, 7)),
+        };
+        SchedEditor {
+            policy,
+            rt_priority: p.rt_priority.max(1),
+            io_class,
+            io_level,
+            cursor: 0,
+        }
+    }
+
+    /// Move the value of the row under the cursor forwards or backwards.
+    pub fn change(&mut self, delta: i32) {
+        fn cycle<T: PartialEq + Copy>(values: &[T], current: T, delta: i32) -> T {
+            let idx = values.iter().position(|v| *v == current).unwrap_or(0) as i32;
+            let len = values.len() as i32;
+            values[((idx + delta) % len + len) as usize % len as usize]
+        }
+        match self.cursor {
+            0 => self.policy = cycle(&SchedPolicy::SETTABLE, self.policy, delta),
+            1 => self.rt_priority = (self.rt_priority + delta).clamp(1, 99),
+            2 => self.io_class = cycle(&IoClass::SETTABLE, self.io_class, delta),
+            _ => self.io_level = (self.io_level + delta).clamp(0, 7),
+        }
+    }
+}
+
+/// Header of a fixed width column, padded to the width of the column. CMD takes what's left.
+fn column_header(app: &CPUTimeApp, column: Column) -> Option<String> {
+    let header = match column {
+        Column::PID => format!("{:<width$}", "PID", width = app.max_pid_len + 1),
+        Column::PPID => format!("{:<width$}", "PPID", width = app.max_pid_len + 1),
+        Column::User => String::from("USER            "),
+        Column::Priority => String::from("P   "),
+        Column::Nice => String::from("N  "),
+        Column::Status => String::from("S "),
+        Column::TTY => String::from("TTY    "),
+        Column::CPUPercentage => String::from("CPU%  "),
+        Column::MemoryPercentage => String::from("MEM%  "),
+        Column::Memory => String::from("MEM     "),
+        Column::VirtualMemory => String::from("VIRT     "),
+        Column::CPUTime => String::from("CPUTIME  "),
+        Column::StartTime => String::from("STARTTIME           "),
+        Column::LastCPU => String::from("LCPU "),
+        Column::SchedPolicy => String::from("SCHED     "),
+        Column::IOPriority => String::from("IO     "),
+        Column::PGID => format!("{:<width$}", "PGID", width = app.max_pid_len + 1),
+        Column::SID => format!("{:<width$}", "SID", width = app.max_pid_len + 1),
+        Column::Threads => String::from("THR   "),
+        Column::MinorFaults => String::from("MINFLT     "),
+        Column::MajorFaults => String::from("MAJFLT  "),
+        Column::VoluntarySwitches => String::from("VCSW       "),
+        Column::InvoluntarySwitches => String::from("NVCSW     "),
+        Column::OOMScore => String::from("OOM  "),
+        Column::WChan => String::from("WCHAN           "),
+        Column::Exe => String::from("EXE                     "),
+        Column::Cwd => String::from("CWD                     "),
+        Column::Elapsed => String::from("ELAPSED    "),
+        Column::CMD => return None,
+    };
+    Some(header)
+}
+
+/// The column drawn at `x` cells right of the table's left border, `spans` are the columns
+/// and widths of the last drawn table.
+pub fn column_at(spans: &[(Column, u16)], x: u16) -> Option<Column> {
+    let mut end = 0;
+    for (column, width) in spans {
+        end += width;
+        if x < end {
+            return Some(*column);
+        }
+    }
+    None
+}
+
+/// Cut `text` to leave a blank cell before the next column, marking the cut with an ellipsis.
+fn fit(text: &str, width: u16) -> String {
+    let width = width as usize;
+    if text.trim_end().width() < width {
+        return String::from(text);
+    }
+    let mut fitted = String::new();
+    let mut used = 0;
+    for c in text.trim().chars() {
+        let w = c.width().unwrap_or(0);
+        if used + w + 2 > width {
+            break;
+        }
+        fitted.push(c);
+        used += w;
+    }
+    fitted.push('…');
+    fitted
+}
+
+/// Time since the process started, days are only shown when there are any.
+fn format_elapsed(p: &MProcess) -> String {
+    let secs = p.get_run_duration().num_seconds().max(0);
+    let hms = format!("{:0>2}:{:0>2}:{:0>2}", (secs / 3600) % 24, (secs / 60) % 60, secs % 60);
+    match secs / 86400 {
+        0 => hms,
+        days => format!("{}d {}", days, &hms[..5]),
+    }
+}
+
+/// Horizontal scroll position and search of the process table.
+#[derive(Default)]
+pub struct TableView {
+    /// Columns scrolled away on the left.
+    pub column_offset: usize,
+    /// Cells of the command line scrolled away, independent of the other columns.
+    pub command_offset: usize,
+    /// Show the full command line of the highlighted process below the table.
+    pub expanded: bool,
+    /// Searched for text, unlike the filter it leaves every row in the table.
+    pub search: String,
+}
+
+impl TableView {
+    /// Cells the command line moves per key press.
+    pub const COMMAND_STEP: usize = 8;
+
+    pub fn scroll_left(&mut self) {
+        self.column_offset = self.column_offset.saturating_sub(1);
+    }
+
+    /// Capped to the last column when drawing.
+    pub fn scroll_right(&mut self) {
+        self.column_offset += 1;
+    }
+
+    pub fn scroll_command_left(&mut self) {
+        self.command_offset = self.command_offset.saturating_sub(TableView::COMMAND_STEP);
+    }
+
+    /// Capped to the longest command line on screen when drawing.
+    pub fn scroll_command_right(&mut self) {
+        self.command_offset += TableView::COMMAND_STEP;
+    }
+}
+
+/// Byte range of the first occurrence of `search` in `text`, ignoring case.
+fn find_ignore_case(text: &str, search: &str) -> Option<(usize, usize)> {
+    if search.is_empty() {
+        return None;
+    }
+    text.char_indices().find_map(|(start, _)| {
+        let mut end = start;
+        let mut chars = text[start..].chars();
+        let found = search.chars().all(|s| match chars.next() {
+            Some(c) if c.to_lowercase().eq(s.to_lowercase()) => {
+                end += c.len_utf8();
+                true
+            }
+            _ => false,
+        });
+        if found {
+            Some((start, end))
+        } else {
+            None
+        }
+    })
+}
+
+/// A cell of a row matching the search, the found text marked.
+fn search_cell<'a>(text: String, search: &str, theme: &Theme) -> Cell<'a> {
+    match find_ignore_case(&text, search) {
+        Some((start, end)) => Cell::from(Spans::from(vec![
+            Span::raw(String::from(&text[..start])),
+            Span::styled(String::from(&text[start..end]), theme.search_match),
+            Span::raw(String::from(&text[end..])),
+        ])),
+        None => Cell::from(text),
+    }
+}
+
+/// `text` without its first `cells` cells. A wide character cut in half becomes a blank.
+fn skip_cells(text: &str, cells: usize) -> Cow<'_, str> {
+    if cells == 0 {
+        return Cow::Borrowed(text);
+    }
+    let mut skipped = 0;
+    for (i, c) in text.char_indices() {
+        if skipped >= cells {
+            let pad = " ".repeat(skipped - cells);
+            return Cow::Owned(pad + &text[i..]);
+        }
+        skipped += c.width().unwrap_or(0);
+    }
+    Cow::Owned(" ".repeat(skipped.saturating_sub(cells)))
+}
+
+/// Index in the table, process and the text and style of each cell.
+type VisibleRow<'a> = (usize, &'a MProcess, Vec<(String, Style)>);
+
+/// Arrow comparing a value with the one of the previous refresh, blank when it moved less
+/// than `threshold`.
+fn trend(current: f64, previous: f64, threshold: f64) -> char {
+    if current - previous > threshold {
+        '↑'
+    } else if previous - current > threshold {
+        '↓'
+    } else {
+        ' '
+    }
+}
 
 pub fn render_process_table(
     app: &CPUTimeApp,
+    theme: &Theme,
     process_table: &[i32],
     area: Rect,
     process_table_start: usize,
     f: &mut Frame<'_, MBackend>,
     proc_columns: &Vec<Column>,
+    column_widths: &HashMap<Column, ColumnWidth>,
+    column_spans: &mut Vec<(Column, u16)>,
+    view: &mut TableView,
     show_paths: bool,
-    show_find: bool,
-    show_find_cat: bool,
-    show_kill: bool,
-    show_suspend: bool,
-    show_resume: bool,
-    show_nice: bool,
-    show_rate: bool,
+    show_heat: bool,
+    prompt: Option<&Prompt>,
     filter: &str,
-    action_pid: &str,
-    action_input: &str,
-    new_rate: &str,
-    process_table_message: &String,
+    filter_category: bool,
     highlighted_row: usize,
     freeze: bool,
     tick_rate: u64,
@@ -63,7 +298,8 @@ pub fn render_process_table(
         return highlighted_process; // not enough space to draw anything
     }
 
-    let rows: Vec<Row> = procs
+    // cells of the visible rows, auto-fit columns are as wide as what they show
+    let visible: Vec<VisibleRow> = procs
         .iter()
         .enumerate()
         .skip(process_table_start)
@@ -83,8 +319,29 @@ pub fn render_process_table(
                 String::from("")
             };
 
+            // the highlighted and exited rows keep their own colours
+            let colour_cells = show_heat && i != highlighted_row && p.end_time.is_none();
+            let heat = |percent: f32| {
+                if colour_cells {
+                    theme.heat(percent)
+                } else {
+                    Style::default()
+                }
+            };
+            let mem_percent = percent_of(p.memory, app.mem_total);
+            // memory moves a little all the time, only show changes over 1%
+            let (cpu_trend, mem_trend) = if show_heat {
+                (
+                    trend(p.cpu_usage as f64, p.prev_cpu_usage as f64, 1.0),
+                    trend(p.memory as f64, p.prev_memory as f64, p.prev_memory as f64 * 0.01),
+                )
+            } else {
+                (' ', ' ')
+            };
+
             // Loop over columns and add cells to the row
             let mut row = vec![];
+            let plain = Style::default();
 
             for column in proc_columns {
                 let cpu_time = format!(
@@ -94,136 +351,184 @@ pub fn render_process_table(
                     p.cpu_time % 60
                 );
                 match column {
-                    Column::PID => row.push(Cell::from(format!("{: >width$}", p.pid, width = app.max_pid_len))),
-                    Column::PPID => row.push(Cell::from(format!("{: >width$}", p.ppid, width = app.max_pid_len))),
-                    Column::User => row.push(Cell::from(format!("{: <10}", p.user_name))),
-                    Column::Priority => row.push(Cell::from(format!("{: <3}", p.priority))),
-                    Column::Nice => row.push(Cell::from(format!("{: <3}", p.nice))),
-                    Column::Status => row.push(Cell::from(format!("{:1}", p.status.to_single_char()))),
-                    Column::TTY => row.push(Cell::from(format!("{: <10}", p.tty))),
-                    Column::CPUPercentage => row.push(Cell::from(format!("{:>5.1}", p.cpu_usage))),
-                    Column::MemoryPercentage => row.push(Cell::from(format!("{:>5.1}", percent_of(p.memory, app.mem_total)))),
-                    Column::Memory => row.push(Cell::from(format!("{:>8}", float_to_byte_string!(p.memory as f64, ByteUnit::B).replace('B', "")))),
-                    Column::VirtualMemory => row.push(Cell::from(format!("{:>8}", float_to_byte_string!(p.virtual_memory as f64, ByteUnit::KB).replace('B', "")))),
-                    Column::CPUTime => row.push(Cell::from(cpu_time)),
-                    Column::StartTime => row.push(Cell::from(format!("{:>5.1}", DateTime::<Local>::from(UNIX_EPOCH + Duration::from_secs(p.start_time))))),
-                    Column::CMD => row.push(Cell::from(format!("{:}{:}", p.name, cmd_string))),
+                    Column::PID => row.push((format!("{: >width$}", p.pid, width = app.max_pid_len), plain)),
+                    Column::PPID => row.push((format!("{: >width$}", p.ppid, width = app.max_pid_len), plain)),
+                    Column::User => row.push((format!("{: <10}", p.user_name), plain)),
+                    Column::Priority => row.push((format!("{: <3}", p.priority), plain)),
+                    Column::Nice => row.push((format!("{: <3}", p.nice), plain)),
+                    Column::Status => row.push((format!("{:1}", p.status.to_single_char()), plain)),
+                    Column::TTY => row.push((format!("{: <10}", p.tty), plain)),
+                    Column::CPUPercentage => row.push((format!("{:>5.1}{}", p.cpu_usage, cpu_trend), heat(p.cpu_usage))),
+                    Column::MemoryPercentage => row.push((format!("{:>5.1}{}", mem_percent, mem_trend), heat(mem_percent))),
+                    Column::Memory => row.push((format!("{:>8}", float_to_byte_string!(p.memory as f64, ByteUnit::B).replace('B', "")), heat(mem_percent))),
+                    Column::VirtualMemory => row.push((format!("{:>8}", float_to_byte_string!(p.virtual_memory as f64, ByteUnit::KB).replace('B', "")), plain)),
+                    Column::CPUTime => row.push((cpu_time, plain)),
+                    Column::StartTime => row.push((format!("{:>5.1}", DateTime::<Local>::from(UNIX_EPOCH + Duration::from_secs(p.start_time))), plain)),
+                    Column::LastCPU => row.push((format!("{: >4} ", p.last_cpu.map_or(String::from("-"), |c| format!("{}", c + 1))), plain)),
+                    Column::SchedPolicy => row.push((format!("{: <10}", format_sched_policy(p)), plain)),
+                    Column::IOPriority => row.push((format!("{: <7}", p.io_priority.map_or(String::from("-"), |io| io.short())), plain)),
+                    Column::PGID => row.push((format!("{: >width$}", p.pgid, width = app.max_pid_len), plain)),
+                    Column::SID => row.push((format!("{: >width$}", p.sid, width = app.max_pid_len), plain)),
+                    Column::Threads => row.push((format!("{: >5}", p.threads_total), plain)),
+                    Column::MinorFaults => row.push((format!("{: >10}", p.minor_faults), plain)),
+                    Column::MajorFaults => row.push((format!("{: >7}", p.major_faults), plain)),
+                    Column::VoluntarySwitches => row.push((format!("{: >10}", p.voluntary_switches), plain)),
+                    Column::InvoluntarySwitches => row.push((format!("{: >9}", p.involuntary_switches), plain)),
+                    Column::OOMScore => row.push((format!("{: >4}", p.oom_score.map_or(String::from("-"), |s| s.to_string())), plain)),
+                    Column::WChan => row.push((if p.wchan.is_empty() { String::from("-") } else { p.wchan.clone() }, plain)),
+                    Column::Exe => row.push((if p.exe.is_empty() { String::from("-") } else { p.exe.clone() }, plain)),
+                    Column::Cwd => row.push((if p.cwd.is_empty() { String::from("-") } else { p.cwd.clone() }, plain)),
+                    Column::Elapsed => row.push((format!("{: >10}", format_elapsed(p)), plain)),
+                    Column::CMD => match p.end_time {
+                        Some(et) => row.push((format!(
+                            "{:}{:} (exited {:})",
+                            p.name,
+                            cmd_string,
+                            DateTime::<Local>::from(UNIX_EPOCH + Duration::from_secs(et)).format("%H:%M:%S")
+                        ), plain)),
+                        None => row.push((format!("{:}{:}", p.name, cmd_string), plain)),
+                    },
                 }
             }
-            
-            let row = Row::new(row);
-
-            if i == highlighted_row {
-                row.style(
-                    Style::default()
-                        .bg(Color::Gray)
-                        .fg(Color::Black)
-                        .add_modifier(Modifier::BOLD),
-                )
-            } else {
-                row
-            }
+            (i, *p, row)
         })
         .collect();
 
-    // Loop over columns and add cells to the row
-    let mut header = vec![];
-
-    for column in proc_columns {
-        match column {
-            Column::PID => header.push(format!("{:<width$}", "PID", width = app.max_pid_len + 1)),
-            Column::PPID => header.push(format!("{:<width$}", "PPID", width = app.max_pid_len + 1)),
-            Column::User => header.push(String::from("USER            ")),
-            Column::Priority => header.push(String::from("P   ")),
-            Column::Nice => header.push(String::from("N  ")),
-            Column::Status => header.push(String::from("S ")),
-            Column::TTY => header.push(String::from("TTY    ")),
-            Column::CPUPercentage => header.push(String::from("CPU%  ")),
-            Column::MemoryPercentage => header.push(String::from("MEM%  ")),
-            Column::Memory => header.push(String::from("MEM     ")),
-            Column::VirtualMemory => header.push(String::from("VIRT     ")),
-            Column::CPUTime => header.push(String::from("CPUTIME  ")),
-            Column::StartTime => header.push(String::from("STARTTIME           ")),
-            _ => {}
-        }
-    }
+    // widths of every column but CMD, which takes what's left
+    let fixed_widths: Vec<Option<u16>> = proc_columns
+        .iter()
+        .enumerate()
+        .map(|(idx, column)| {
+            let default = column_header(app, *column)?;
+            let label_width = default.trim_end().len();
+            let width = match column_widths.get(column).copied().unwrap_or_default() {
+                ColumnWidth::Default => default.len(),
+                ColumnWidth::Fixed(w) => w as usize,
+                ColumnWidth::Auto => {
+                    visible
+                        .iter()
+                        .map(|(_, _, cells)| cells[idx].0.trim_end().width())
+                        .max()
+                        .unwrap_or(0)
+                        .max(label_width)
+                        + 1
+                }
+            };
+            // room for a letter of the label and the sort arrow
+            Some(width.max(3) as u16)
+        })
+        .collect();
+    // columns scrolled off to the left, at least one stays
+    let first = view.column_offset.min(proc_columns.len().saturating_sub(1));
+    view.column_offset = first;
+    let used_width: u16 = fixed_widths[first..].iter().flatten().sum();
+    let cmd_width = area.width.saturating_sub(used_width).saturating_sub(3);
 
-    let mut widths = Vec::with_capacity(header.len() + 1);
-    let mut used_width = 0;
-    for item in &header {
-        let len = item.len() as u16;
-        widths.push(Constraint::Length(len));
-        used_width += len;
-    }
-    let cmd_width = f.size().width.saturating_sub(used_width).saturating_sub(3);
-    let cmd_header = format!("{:<width$}", "CMD", width = cmd_width as usize);
-    
-    if proc_columns.contains(&Column::CMD) {
-        widths.push(Constraint::Min(cmd_width));
-        header.push(cmd_header);
-    }
+    // stop scrolling the command lines once the longest one ends on screen
+    let longest_command = match proc_columns[first..].iter().position(|c| *c == Column::CMD) {
+        Some(idx) => visible
+            .iter()
+            .map(|(_, _, cells)| cells[first + idx].0.width())
+            .max()
+            .unwrap_or(0),
+        None => 0,
+    };
+    view.command_offset = view
+        .command_offset
+        .min(longest_command.saturating_sub(cmd_width as usize));
+    let command_offset = view.command_offset;
 
-    let mut sort_index = 0;
-    let mut found = false;
-     for i in 0..NUMBER_OF_COLUMNS { // TODO: make 14 a constant
-        let column: Column = FromPrimitive::from_u32(i as u32)
-                .expect("Index not in range for Column enum");
-        if proc_columns.contains(&column) {
-            if column == app.psortby {
-                found = true;
-                break;
+    let mut widths = Vec::with_capacity(proc_columns.len());
+    let mut header = Vec::with_capacity(proc_columns.len());
+    column_spans.clear();
+    for (column, width) in proc_columns.iter().zip(&fixed_widths).skip(first) {
+        match width {
+            Some(w) => {
+                let label = column_header(app, *column).unwrap_or_default();
+                header.push(format!("{:<width$}", fit(label.trim_end(), *w), width = *w as usize));
+                widths.push(Constraint::Length(*w));
+                column_spans.push((*column, *w));
             }
-            else {
-                sort_index += 1;
+            None => {
+                header.push(format!("{:<width$}", "CMD", width = cmd_width as usize));
+                widths.push(Constraint::Min(cmd_width));
+                column_spans.push((*column, cmd_width));
             }
         }
     }
-    if !found {
-        sort_index = 0;
-    }
 
-    header[sort_index].pop();
-    let sort_ind = match app.psortorder {
-        ProcessTableSortOrder::Ascending => '↑',
-        ProcessTableSortOrder::Descending => '↓',
-    };
-    header[sort_index].insert(0, sort_ind); //sort column indicator
+    let search_lc = view.search.to_lowercase();
+    let rows: Vec<Row> = visible
+        .into_iter()
+        .map(|(i, p, cells)| {
+            let matched = !search_lc.is_empty() && process_matches(p, &search_lc);
+            let row = Row::new(cells.into_iter().zip(&fixed_widths).skip(first).map(|((text, style), width)| {
+                let text = match width {
+                    Some(w) => fit(&text, *w),
+                    // mark command lines scrolled to the left
+                    None if command_offset > 0 => format!("«{}", skip_cells(&text, command_offset + 1)),
+                    None => text,
+                };
+                if matched {
+                    search_cell(text, &view.search, theme).style(style)
+                } else {
+                    Cell::from(text).style(style)
+                }
+            }));
+
+            if i == highlighted_row {
+                row.style(theme.selected)
+            } else if p.end_time.is_some() {
+                // exited, kept around for the grace period
+                row.style(theme.dimmed)
+            } else {
+                row
+            }
+        })
+        .collect();
+
+    // the sort column may be scrolled away
+    let sort_index = proc_columns[first..].iter().position(|c| *c == app.psortby);
+
+    if let Some(sort_index) = sort_index {
+        header[sort_index].pop();
+        let sort_ind = match app.psortorder {
+            ProcessTableSortOrder::Ascending => '↑',
+            ProcessTableSortOrder::Descending => '↓',
+        };
+        header[sort_index].insert(0, sort_ind); //sort column indicator
+    }
     let header_row: Vec<Cell> = header
         .iter()
         .enumerate()
         .map(|(i, c)| {
-            if i == sort_index {
-                Cell::from(c.as_str()).style(
-                    Style::default()
-                        .bg(Color::Gray)
-                        .fg(Color::Black)
-                        .add_modifier(Modifier::BOLD),
-                )
+            if Some(i) == sort_index {
+                Cell::from(c.as_str()).style(theme.selected)
             } else {
                 Cell::from(c.as_str())
             }
         })
         .collect();
-    let title = if show_find {
-        format!("[ESC] Clear, Find: {:}", filter)
-    } else if show_find_cat {
-        format!("[ESC] Clear, Category: {:}", filter)
-    }
-    else if !filter.is_empty() {
-        format!("Filtered Results: {:}, [/] to change/clear", filter)
-    } else if show_kill {
-        format!("[ESC] Clear, PID to kill: {:}{}", action_pid, process_table_message)
-    } else if show_suspend {
-        format!("[ESC] Clear, PID to suspend: {:}{}", action_pid, process_table_message)
-    } else if show_resume {
-        format!("[ESC] Clear, PID to resume: {:}{}", action_pid, process_table_message)
-    } else if show_nice {
-        format!("[ESC] Clear, PID to nice: {:}{}{}", action_pid, process_table_message, action_input)
-    } else if show_rate {
-        format!("[ESC] Clear, set refresh rate in millis: {:}{}", new_rate, process_table_message)
+    let matching = if search_lc.is_empty() {
+        0
+    } else {
+        procs.iter().filter(|p| process_matches(p, &search_lc)).count()
+    };
+    let title = if let Some(prompt) = prompt {
+        let mut title = prompt.title(&prompt.kind.label(), theme);
+        if prompt.kind == PromptKind::Search {
+            title.0.push(Span::raw(format!(" ({} matching)", matching)));
+        }
+        title
+    } else if !view.search.is_empty() {
+        Spans::from(format!("Search: {:} ({} matching), [n]ext [N] previous [?] to change, [ESC] to clear", view.search, matching))
+    } else if filter_category && !filter.is_empty() {
+        Spans::from(format!("Category: {:}, [c] to change/clear", filter))
+    } else if !filter.is_empty() {
+        Spans::from(format!("Filtered Results: {:}, [/] to change/clear", filter))
     }
      else {
-        format!("Freeze [f] Sort Col [,/.] Asc/Dec [;] Filter [/] Category [c] Kill [k] Suspend [s] Resume [r] Nice [n]")
+        Spans::from(format!("Freeze [f] Sort Col [,/.] Asc/Dec [;] Filter [/] Search [?] Category [c] Kill [k] Suspend [s] Resume [r] Nice [n] Run [x] Rate [u] Delta [D]"))
     };
 
     Table::new(rows)
@@ -236,14 +541,14 @@ pub fn render_process_table(
         .column_spacing(0)
         .header(
             Row::new(header_row)
-                .style(Style::default().bg(Color::DarkGray))
+                .style(theme.header)
                 .bottom_margin(0),
         )
         .render(f, area);
 
 
     let frozen_text = vec![Spans::from(vec![
-            Span::styled("  FROZEN  ", Style::default().fg(Color::White).bg(Color::Blue).add_modifier(Modifier::BOLD)),
+            Span::styled("  FROZEN  ", theme.badge),
         ])];
         
     if freeze{
@@ -253,8 +558,8 @@ pub fn render_process_table(
     }
     else {
         let rate_text = vec![Spans::from(vec![
-            Span::styled("  Refresh Rate:", Style::default().fg(Color::White).bg(Color::DarkGray)),
-            Span::styled(format!(" {}  ", tick_rate), Style::default().fg(Color::White).bg(Color::DarkGray)),
+            Span::styled("  Refresh Rate:", theme.status_bar),
+            Span::styled(format!(" {}  ", tick_rate), theme.status_bar),
         ])];
 
         let l : u16 = (tick_rate.to_string().len() + 18) as u16;
@@ -267,14 +572,57 @@ pub fn render_process_table(
     highlighted_process
 }
 
+/// Full command line and executable of the highlighted process, wrapped to fit.
+pub fn render_command_detail(
+    theme: &Theme,
+    p: Option<&MProcess>,
+    area: Rect,
+    f: &mut Frame<'_, MBackend>,
+) {
+    let block = Block::default().borders(Borders::ALL);
+    let p = match p {
+        Some(p) => p,
+        None => {
+            Paragraph::new("No process highlighted.")
+                .block(block.title("Command Line [w] to close"))
+                .render(f, area);
+            return;
+        }
+    };
+    let command = if p.command.is_empty() {
+        format!("[{}]", p.name)
+    } else {
+        p.command.join(" ")
+    };
+    let exe = if p.exe.is_empty() { "-" } else { p.exe.as_str() };
+    let text = vec![
+        Spans::from(vec![
+            Span::styled(format!("{} ", p.pid), theme.accent),
+            Span::styled(command, theme.value),
+        ]),
+        Spans::from(vec![
+            Span::styled("EXE ", theme.heading),
+            Span::raw(exe),
+        ]),
+    ];
+    Paragraph::new(text)
+        .block(block.title(format!("Command Line of {} [w] to close", p.name)))
+        .wrap(Wrap { trim: false })
+        .render(f, area);
+}
+
 pub fn render_process(
     app: &CPUTimeApp,
+    theme: &Theme,
     layout: Rect,
     f: &mut Frame<'_, MBackend>,
     process_message: &Option<String>,
     p: &MProcess,
     freeze: bool,
     tick_rate: u64,
+    affinity_editor: Option<(&[bool], usize)>,
+    sched_editor: Option<&SchedEditor>,
+    limits_view: Option<(usize, Option<&Prompt>)>,
 ) {
     Block::default()
         .title(format!("Process: {0}", p.name))
@@ -286,14 +634,11 @@ pub fn render_process(
         .constraints([Constraint::Length(2), Constraint::Min(1)].as_ref())
         .split(layout);
 
-    let title = format!("(b)ack (n)ice (p)riority 0 (s)uspend (r)esume (k)ill [SIGKILL] (t)erminate [SIGTERM] {:} {: >width$}", 
+    let title = format!("(b)ack (n)ice (p)riority 0 (a)ffinity sch(e)duling (l)imits (s)uspend (r)esume (k)ill [SIGKILL] (t)erminate [SIGTERM] s(T)op {:} {: >width$}", 
                         process_message.as_ref().unwrap_or(&String::from("")), "", width = layout.width as usize);
 
     Block::default()
-        .title(Span::styled(
-            title,
-            Style::default().bg(Color::DarkGray).fg(Color::White),
-        ))
+        .title(Span::styled(title, theme.status_bar))
         .render(f, v_sections[0]);
 
     let alive = if p.end_time.is_some() {
@@ -312,7 +657,7 @@ pub fn render_process(
         run_duration.num_seconds() % 60
     );
 
-    let rhs_style = Style::default().fg(Color::Green);
+    let rhs_style = theme.value;
     let mut text = vec![
         Spans::from(vec![
             Span::raw("Name:                  "),
@@ -364,6 +709,37 @@ pub fn render_process(
             Span::raw("Nice:                  "),
             Span::styled(format!("{:}", p.nice), rhs_style),
         ]),
+        Spans::from(vec![
+            Span::raw("Scheduling Policy:     "),
+            Span::styled(format_sched_policy(p), rhs_style),
+        ]),
+        Spans::from(vec![
+            Span::raw("I/O Priority:          "),
+            Span::styled(
+                match p.io_priority {
+                    Some(io) => format!("{} (level {})", io.class.as_str(), io.level),
+                    None => String::from("unknown"),
+                },
+                rhs_style,
+            ),
+        ]),
+        Spans::from(vec![
+            Span::raw("CPU Affinity:          "),
+            Span::styled(
+                match &p.affinity {
+                    Some(a) => format_cpu_list(a),
+                    None => String::from("unknown"),
+                },
+                rhs_style,
+            ),
+        ]),
+        Spans::from(vec![
+            Span::raw("Last CPU:              "),
+            Span::styled(
+                p.last_cpu.map_or(String::from("unknown"), |c| format!("{}", c + 1)),
+                rhs_style,
+            ),
+        ]),
         Spans::from(vec![
             Span::raw("MEM Usage:             "),
             Span::styled(
@@ -434,10 +810,12 @@ pub fn render_process(
     ];
 
     let frozen_text = vec![Spans::from(vec![
-        Span::styled("  FROZEN  ", Style::default().fg(Color::White).bg(Color::Blue).add_modifier(Modifier::BOLD)),
+        Span::styled("  FROZEN  ", theme.badge),
     ])];
 
-    if text.len() > v_sections[1].height as usize * 3 {
+    if let Some((cursor, prompt)) = limits_view {
+        render_limits(p, app.user_tasks(p.uid), theme, cursor, prompt, v_sections[1], f);
+    } else if text.len() > v_sections[1].height as usize * 3 {
         let h_sections = Layout::default()
             .direction(Direction::Horizontal)
             .margin(0)
@@ -468,7 +846,14 @@ pub fn render_process(
         .wrap(Wrap { trim: true })
         .render(f, v_sections[1]);
     }
-    
+
+    if let Some((selection, cursor)) = affinity_editor {
+        render_affinity_editor(app, theme, selection, cursor, v_sections[1], f);
+    }
+    if let Some(editor) = sched_editor {
+        render_sched_editor(editor, theme, v_sections[1], f);
+    }
+
     if freeze{
         Paragraph::new(frozen_text)
         .block(Block::default())
@@ -476,8 +861,8 @@ pub fn render_process(
     }
     else {
         let rate_text = vec![Spans::from(vec![
-            Span::styled("  Refresh Rate:", Style::default().fg(Color::White).bg(Color::DarkGray)),
-            Span::styled(format!(" {}  ", tick_rate), Style::default().fg(Color::White).bg(Color::DarkGray)),
+            Span::styled("  Refresh Rate:", theme.status_bar),
+            Span::styled(format!(" {}  ", tick_rate), theme.status_bar),
         ])];
 
         let l : u16 = (tick_rate.to_string().len() + 18) as u16;
@@ -488,7 +873,200 @@ pub fn render_process(
     }
 }
 
-pub fn filter_process_table<'a>(app: &'a CPUTimeApp, filter: &str, show_find_cat: bool) -> Cow<'a, [i32]> {
+/// Table of resource limits shown instead of the details in the process view.
+fn render_limits(
+    p: &MProcess,
+    user_tasks: u64,
+    theme: &Theme,
+    cursor: usize,
+    prompt: Option<&Prompt>,
+    area: Rect,
+    f: &mut Frame<'_, MBackend>,
+) {
+    let limits = match &p.limits {
+        Some(l) => l,
+        None => {
+            Paragraph::new("Couldn't read the limits of this process.")
+                .block(Block::default().borders(Borders::ALL).title("Limits"))
+                .render(f, area);
+            return;
+        }
+    };
+    let rows: Vec<Row> = limits
+        .iter()
+        .enumerate()
+        .map(|(i, l)| {
+            let usage = p.limit_usage(l, user_tasks);
+            // highlight limits that are close to being hit
+            let usage_style = match (usage, l.soft) {
+                (Some(u), Some(s)) if s > 0 && u >= s => theme.bad,
+                (Some(u), Some(s)) if s > 0 && u as f64 >= s as f64 * 0.8 => theme.warn,
+                _ => theme.good,
+            };
+            let row = Row::new(vec![
+                Cell::from(l.name.clone()),
+                Cell::from(format_limit_value(l.soft)),
+                Cell::from(format_limit_value(l.hard)),
+                Cell::from(l.units.clone()),
+                Cell::from(usage.map_or(String::from(""), |u| format!("{}", u))).style(usage_style),
+            ]);
+            if i == cursor {
+                row.style(theme.selected)
+            } else {
+                row
+            }
+        })
+        .collect();
+    let title = match prompt {
+        Some(prompt) => {
+            let label = format!(
+                "{} for {}",
+                prompt.kind.label(),
+                limits.get(cursor).map_or("", |l| l.name.as_str())
+            );
+            prompt.title(&label, theme)
+        }
+        None => Spans::from("Limits: [↑/↓] Select [Enter] Edit [l] Back"),
+    };
+    Table::new(rows)
+        .block(Block::default().borders(Borders::ALL).title(title))
+        .header(
+            Row::new(vec!["LIMIT", "SOFT", "HARD", "UNITS", "CURRENT"])
+                .style(theme.header),
+        )
+        .widths(
+            [
+                Constraint::Length(24),
+                Constraint::Length(22),
+                Constraint::Length(22),
+                Constraint::Length(10),
+                Constraint::Min(10),
+            ]
+            .as_ref(),
+        )
+        .column_spacing(1)
+        .render(f, area);
+}
+
+/// Policy and I/O priority pickers drawn on the right side of the process view.
+fn render_sched_editor(
+    editor: &SchedEditor,
+    theme: &Theme,
+    area: Rect,
+    f: &mut Frame<'_, MBackend>,
+) {
+    let width = 40.min(area.width);
+    let editor_area = Rect::new(area.right().saturating_sub(width), area.y, width, 8.min(area.height));
+    let rt = if editor.policy.is_realtime() {
+        format!("{}", editor.rt_priority)
+    } else {
+        String::from("- (FIFO/RR only)")
+    };
+    let rows = [
+        ("Policy:      ", String::from(editor.policy.as_str())),
+        ("RT Priority: ", rt),
+        ("I/O Class:   ", String::from(editor.io_class.as_str())),
+        ("I/O Level:   ", format!("{} (0 high - 7 low)", editor.io_level)),
+    ];
+    let text: Vec<Spans> = rows
+        .iter()
+        .enumerate()
+        .map(|(i, (label, value))| {
+            let value_style = if i == editor.cursor {
+                theme.menu_highlight
+            } else {
+                theme.value
+            };
+            Spans::from(vec![
+                Span::raw(*label),
+                Span::styled(format!("< {} >", value), value_style),
+            ])
+        })
+        .collect();
+    Clear.render(f, editor_area);
+    Paragraph::new(text)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title("Scheduling [←/→] [Enter] [Esc]"),
+        )
+        .render(f, editor_area);
+}
+
+/// Per-core checklist drawn on the right side of the process view.
+fn render_affinity_editor(
+    app: &CPUTimeApp,
+    theme: &Theme,
+    selection: &[bool],
+    cursor: usize,
+    area: Rect,
+    f: &mut Frame<'_, MBackend>,
+) {
+    let width = 28.min(area.width);
+    let editor_area = Rect::new(area.right().saturating_sub(width), area.y, width, area.height);
+    let items: Vec<ListItem> = selection
+        .iter()
+        .enumerate()
+        .map(|(i, allowed)| {
+            let (label, usage) = app
+                .cpus
+                .get(i)
+                .map_or((format!("{}", i + 1), 0.0), |(l, u)| (l.clone(), *u));
+            ListItem::new(format!(
+                "[{}] CPU {:>2} {:>5.1}%",
+                if *allowed { "x" } else { " " },
+                label,
+                usage
+            ))
+        })
+        .collect();
+    let mut state = ListState::default();
+    state.select(Some(cursor));
+    Clear.render(f, editor_area);
+    let list = List::new(items)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title("Affinity [Space] [a]ll [Enter] [Esc]"),
+        )
+        .highlight_style(theme.menu_highlight)
+        .highlight_symbol("➡ ");
+    f.render_stateful_widget(list, editor_area, &mut state);
+}
+
+/// Whether `p` has the lower case `filter_lc` in its name, command line, pid, status or user.
+fn process_matches(p: &MProcess, filter_lc: &str) -> bool {
+    p.name.to_lowercase().contains(filter_lc)
+        || p.exe.to_lowercase().contains(filter_lc)
+        || p.command.join(" ").to_lowercase().contains(filter_lc)
+        || format!("{:}", p.pid).contains(filter_lc)
+        || p.status.to_string().to_lowercase().contains(filter_lc)
+        || p.user_name.to_lowercase().contains(filter_lc)
+        || format!("{:}", p.ppid).contains(filter_lc)
+}
+
+/// Row of the first process matching `search` from the row `from` on, going up the table
+/// when not `forward` and wrapping around its ends.
+pub fn find_match(
+    app: &CPUTimeApp,
+    process_table: &[i32],
+    search: &str,
+    from: usize,
+    forward: bool,
+) -> Option<usize> {
+    let len = process_table.len();
+    if search.is_empty() || len == 0 {
+        return None;
+    }
+    let search_lc = search.to_lowercase();
+    (0..len)
+        .map(|i| if forward { (from + i) % len } else { (from % len + len - i) % len })
+        .find(|row| {
+            matches!(app.process_map.get(&process_table[*row]), Some(p) if process_matches(p, &search_lc))
+        })
+}
+
+pub fn filter_process_table<'a>(app: &'a CPUTimeApp, filter: &str, category: bool) -> Cow<'a, [i32]> {
     if filter.is_empty() {
         return Cow::Borrowed(&app.processes);
     }
@@ -496,7 +1074,7 @@ pub fn filter_process_table<'a>(app: &'a CPUTimeApp, filter: &str, show_find_cat
     let filter_lc = filter.to_lowercase();
     #[allow(unused_assignments)]
     let mut results : Vec<i32> = Vec::new();
-    if !show_find_cat {
+    if !category {
         results = app
         .processes
         .iter()
@@ -505,13 +1083,7 @@ pub fn filter_process_table<'a>(app: &'a CPUTimeApp, filter: &str, show_find_cat
                 .process_map
                 .get(pid)
                 .expect("Pid present in processes but not in map.");
-            p.name.to_lowercase().contains(&filter_lc)
-                || p.exe.to_lowercase().contains(&filter_lc)
-                || p.command.join(" ").to_lowercase().contains(&filter_lc)
-                || format!("{:}", p.pid).contains(&filter_lc)
-                || p.status.to_string().to_lowercase().contains(&filter_lc)
-                || p.user_name.to_lowercase().contains(&filter_lc)
-                || format!("{:}", p.ppid).contains(&filter_lc)
+            process_matches(p, &filter_lc)
         })
         .copied()
         .collect();
@@ -0,0 +1,484 @@
+//! The line of text typed at a prompt: filters, PIDs to signal, the refresh rate, commands
+//! to run, tab names and limits. Only one prompt is open at a time, its kind decides where
+//! it's shown and what Enter does with the answer.
+
+use super::theme::Theme;
+use crossterm::event::{KeyCode as Key, KeyEvent, KeyModifiers};
+use std::collections::HashMap;
+use tui::style::{Modifier, Style};
+use tui::text::{Span, Spans};
+
+/// Answers kept per history.
+const HISTORY_LENGTH: usize = 50;
+
+/// What a key did to a [`LineEditor`].
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Edit {
+    Changed,
+    /// Only the cursor moved.
+    Moved,
+    Submit,
+    /// Esc, or Backspace with nothing typed.
+    Cancel,
+    /// Up/Down, recall an older/newer answer.
+    Older,
+    Newer,
+    Ignored,
+}
+
+/// Text with a cursor, edited with the usual readline keys.
+#[derive(Clone, Debug, Default)]
+pub struct LineEditor {
+    text: String,
+    /// Byte offset into `text`, always on a char boundary.
+    cursor: usize,
+}
+
+impl LineEditor {
+    pub fn new(text: &str) -> LineEditor {
+        LineEditor {
+            text: String::from(text),
+            cursor: text.len(),
+        }
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Replace the text, the cursor goes to its end.
+    pub fn set_text(&mut self, text: &str) {
+        self.text = String::from(text);
+        self.cursor = self.text.len();
+    }
+
+    /// Insert at the cursor, e.g. pasted text. Line breaks become blanks rather than
+    /// submitting half of it.
+    pub fn insert_str(&mut self, text: &str) {
+        let text: String = text
+            .chars()
+            .map(|c| if c.is_control() { ' ' } else { c })
+            .collect();
+        self.text.insert_str(self.cursor, &text);
+        self.cursor += text.len();
+    }
+
+    fn previous_char(&self, from: usize) -> usize {
+        self.text[..from]
+            .chars()
+            .next_back()
+            .map_or(0, |c| from - c.len_utf8())
+    }
+
+    fn next_char(&self, from: usize) -> usize {
+        self.text[from..]
+            .chars()
+            .next()
+            .map_or(from, |c| from + c.len_utf8())
+    }
+
+    /// Start of the word before `from`, skipping the blanks in between.
+    fn previous_word(&self, from: usize) -> usize {
+        let before = self.text[..from].trim_end();
+        before
+            .char_indices()
+            .rev()
+            .find(|(_, c)| c.is_whitespace())
+            .map_or(0, |(i, c)| i + c.len_utf8())
+    }
+
+    /// End of the word after `from`, skipping the blanks in between.
+    fn next_word(&self, from: usize) -> usize {
+        let after = &self.text[from..];
+        let blanks = after.len() - after.trim_start().len();
+        let word = &after[blanks..];
+        from + blanks + word.find(char::is_whitespace).unwrap_or(word.len())
+    }
+
+    fn delete(&mut self, from: usize, to: usize) {
+        self.text.replace_range(from..to, "");
+        self.cursor = from;
+    }
+
+    pub fn handle_key(&mut self, key: KeyEvent) -> Edit {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        let alt = key.modifiers.contains(KeyModifiers::ALT);
+        let length = self.text.len();
+        match key.code {
+            Key::Enter => return Edit::Submit,
+            Key::Esc => return Edit::Cancel,
+            Key::Backspace if self.text.is_empty() => return Edit::Cancel,
+            Key::Up => return Edit::Older,
+            Key::Down => return Edit::Newer,
+            Key::Left if ctrl || alt => self.cursor = self.previous_word(self.cursor),
+            Key::Char('b') if alt => self.cursor = self.previous_word(self.cursor),
+            Key::Right if ctrl || alt => self.cursor = self.next_word(self.cursor),
+            Key::Char('f') if alt => self.cursor = self.next_word(self.cursor),
+            Key::Left => self.cursor = self.previous_char(self.cursor),
+            Key::Char('b') if ctrl => self.cursor = self.previous_char(self.cursor),
+            Key::Right => self.cursor = self.next_char(self.cursor),
+            Key::Char('f') if ctrl => self.cursor = self.next_char(self.cursor),
+            Key::Home => self.cursor = 0,
+            Key::Char('a') if ctrl => self.cursor = 0,
+            Key::End => self.cursor = self.text.len(),
+            Key::Char('e') if ctrl => self.cursor = self.text.len(),
+            Key::Backspace if ctrl || alt => self.delete(self.previous_word(self.cursor), self.cursor),
+            Key::Char('w') if ctrl => self.delete(self.previous_word(self.cursor), self.cursor),
+            Key::Char('d') if alt => self.delete(self.cursor, self.next_word(self.cursor)),
+            Key::Backspace => self.delete(self.previous_char(self.cursor), self.cursor),
+            Key::Char('h') if ctrl => self.delete(self.previous_char(self.cursor), self.cursor),
+            Key::Delete => self.delete(self.cursor, self.next_char(self.cursor)),
+            Key::Char('d') if ctrl => self.delete(self.cursor, self.next_char(self.cursor)),
+            Key::Char('u') if ctrl => self.delete(0, self.cursor),
+            Key::Char('k') if ctrl => self.delete(self.cursor, self.text.len()),
+            Key::Char(c) if !ctrl && !alt => self.insert_str(c.encode_utf8(&mut [0; 4])),
+            _ => return Edit::Ignored,
+        }
+        if self.text.len() == length {
+            Edit::Moved
+        } else {
+            Edit::Changed
+        }
+    }
+
+    /// The text in `style`, the cursor as a reversed cell.
+    pub fn spans(&self, style: Style) -> Vec<Span<'_>> {
+        let (before, after) = self.text.split_at(self.cursor);
+        let (under, rest) = after.split_at(self.next_char(self.cursor) - self.cursor);
+        vec![
+            Span::styled(before, style),
+            Span::styled(
+                if under.is_empty() { " " } else { under },
+                style.add_modifier(Modifier::REVERSED),
+            ),
+            Span::styled(rest, style),
+        ]
+    }
+}
+
+/// What a prompt asks for.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PromptKind {
+    Filter,
+    Category,
+    Search,
+    Kill,
+    Suspend,
+    Resume,
+    NicePid,
+    /// Asked after [`PromptKind::NicePid`].
+    NiceValue(i32),
+    Rate,
+    Run,
+    TabName,
+    /// New values of the limit under the cursor of the limits table.
+    Limit,
+    EventFilter,
+}
+
+impl PromptKind {
+    /// Start of the title of the section the prompt is shown in.
+    pub fn label(&self) -> String {
+        let label = match self {
+            PromptKind::Filter => "[ESC] Clear, Find",
+            PromptKind::Category => "[ESC] Clear, Category",
+            PromptKind::Search => "[ESC] Clear, Search",
+            PromptKind::Kill => "[ESC] Clear, PID to kill",
+            PromptKind::Suspend => "[ESC] Clear, PID to suspend",
+            PromptKind::Resume => "[ESC] Clear, PID to resume",
+            PromptKind::NicePid => "[ESC] Clear, PID to nice",
+            PromptKind::NiceValue(pid) => {
+                return format!("[ESC] Clear, Nice value for PID {} (-20 to 19)", pid)
+            }
+            PromptKind::Rate => "[ESC] Clear, set refresh rate in millis",
+            PromptKind::Run => "[ESC] Clear, Run [nice=N cpus=1-2 cwd=DIR VAR=value] command",
+            PromptKind::TabName => "[ESC] Cancel, Tab name",
+            PromptKind::Limit => "[ESC] Cancel, new soft and hard limit",
+            PromptKind::EventFilter => "[ESC] Done, Filter events",
+        };
+        String::from(label)
+    }
+
+    /// Prompts asking for the same kind of answer share their history.
+    fn history(&self) -> &'static str {
+        match self {
+            PromptKind::Filter | PromptKind::Category => "filter",
+            PromptKind::Search => "search",
+            PromptKind::Kill | PromptKind::Suspend | PromptKind::Resume | PromptKind::NicePid => "pid",
+            PromptKind::NiceValue(_) => "nice",
+            PromptKind::Rate => "rate",
+            PromptKind::Run => "run",
+            PromptKind::TabName => "tab",
+            PromptKind::Limit => "limit",
+            PromptKind::EventFilter => "events",
+        }
+    }
+
+    /// Shown in the title of the process table.
+    pub fn in_table(&self) -> bool {
+        !matches!(self, PromptKind::TabName | PromptKind::Limit | PromptKind::EventFilter)
+    }
+
+    /// What's wrong with `text` as an answer. Shown while typing, Enter refuses it.
+    pub fn check(&self, text: &str) -> Option<&'static str> {
+        let text = text.trim();
+        match self {
+            PromptKind::Kill | PromptKind::Suspend | PromptKind::Resume | PromptKind::NicePid => {
+                match text.parse::<i32>() {
+                    Ok(pid) if pid > 0 => None,
+                    _ => Some("Invalid PID"),
+                }
+            }
+            PromptKind::NiceValue(_) => match text.parse::<i32>() {
+                Ok(nice) if (-20..=19).contains(&nice) => None,
+                _ => Some("Invalid nice value"),
+            },
+            PromptKind::Rate => match text.parse::<u64>() {
+                Ok(rate) if rate >= 1000 => None,
+                Ok(_) => Some("The rate must be at least 1000 millis"),
+                Err(_) => Some("Invalid rate"),
+            },
+            PromptKind::Run if text.is_empty() => Some("Nothing to run"),
+            _ => None,
+        }
+    }
+}
+
+/// Earlier answers of each kind of prompt, newest last.
+#[derive(Default)]
+pub struct PromptHistory(HashMap<&'static str, Vec<String>>);
+
+impl PromptHistory {
+    pub fn add(&mut self, kind: PromptKind, answer: &str) {
+        if answer.trim().is_empty() {
+            return;
+        }
+        let answers = self.0.entry(kind.history()).or_default();
+        answers.retain(|a| a != answer);
+        answers.push(String::from(answer));
+        if answers.len() > HISTORY_LENGTH {
+            answers.remove(0);
+        }
+    }
+
+    fn answers(&self, kind: PromptKind) -> &[String] {
+        self.0.get(kind.history()).map_or(&[], Vec::as_slice)
+    }
+}
+
+pub struct Prompt {
+    pub kind: PromptKind,
+    pub editor: LineEditor,
+    /// Outcome of the last answer, e.g. what sending the signal did.
+    pub message: Option<String>,
+    /// Why the last answer was refused.
+    pub error: Option<&'static str>,
+    /// Recalled answer, counted from the newest, and the text typed before recalling it.
+    recalled: Option<(usize, String)>,
+}
+
+impl Prompt {
+    pub fn new(kind: PromptKind) -> Prompt {
+        Prompt::with_text(kind, "")
+    }
+
+    pub fn with_text(kind: PromptKind, text: &str) -> Prompt {
+        Prompt {
+            kind,
+            editor: LineEditor::new(text),
+            message: None,
+            error: None,
+            recalled: None,
+        }
+    }
+
+    pub fn text(&self) -> &str {
+        self.editor.text()
+    }
+
+    /// Ready for the next answer, showing what the last one did.
+    pub fn answered(&mut self, message: String) {
+        self.editor.set_text("");
+        self.message = Some(message);
+        self.recalled = None;
+    }
+
+    /// Show an older or newer answer from `history`, going past the newest brings back
+    /// what was being typed.
+    pub fn recall(&mut self, history: &PromptHistory, older: bool) {
+        let answers = history.answers(self.kind);
+        let index = match (self.recalled.as_ref().map(|(i, _)| *i), older) {
+            (None, true) => Some(0),
+            (Some(i), true) => Some(i + 1),
+            (Some(0), false) => None,
+            (Some(i), false) => Some(i - 1),
+            (None, false) => return,
+        };
+        match index {
+            Some(i) if i >= answers.len() => {}
+            Some(i) => {
+                let typed = match self.recalled.take() {
+                    Some((_, typed)) => typed,
+                    None => String::from(self.editor.text()),
+                };
+                self.editor.set_text(&answers[answers.len() - 1 - i]);
+                self.recalled = Some((i, typed));
+            }
+            None => {
+                if let Some((_, typed)) = self.recalled.take() {
+                    self.editor.set_text(&typed);
+                }
+            }
+        }
+    }
+
+    /// `label: text` with the cursor, followed by what's wrong with the text or the outcome
+    /// of the last answer.
+    pub fn title<'a>(&'a self, label: &str, theme: &Theme) -> Spans<'a> {
+        let mut spans = vec![Span::raw(format!("{}: ", label))];
+        spans.extend(self.editor.spans(Style::default()));
+        let problem = match self.text().trim() {
+            "" => self.error,
+            text => self.kind.check(text).or(self.error),
+        };
+        if let Some(problem) = problem {
+            spans.push(Span::styled(format!(" {}", problem), theme.warn));
+        } else if let Some(message) = &self.message {
+            spans.push(Span::raw(format!(" {}", message)));
+        }
+        Spans::from(spans)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(code: Key) -> KeyEvent {
+        KeyEvent::new(code, KeyModifiers::NONE)
+    }
+
+    fn ctrl(c: char) -> KeyEvent {
+        KeyEvent::new(Key::Char(c), KeyModifiers::CONTROL)
+    }
+
+    fn alt(code: Key) -> KeyEvent {
+        KeyEvent::new(code, KeyModifiers::ALT)
+    }
+
+    fn typed(text: &str) -> LineEditor {
+        let mut editor = LineEditor::default();
+        for c in text.chars() {
+            assert_eq!(editor.handle_key(key(Key::Char(c))), Edit::Changed);
+        }
+        editor
+    }
+
+    #[test]
+    fn moves_by_character_and_word() {
+        let mut editor = typed("kill  the server");
+        assert_eq!(editor.handle_key(alt(Key::Left)), Edit::Moved);
+        assert_eq!(editor.cursor, 10);
+        editor.handle_key(ctrl('b'));
+        assert_eq!(editor.cursor, 9);
+        editor.handle_key(alt(Key::Char('b')));
+        assert_eq!(editor.cursor, 6);
+        editor.handle_key(alt(Key::Char('b')));
+        assert_eq!(editor.cursor, 0);
+        editor.handle_key(KeyEvent::new(Key::Right, KeyModifiers::CONTROL));
+        assert_eq!(editor.cursor, 4);
+        editor.handle_key(alt(Key::Char('f')));
+        assert_eq!(editor.cursor, 9);
+        editor.handle_key(ctrl('e'));
+        assert_eq!(editor.cursor, 16);
+        editor.handle_key(key(Key::Home));
+        assert_eq!(editor.cursor, 0);
+    }
+
+    #[test]
+    fn deletes() {
+        let mut editor = typed("one two three");
+        editor.handle_key(ctrl('w'));
+        assert_eq!(editor.text(), "one two ");
+        editor.handle_key(key(Key::Backspace));
+        assert_eq!(editor.text(), "one two");
+        editor.handle_key(ctrl('a'));
+        editor.handle_key(alt(Key::Char('d')));
+        assert_eq!(editor.text(), " two");
+        editor.handle_key(ctrl('d'));
+        assert_eq!(editor.text(), "two");
+        editor.handle_key(key(Key::Right));
+        editor.handle_key(ctrl('k'));
+        assert_eq!(editor.text(), "t");
+        editor.handle_key(ctrl('u'));
+        assert_eq!((editor.text(), editor.cursor), ("", 0));
+        // nothing left to delete
+        assert_eq!(editor.handle_key(key(Key::Backspace)), Edit::Cancel);
+    }
+
+    #[test]
+    fn keeps_multibyte_characters_whole() {
+        let mut editor = typed("héllo");
+        editor.handle_key(key(Key::Left));
+        editor.handle_key(key(Key::Left));
+        editor.handle_key(key(Key::Left));
+        editor.handle_key(key(Key::Left));
+        assert_eq!(editor.cursor, 1);
+        editor.handle_key(key(Key::Delete));
+        assert_eq!(editor.text(), "hllo");
+        editor.handle_key(key(Key::End));
+        editor.handle_key(key(Key::Left));
+        editor.insert_str("ü");
+        assert_eq!(editor.text(), "hllüo");
+    }
+
+    #[test]
+    fn pasted_line_breaks_become_blanks() {
+        let mut editor = LineEditor::new("ls");
+        editor.insert_str(" -l\n/tmp\t");
+        assert_eq!(editor.text(), "ls -l /tmp ");
+    }
+
+    #[test]
+    fn submit_cancel_and_history_keys() {
+        let mut editor = typed("x");
+        assert_eq!(editor.handle_key(key(Key::Enter)), Edit::Submit);
+        assert_eq!(editor.handle_key(key(Key::Esc)), Edit::Cancel);
+        assert_eq!(editor.handle_key(key(Key::Up)), Edit::Older);
+        assert_eq!(editor.handle_key(key(Key::Down)), Edit::Newer);
+        assert_eq!(editor.handle_key(key(Key::F(5))), Edit::Ignored);
+        assert_eq!(editor.text(), "x");
+    }
+
+    #[test]
+    fn recalls_answers_newest_first() {
+        let mut history = PromptHistory::default();
+        history.add(PromptKind::Kill, "12");
+        history.add(PromptKind::Suspend, "34");
+        history.add(PromptKind::Kill, "12");
+        history.add(PromptKind::Run, "make");
+        let mut prompt = Prompt::with_text(PromptKind::Resume, "5");
+        prompt.recall(&history, true);
+        assert_eq!(prompt.text(), "12");
+        prompt.recall(&history, true);
+        assert_eq!(prompt.text(), "34");
+        prompt.recall(&history, true);
+        assert_eq!(prompt.text(), "34");
+        prompt.recall(&history, false);
+        prompt.recall(&history, false);
+        // back to what was typed
+        assert_eq!(prompt.text(), "5");
+    }
+
+    #[test]
+    fn checks_answers() {
+        assert_eq!(PromptKind::Kill.check(" 42 "), None);
+        assert_eq!(PromptKind::Kill.check("0"), Some("Invalid PID"));
+        assert_eq!(PromptKind::NiceValue(1).check("-20"), None);
+        assert_eq!(PromptKind::NiceValue(1).check("20"), Some("Invalid nice value"));
+        assert_eq!(PromptKind::Rate.check("999"), Some("The rate must be at least 1000 millis"));
+        assert_eq!(PromptKind::Rate.check("1000"), None);
+        assert_eq!(PromptKind::Run.check("  "), Some("Nothing to run"));
+        assert_eq!(PromptKind::Filter.check(""), None);
+    }
+}
@@ -1,29 +1,50 @@
 use tui::layout::{Alignment, Constraint, Direction, Layout, Rect};
-use tui::style::{Color, Modifier, Style};
+use tui::style::{Modifier, Style};
 use tui::text::{Span, Spans};
 use tui::widgets::{Block, Borders, List, ListItem, ListState, Paragraph, Wrap};
 use tui::Frame;
 
+use super::layout::SectionLayout;
+use super::theme::Theme;
 use super::{FromPrimitive, Render, MBackend};
-use std::collections::HashSet;
 use std::fmt;
 
 #[derive(FromPrimitive, PartialEq, Copy, Clone, Debug, Ord, PartialOrd, Eq)]
 pub enum Section {
     SystemInfo = 0,
-    Process = 1,
+    Sensors = 1,
+    Events = 2,
+    Exits = 3,
+    Process = 4,
 }
 
 impl fmt::Display for Section {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let name = match self {
             Section::SystemInfo => " System Info",
+            Section::Sensors => " Sensors",
+            Section::Events => " Events",
+            Section::Exits => " Exited",
             Section::Process => " Process",
         };
         write!(f, "{}", name)
     }
 }
 
+impl Section {
+    /// Names of the sections in layouts, in the order of the enum.
+    pub const KEYS: [&'static str; 5] = ["system_info", "sensors", "events", "exits", "process"];
+
+    pub fn key(self) -> &'static str {
+        Section::KEYS[self as usize]
+    }
+
+    pub fn from_key(key: &str) -> Option<Section> {
+        let index = Section::KEYS.iter().position(|k| *k == key)?;
+        FromPrimitive::from_usize(index)
+    }
+}
+
 pub fn sum_section_heights(geometry: &[(Section, f64)]) -> f64 {
     let mut sum = 0.0;
     for section in geometry {
@@ -38,25 +59,19 @@ pub struct SectionMGRList<'a> {
 }
 
 impl<'a> SectionMGRList<'a> {
-    pub fn with_geometry(geometry: Vec<(Section, f64)>) -> SectionMGRList<'a> {
-        let mut section_set = HashSet::new();
-
-        for (s, _) in geometry {
-            section_set.insert(format!("{}", s));
-        }
-
+    pub fn with_layout(layout: &SectionLayout) -> SectionMGRList<'a> {
         let mut state = ListState::default();
-        let items: Vec<(Section, ListItem)> = [0, 1]
+        let items: Vec<(Section, ListItem)> = [0, 1, 2, 3, 4]
             .iter()
             .map(|i| {
                 let section: Section = FromPrimitive::from_u32(*i as u32)
                     .expect("Index not in range for Section enum");
                 let s: String = format!("{}", section);
-                if section_set.contains(s.as_str()) {
+                if let Some((row, width)) = layout.find(section) {
                     (
                         section,
                         Span::styled(
-                            format!("*{}", s),
+                            format!("*{:<14} height {:<16} width {}", s, row.height.to_string(), width),
                             Style::default().add_modifier(Modifier::BOLD),
                         ),
                     )
@@ -75,7 +90,13 @@ impl<'a> SectionMGRList<'a> {
     }
 }
 
-pub fn render_section_mgr(list: &mut SectionMGRList<'_>, area: Rect, f: &mut Frame<'_, MBackend>) {
+/// Returns where the list went, for mouse clicks.
+pub fn render_section_mgr(
+    list: &mut SectionMGRList<'_>,
+    theme: &Theme,
+    area: Rect,
+    f: &mut Frame<'_, MBackend>,
+) -> Rect {
     let layout = Layout::default()
         .margin(5)
         .direction(Direction::Vertical)
@@ -88,10 +109,10 @@ pub fn render_section_mgr(list: &mut SectionMGRList<'_>, area: Rect, f: &mut Fra
             .as_ref(),
         )
         .split(area);
-    let header_style = Style::default().fg(Color::Green);
+    let header_style = theme.heading;
     let t = vec![Span::styled("Options", header_style)];
     let help = vec![Span::styled(
-        "Navigate [↑/↓] Toggle [Space] Return [i]",
+        "Navigate [↑/↓] Toggle [Space] Taller/Shorter [+/-] Wider/Narrower [>/<] Return [i]",
         header_style,
     )];
     Paragraph::new(Spans::from(t))
@@ -105,7 +126,8 @@ pub fn render_section_mgr(list: &mut SectionMGRList<'_>, area: Rect, f: &mut Fra
     let list_items: Vec<ListItem> = list.items.iter().map(|i| i.1.clone()).collect();
     let list_widget = List::new(list_items)
         .block(Block::default().title("Sections").borders(Borders::ALL))
-        .highlight_style(Style::default().bg(Color::Green))
+        .highlight_style(theme.menu_highlight)
         .highlight_symbol("➡ ");
     f.render_stateful_widget(list_widget, layout[1], &mut list.state);
+    layout[1]
 }
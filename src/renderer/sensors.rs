@@ -0,0 +1,101 @@
+use super::{Render, MBackend};
+use super::theme::Theme;
+use crate::metrics::sensors::TemperatureLevel;
+use crate::metrics::*;
+use tui::layout::{Constraint, Direction, Layout, Rect};
+use tui::style::Style;
+use tui::widgets::{Block, Borders, Cell, Paragraph, Row, Table, Wrap};
+use tui::Frame;
+
+fn temperature_style(level: TemperatureLevel, theme: &Theme) -> Style {
+    match level {
+        TemperatureLevel::Normal => theme.good,
+        TemperatureLevel::Warm => theme.warn,
+        TemperatureLevel::High => theme.bad,
+        TemperatureLevel::Critical => theme.critical,
+    }
+}
+
+fn format_threshold(t: Option<f64>) -> String {
+    match t {
+        Some(t) => format!("{:>5.1}°C", t),
+        None => String::from("     - "),
+    }
+}
+
+pub fn render_sensors(
+    app: &CPUTimeApp,
+    theme: &Theme,
+    area: Rect,
+    f: &mut Frame<'_, MBackend>,
+) {
+    let sensors = &app.sensors;
+    if sensors.temperatures.is_empty() && sensors.fans.is_empty() {
+        Paragraph::new(format!(
+            "No temperature or fan sensors found under {}",
+            sensors.root.display()
+        ))
+        .block(Block::default().borders(Borders::ALL).title("Sensors"))
+        .wrap(Wrap { trim: true })
+        .render(f, area);
+        return;
+    }
+
+    let h_sections = if sensors.fans.is_empty() {
+        vec![area]
+    } else {
+        Layout::default()
+            .direction(Direction::Horizontal)
+            .margin(0)
+            .constraints([Constraint::Percentage(70), Constraint::Percentage(30)].as_ref())
+            .split(area)
+    };
+
+    let header_style = theme.header;
+    let temp_rows: Vec<Row> = sensors
+        .temperatures
+        .iter()
+        .map(|t| {
+            Row::new(vec![
+                Cell::from(t.label.clone()),
+                Cell::from(format!("{:>5.1}°C", t.current)).style(temperature_style(t.level(), theme)),
+                Cell::from(format_threshold(t.high)),
+                Cell::from(format_threshold(t.critical)),
+            ])
+        })
+        .collect();
+    Table::new(temp_rows)
+        .block(Block::default().borders(Borders::ALL).title("Sensors"))
+        .header(Row::new(vec!["SENSOR", "CURRENT", "HIGH", "CRITICAL"]).style(header_style))
+        .widths(
+            [
+                Constraint::Min(20),
+                Constraint::Length(9),
+                Constraint::Length(9),
+                Constraint::Length(9),
+            ]
+            .as_ref(),
+        )
+        .column_spacing(1)
+        .render(f, h_sections[0]);
+
+    if !sensors.fans.is_empty() {
+        let fan_rows: Vec<Row> = sensors
+            .fans
+            .iter()
+            .map(|fan| {
+                let style = if fan.rpm == 0 { theme.dimmed } else { theme.info };
+                Row::new(vec![
+                    Cell::from(fan.label.clone()),
+                    Cell::from(format!("{:>6} RPM", fan.rpm)).style(style),
+                ])
+            })
+            .collect();
+        Table::new(fan_rows)
+            .block(Block::default().borders(Borders::ALL).title("Fans"))
+            .header(Row::new(vec!["FAN", "SPEED"]).style(header_style))
+            .widths([Constraint::Min(10), Constraint::Length(10)].as_ref())
+            .column_spacing(1)
+            .render(f, h_sections[1]);
+    }
+}
@@ -0,0 +1,92 @@
+//! Stopping a process: SIGTERM first, then SIGKILL if it's still running once the timeout is up.
+
+use crate::metrics::mprocess::MProcess;
+use crate::metrics::CPUTimeApp;
+use std::time::{Duration, Instant};
+use sysinfo::ProcessStatus;
+
+/// A process sent SIGTERM, waiting to exit.
+pub struct Stop {
+    pub pid: i32,
+    pub name: String,
+    /// Tells the process apart from a later one given the same PID.
+    start_time: u64,
+    sent: Instant,
+    timeout: Duration,
+}
+
+/// What became of a stop at the last update.
+pub enum StopState {
+    Waiting,
+    Exited,
+    /// Still running with the timeout up, time for SIGKILL.
+    Overdue,
+}
+
+impl Stop {
+    pub fn new(p: &MProcess, timeout: Duration) -> Stop {
+        Stop {
+            pid: p.pid,
+            name: p.name.clone(),
+            start_time: p.start_time,
+            sent: Instant::now(),
+            timeout,
+        }
+    }
+
+    /// Looks the process up in the latest update, a zombie or a new process with the PID
+    /// counts as exited.
+    pub fn state(&self, app: &CPUTimeApp) -> StopState {
+        let running = matches!(app.process_map.get(&self.pid), Some(p)
+            if p.start_time == self.start_time
+                && p.end_time.is_none()
+                && p.status != ProcessStatus::Zombie);
+        if !running {
+            StopState::Exited
+        } else if self.sent.elapsed() >= self.timeout {
+            StopState::Overdue
+        } else {
+            StopState::Waiting
+        }
+    }
+
+    /// The countdown for the process view.
+    pub fn countdown(&self) -> String {
+        let left = self.timeout.saturating_sub(self.sent.elapsed());
+        // also while frozen, SIGKILL waits for an update to check the process
+        if left.is_zero() {
+            String::from("Sent SIGTERM, SIGKILL once an update shows it still running.")
+        } else {
+            // round up so the countdown ends on 1s, not 0s
+            let secs = left.as_millis().div_ceil(1000);
+            format!("Sent SIGTERM, SIGKILL in {}s if it's still running.", secs)
+        }
+    }
+
+    pub fn exited_message(&self) -> String {
+        format!(
+            "PID {} {} exited {:.1}s after SIGTERM.",
+            self.pid,
+            self.name,
+            self.sent.elapsed().as_secs_f32()
+        )
+    }
+
+    pub fn start_time(&self) -> u64 {
+        self.start_time
+    }
+
+    /// `result` is what sending SIGKILL returned.
+    pub fn killed_message(&self, result: &Result<(), String>) -> String {
+        format!(
+            "PID {} {} still ran {}s after SIGTERM, SIGKILL: {}",
+            self.pid,
+            self.name,
+            self.sent.elapsed().as_secs(),
+            match result {
+                Ok(_) => "sent.",
+                Err(e) => e,
+            }
+        )
+    }
+}
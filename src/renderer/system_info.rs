@@ -1,7 +1,8 @@
 use super::{Render, MBackend};
+use super::theme::Theme;
 use crate::metrics::*;
 use tui::layout::Rect;
-use tui::style::{Color, Modifier, Style};
+use tui::style::{Modifier, Style};
 use tui::text::{Span, Spans};
 use tui::widgets::Paragraph;
 use tui::Frame;
@@ -10,6 +11,7 @@ use crate::util::to_gb;
 
 pub fn render_system_info(
     app: &CPUTimeApp,
+    theme: &Theme,
     area: Rect,
     f: &mut Frame<'_, MBackend>,
 ) {
@@ -23,11 +25,27 @@ pub fn render_system_info(
         ));
         cpu_spans.push(Span::styled(
             format!("{:>4.1}% ", cpu),
-            Style::default().fg(Color::Blue),
+            theme.info,
         ));
     });
 
+    let uptime = app.uptime.as_secs();
     let text = vec![
+        // Host
+        Spans::from(vec![
+            Span::styled("Host: ", bold_style),
+            Span::styled(format!("{} ", app.hostname), theme.accent),
+            Span::raw(format!("{} {} ({}) ", app.osname, app.release, app.arch)),
+            Span::styled("CPU: ", bold_style),
+            Span::raw(format!("{} @ {} MHz ", app.processor_name, app.frequency)),
+            Span::styled("Up: ", bold_style),
+            Span::raw(format!(
+                "{}d {:0>2}:{:0>2}",
+                uptime / 86400,
+                (uptime / 3600) % 24,
+                (uptime / 60) % 60
+            )),
+        ]),
         // Memory
         Spans::from(vec![
             Span::styled("Memory: ", bold_style),
@@ -48,25 +66,31 @@ pub fn render_system_info(
         // Tasks (Processes)
         Spans::from(vec![
             Span::styled("Tasks: ", bold_style),
-            Span::styled(format!("{:>3} ", app.total_processes), Style::default().fg(Color::White)),
+            Span::styled(format!("{:>3} ", app.total_processes), theme.text),
             Span::raw("total, "),
-            Span::styled(format!("{:>3} ", app.running_processes), Style::default().fg(Color::Green)),
+            Span::styled(format!("{:>3} ", app.running_processes), theme.good),
             Span::raw("running, "),
-            Span::styled(format!("{:>3} ", app.sleeping_processes), Style::default().fg(Color::Yellow)),
+            Span::styled(format!("{:>3} ", app.sleeping_processes), theme.warn),
             Span::raw("sleeping, "),
-            Span::styled(format!("{:>3} ", app.stopped_processes), Style::default().fg(Color::Red)),
+            Span::styled(format!("{:>3} ", app.stopped_processes), theme.bad),
             Span::raw("stopped, "),
-            Span::styled(format!(" {:>3} ", app.zombie_processes), Style::default().fg(Color::Blue)),
+            Span::styled(format!(" {:>3} ", app.zombie_processes), theme.info),
             Span::raw("zombie."),
         ]),
         // CPU
         Spans::from(cpu_spans),
     ];
 
+    let title = match (&app.remote, &app.remote_error) {
+        (Some(r), Some(e)) => format!("System Info - agent {} (disconnected: {})", r.address, e),
+        (Some(r), None) => format!("System Info - agent {}", r.address),
+        _ => String::from("System Info"),
+    };
+
     Paragraph::new(text)
         .block(Block::default()
             .borders(Borders::ALL)
-            .title("System Info"))
+            .title(title))
         .style(Style::default())
         .wrap(Wrap { trim: true }).render(f, area);
 }
\ No newline at end of file
@@ -0,0 +1,175 @@
+//! Named views of the process table, each with its own filter, sort, columns and highlighted
+//! process. They are saved to a text file, a `tab NAME` line starting each tab:
+//!
+//! ```text
+//! tab service
+//! filter postgres
+//! sort mem descending
+//! column pid default
+//! column mem auto
+//! column cmd default
+//! ```
+//!
+//! A category filter (`c`) is saved as `category running` in place of `filter`.
+
+use super::column::{column_line, parse_column_line, Column, ColumnWidth};
+use super::prompt::Prompt;
+use super::theme::Theme;
+use super::{MBackend, Render};
+use crate::metrics::ProcessTableSortOrder;
+use crate::util::config_dir;
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use tui::layout::Rect;
+use tui::style::Style;
+use tui::text::{Span, Spans};
+use tui::widgets::Paragraph;
+use tui::Frame;
+
+/// Number keys switch between the first tabs.
+pub const MAX_TABS: usize = 9;
+
+#[derive(Clone, Debug)]
+pub struct Tab {
+    pub name: String,
+    pub filter: String,
+    /// The filter is a category rather than text.
+    pub filter_category: bool,
+    pub sort_by: Column,
+    pub sort_order: ProcessTableSortOrder,
+    pub columns: Vec<Column>,
+    pub column_widths: HashMap<Column, ColumnWidth>,
+    /// Followed across refreshes rather than the row, which moves as the table is sorted.
+    pub highlighted_pid: Option<i32>,
+    pub row_start: usize,
+}
+
+impl Tab {
+    pub fn new(name: &str, columns: Vec<Column>, column_widths: HashMap<Column, ColumnWidth>) -> Tab {
+        Tab {
+            name: String::from(name),
+            filter: String::new(),
+            filter_category: false,
+            sort_by: Column::CPUPercentage,
+            sort_order: ProcessTableSortOrder::Descending,
+            columns,
+            column_widths,
+            highlighted_pid: None,
+            row_start: 0,
+        }
+    }
+
+    fn to_text(&self) -> String {
+        let order = match self.sort_order {
+            ProcessTableSortOrder::Ascending => "ascending",
+            ProcessTableSortOrder::Descending => "descending",
+        };
+        let mut text = format!("tab {}\n", self.name);
+        if !self.filter.is_empty() {
+            let key = if self.filter_category { "category" } else { "filter" };
+            text += &format!("{} {}\n", key, self.filter);
+        }
+        text += &format!("sort {} {}\n", self.sort_by.key(), order);
+        for c in &self.columns {
+            text += &format!("column {}\n", column_line(*c, &self.column_widths));
+        }
+        text
+    }
+}
+
+fn tabs_path() -> PathBuf {
+    config_dir().join("tabs")
+}
+
+/// Tabs as they were last left. Unknown lines are skipped, tabs without columns get `columns`.
+pub fn load_tabs(columns: &[Column]) -> Vec<Tab> {
+    let text = match fs::read_to_string(tabs_path()) {
+        Ok(t) => t,
+        Err(_) => return vec![],
+    };
+    let mut tabs: Vec<Tab> = vec![];
+    for line in text.lines() {
+        let (key, value) = match line.trim().split_once(' ') {
+            Some((k, v)) => (k, v.trim()),
+            None => continue,
+        };
+        if key == "tab" {
+            tabs.push(Tab::new(value, vec![], HashMap::new()));
+            continue;
+        }
+        let tab = match tabs.last_mut() {
+            Some(t) => t,
+            None => continue,
+        };
+        match key {
+            "filter" | "category" => {
+                tab.filter = String::from(value);
+                tab.filter_category = key == "category";
+            }
+            "sort" => {
+                let mut words = value.split_whitespace();
+                if let Some(c) = words.next().and_then(Column::from_key) {
+                    tab.sort_by = c;
+                }
+                if words.next() == Some("ascending") {
+                    tab.sort_order = ProcessTableSortOrder::Ascending;
+                }
+            }
+            "column" => {
+                if let Some((c, width)) = parse_column_line(value) {
+                    if !tab.columns.contains(&c) {
+                        tab.columns.push(c);
+                        if width != ColumnWidth::Default {
+                            tab.column_widths.insert(c, width);
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    for tab in tabs.iter_mut().filter(|t| t.columns.is_empty()) {
+        tab.columns = columns.to_vec();
+    }
+    tabs.truncate(MAX_TABS);
+    tabs
+}
+
+pub fn save_tabs(tabs: &[Tab]) -> Result<(), String> {
+    let path = tabs_path();
+    let text: String = tabs.iter().map(Tab::to_text).collect::<Vec<_>>().join("\n");
+    path.parent()
+        .map_or(Ok(()), fs::create_dir_all)
+        .and_then(|_| fs::write(&path, text))
+        .map_err(|e| format!("Couldn't save the tabs to {}: {}", path.display(), e))
+}
+
+/// `1 all  2 service` along the top line, the active tab highlighted, followed by the
+/// prompt for the name when renaming and the last error.
+pub fn render_tab_bar(
+    tabs: &[Tab],
+    active: usize,
+    prompt: Option<&Prompt>,
+    message: Option<&str>,
+    theme: &Theme,
+    area: Rect,
+    f: &mut Frame<'_, MBackend>,
+) {
+    let mut spans = vec![];
+    for (i, tab) in tabs.iter().enumerate() {
+        let style = if i == active {
+            theme.selected
+        } else {
+            Style::default()
+        };
+        spans.push(Span::styled(format!(" {} {} ", i + 1, tab.name), style));
+        spans.push(Span::raw(" "));
+    }
+    if let Some(prompt) = prompt {
+        spans.extend(prompt.title(&prompt.kind.label(), theme).0);
+    } else if let Some(message) = message {
+        spans.push(Span::styled(String::from(message), theme.warn));
+    }
+    Paragraph::new(Spans::from(spans)).render(f, area);
+}
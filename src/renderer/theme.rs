@@ -0,0 +1,480 @@
+//! Styles the interface is drawn with. Widgets ask for the style of a role (table header,
+//! warning, ...) rather than naming colours, so a theme decides the look of the whole screen.
+
+use crate::util::config_dir;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tui::style::{Color, Modifier, Style};
+
+pub const BUILTIN_THEMES: &[&str] = &["dark", "light", "high-contrast", "monochrome"];
+
+#[derive(Clone, Debug)]
+pub struct Theme {
+    /// Emphasized plain values, e.g. the total task count.
+    pub text: Style,
+    /// Things that are gone or idle: exited processes, stopped fans.
+    pub dimmed: Style,
+    /// Host name, keys in the help screen, border of the focused section.
+    pub accent: Style,
+    /// Headings of the help screen and the menus.
+    pub heading: Style,
+    /// Values in the process view.
+    pub value: Style,
+    /// CPU usage, fan speeds, zombie count.
+    pub info: Style,
+    pub good: Style,
+    pub warn: Style,
+    pub bad: Style,
+    /// Temperatures past the critical threshold.
+    pub critical: Style,
+    /// Table headers.
+    pub header: Style,
+    /// Highlighted table row and sort column.
+    pub selected: Style,
+    /// Highlighted entry of the menus and editors.
+    pub menu_highlight: Style,
+    /// The FROZEN badge.
+    pub badge: Style,
+    /// Refresh rate and the action bar of the process view.
+    pub status_bar: Style,
+    /// Text found by the process table search.
+    pub search_match: Style,
+}
+
+fn fg(color: Color) -> Style {
+    Style::default().fg(color)
+}
+
+fn on(fg: Color, bg: Color) -> Style {
+    Style::default().fg(fg).bg(bg)
+}
+
+fn with(modifier: Modifier) -> Style {
+    Style::default().add_modifier(modifier)
+}
+
+/// The 6 levels of each channel in the 256 colour cube.
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+/// What xterm shows a colour as by default, none for the terminal's default colour.
+fn rgb(color: Color) -> Option<(u8, u8, u8)> {
+    const BASIC: [(u8, u8, u8); 16] = [
+        (0, 0, 0),
+        (205, 0, 0),
+        (0, 205, 0),
+        (205, 205, 0),
+        (0, 0, 238),
+        (205, 0, 205),
+        (0, 205, 205),
+        (229, 229, 229),
+        (127, 127, 127),
+        (255, 0, 0),
+        (0, 255, 0),
+        (255, 255, 0),
+        (92, 92, 255),
+        (255, 0, 255),
+        (0, 255, 255),
+        (255, 255, 255),
+    ];
+    let index = match color {
+        Color::Reset => return None,
+        Color::Rgb(r, g, b) => return Some((r, g, b)),
+        Color::Black => 0,
+        Color::Red => 1,
+        Color::Green => 2,
+        Color::Yellow => 3,
+        Color::Blue => 4,
+        Color::Magenta => 5,
+        Color::Cyan => 6,
+        Color::Gray => 7,
+        Color::DarkGray => 8,
+        Color::LightRed => 9,
+        Color::LightGreen => 10,
+        Color::LightYellow => 11,
+        Color::LightBlue => 12,
+        Color::LightMagenta => 13,
+        Color::LightCyan => 14,
+        Color::White => 15,
+        Color::Indexed(i) => i,
+    };
+    Some(match index {
+        0..=15 => BASIC[index as usize],
+        16..=231 => {
+            let i = index as usize - 16;
+            (CUBE_LEVELS[i / 36], CUBE_LEVELS[(i / 6) % 6], CUBE_LEVELS[i % 6])
+        }
+        _ => {
+            let level = 8 + 10 * (index - 232);
+            (level, level, level)
+        }
+    })
+}
+
+/// The nearest colour of the 256 colour cube.
+fn indexed((r, g, b): (u8, u8, u8)) -> Color {
+    let level = |c: u8| {
+        (0..CUBE_LEVELS.len())
+            .min_by_key(|i| (CUBE_LEVELS[*i] as i16 - c as i16).abs())
+            .unwrap_or(0) as u8
+    };
+    Color::Indexed(16 + 36 * level(r) + 6 * level(g) + level(b))
+}
+
+/// `t` of the way from `a` to `b`.
+fn blend(a: (u8, u8, u8), b: (u8, u8, u8), t: f32) -> (u8, u8, u8) {
+    let mix = |x: u8, y: u8| (x as f32 + (y as f32 - x as f32) * t).round() as u8;
+    (mix(a.0, b.0), mix(a.1, b.1), mix(a.2, b.2))
+}
+
+impl Theme {
+    pub fn dark() -> Theme {
+        Theme {
+            text: fg(Color::White),
+            dimmed: fg(Color::DarkGray),
+            accent: fg(Color::Cyan),
+            heading: fg(Color::Green),
+            value: fg(Color::Green),
+            info: fg(Color::Blue),
+            good: fg(Color::Green),
+            warn: fg(Color::Yellow),
+            bad: fg(Color::Red),
+            critical: fg(Color::Red).add_modifier(Modifier::BOLD | Modifier::REVERSED),
+            header: Style::default().bg(Color::DarkGray),
+            selected: on(Color::Black, Color::Gray).add_modifier(Modifier::BOLD),
+            menu_highlight: on(Color::Black, Color::Green),
+            badge: on(Color::White, Color::Blue).add_modifier(Modifier::BOLD),
+            status_bar: on(Color::White, Color::DarkGray),
+            search_match: on(Color::Black, Color::Yellow),
+        }
+    }
+
+    /// For terminals with a light background, where white, yellow and gray wash out.
+    pub fn light() -> Theme {
+        Theme {
+            text: with(Modifier::BOLD),
+            dimmed: fg(Color::Gray),
+            accent: fg(Color::Blue),
+            heading: fg(Color::Blue).add_modifier(Modifier::BOLD),
+            value: fg(Color::Green),
+            info: fg(Color::Blue),
+            good: fg(Color::Green),
+            // 256 colour dark orange, yellow is unreadable on white
+            warn: fg(Color::Indexed(130)),
+            bad: fg(Color::Red),
+            critical: fg(Color::Red).add_modifier(Modifier::BOLD | Modifier::REVERSED),
+            header: on(Color::Black, Color::Gray),
+            selected: on(Color::White, Color::Blue).add_modifier(Modifier::BOLD),
+            menu_highlight: on(Color::Black, Color::Cyan),
+            badge: on(Color::White, Color::Blue).add_modifier(Modifier::BOLD),
+            status_bar: on(Color::Black, Color::Gray),
+            search_match: on(Color::Black, Color::Yellow),
+        }
+    }
+
+    pub fn high_contrast() -> Theme {
+        Theme {
+            text: fg(Color::White).add_modifier(Modifier::BOLD),
+            dimmed: fg(Color::Gray),
+            accent: fg(Color::LightCyan).add_modifier(Modifier::BOLD),
+            heading: fg(Color::LightGreen).add_modifier(Modifier::BOLD),
+            value: fg(Color::White),
+            info: fg(Color::LightBlue),
+            good: fg(Color::LightGreen),
+            warn: fg(Color::LightYellow),
+            bad: fg(Color::LightRed).add_modifier(Modifier::BOLD),
+            critical: on(Color::Black, Color::LightRed).add_modifier(Modifier::BOLD),
+            header: on(Color::Black, Color::White).add_modifier(Modifier::BOLD),
+            selected: on(Color::Black, Color::LightYellow).add_modifier(Modifier::BOLD),
+            menu_highlight: on(Color::Black, Color::LightYellow),
+            badge: on(Color::Black, Color::LightCyan).add_modifier(Modifier::BOLD),
+            status_bar: on(Color::Black, Color::White),
+            search_match: on(Color::Black, Color::LightMagenta),
+        }
+    }
+
+    /// No colours at all, only bold, dim, underline and reverse video.
+    pub fn monochrome() -> Theme {
+        Theme {
+            text: Style::default(),
+            dimmed: with(Modifier::DIM),
+            accent: with(Modifier::BOLD),
+            heading: with(Modifier::BOLD),
+            value: Style::default(),
+            info: Style::default(),
+            good: Style::default(),
+            warn: with(Modifier::BOLD),
+            bad: with(Modifier::BOLD),
+            critical: with(Modifier::BOLD | Modifier::REVERSED),
+            header: with(Modifier::BOLD | Modifier::UNDERLINED),
+            selected: with(Modifier::BOLD | Modifier::REVERSED),
+            menu_highlight: with(Modifier::REVERSED),
+            badge: with(Modifier::BOLD | Modifier::REVERSED),
+            status_bar: with(Modifier::REVERSED),
+            search_match: with(Modifier::BOLD | Modifier::UNDERLINED),
+        }
+    }
+
+    /// Colour of a cell by how hot its value is, as a percentage: from `good` at 5% through
+    /// `warn` at 30% to `bad` at 70% and above, blending the colours in between. Styles
+    /// without a colour to blend (monochrome) switch at those thresholds instead.
+    pub fn heat(&self, percent: f32) -> Style {
+        let stops = [(5.0, self.good), (30.0, self.warn), (70.0, self.bad)];
+        if percent < stops[0].0 {
+            return Style::default();
+        }
+        for pair in stops.windows(2) {
+            let ((low, from), (high, to)) = (pair[0], pair[1]);
+            if percent < high {
+                let color = match (from.fg.and_then(rgb), to.fg.and_then(rgb)) {
+                    (Some(a), Some(b)) => blend(a, b, (percent - low) / (high - low)),
+                    _ => return from,
+                };
+                // truecolor themes stay truecolor, others get the nearest of the 256 colours
+                let truecolor = matches!(from.fg, Some(Color::Rgb(..))) || matches!(to.fg, Some(Color::Rgb(..)));
+                return from.fg(if truecolor { Color::Rgb(color.0, color.1, color.2) } else { indexed(color) });
+            }
+        }
+        self.bad
+    }
+
+    pub fn builtin(name: &str) -> Option<Theme> {
+        match name {
+            "dark" => Some(Theme::dark()),
+            "light" => Some(Theme::light()),
+            "high-contrast" => Some(Theme::high_contrast()),
+            "monochrome" => Some(Theme::monochrome()),
+            _ => None,
+        }
+    }
+
+    /// `name` is a built-in theme, a theme in the themes directory or the path of a theme
+    /// file. Without one, NO_COLOR (https://no-color.org) picks monochrome over dark.
+    pub fn load(name: Option<&str>) -> Result<Theme, String> {
+        let name = match name {
+            Some(n) => n,
+            None if no_color() => return Ok(Theme::monochrome()),
+            None => return Ok(Theme::dark()),
+        };
+        if let Some(theme) = Theme::builtin(name) {
+            return Ok(theme);
+        }
+        let path = if name.contains('/') {
+            PathBuf::from(name)
+        } else {
+            themes_dir().join(format!("{}.theme", name))
+        };
+        Theme::from_file(&path)
+    }
+
+    /// Lines of `role = style`, e.g. `header = black on gray bold`. A `base = light` line
+    /// starts from a built-in theme, roles not set keep the style of the base (dark).
+    pub fn from_file(path: &Path) -> Result<Theme, String> {
+        let text = fs::read_to_string(path)
+            .map_err(|e| format!("Couldn't read theme {}: {}", path.display(), e))?;
+        let mut theme = Theme::dark();
+        for (n, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let error = |msg: String| format!("{}:{}: {}", path.display(), n + 1, msg);
+            let (key, value) = match line.split_once('=') {
+                Some((k, v)) => (k.trim(), v.trim()),
+                None => return Err(error(String::from("expected \"role = style\""))),
+            };
+            if key == "base" {
+                theme = Theme::builtin(value).ok_or_else(|| {
+                    error(format!(
+                        "unknown base theme '{}', expected one of {}",
+                        value,
+                        BUILTIN_THEMES.join(", ")
+                    ))
+                })?;
+                continue;
+            }
+            let style = parse_style(value).map_err(error)?;
+            match theme.role_mut(key) {
+                Some(role) => *role = style,
+                None => return Err(error(format!("unknown role '{}'", key))),
+            }
+        }
+        Ok(theme)
+    }
+
+    fn role_mut(&mut self, role: &str) -> Option<&mut Style> {
+        let style = match role {
+            "text" => &mut self.text,
+            "dimmed" => &mut self.dimmed,
+            "accent" => &mut self.accent,
+            "heading" => &mut self.heading,
+            "value" => &mut self.value,
+            "info" => &mut self.info,
+            "good" => &mut self.good,
+            "warn" => &mut self.warn,
+            "bad" => &mut self.bad,
+            "critical" => &mut self.critical,
+            "header" => &mut self.header,
+            "selected" => &mut self.selected,
+            "menu_highlight" => &mut self.menu_highlight,
+            "badge" => &mut self.badge,
+            "status_bar" => &mut self.status_bar,
+            "search_match" => &mut self.search_match,
+            _ => return None,
+        };
+        Some(style)
+    }
+}
+
+fn no_color() -> bool {
+    matches!(std::env::var_os("NO_COLOR"), Some(v) if !v.is_empty())
+}
+
+pub fn themes_dir() -> PathBuf {
+    config_dir().join("themes")
+}
+
+/// `[fg] [on bg] [modifiers]`: colours are names (red, light_red, dark_gray, default, ...),
+/// 256 colour indexes or #rrggbb, modifiers are bold, dim, italic, underlined and reversed.
+fn parse_style(spec: &str) -> Result<Style, String> {
+    let mut style = Style::default();
+    let mut words = spec.split_whitespace();
+    while let Some(word) = words.next() {
+        let word = word.to_lowercase();
+        let modifier = match word.as_str() {
+            "bold" => Some(Modifier::BOLD),
+            "dim" => Some(Modifier::DIM),
+            "italic" => Some(Modifier::ITALIC),
+            "underlined" => Some(Modifier::UNDERLINED),
+            "reversed" => Some(Modifier::REVERSED),
+            _ => None,
+        };
+        if let Some(m) = modifier {
+            style = style.add_modifier(m);
+        } else if word == "on" {
+            let bg = words.next().ok_or("expected a colour after 'on'")?;
+            style = style.bg(parse_color(bg)?);
+        } else {
+            style = style.fg(parse_color(&word)?);
+        }
+    }
+    Ok(style)
+}
+
+fn parse_color(word: &str) -> Result<Color, String> {
+    let color = match word.to_lowercase().replace('-', "_").as_str() {
+        "default" | "reset" => Color::Reset,
+        "black" => Color::Black,
+        "red" => Color::Red,
+        "green" => Color::Green,
+        "yellow" => Color::Yellow,
+        "blue" => Color::Blue,
+        "magenta" => Color::Magenta,
+        "cyan" => Color::Cyan,
+        "gray" | "grey" => Color::Gray,
+        "dark_gray" | "dark_grey" => Color::DarkGray,
+        "light_red" => Color::LightRed,
+        "light_green" => Color::LightGreen,
+        "light_yellow" => Color::LightYellow,
+        "light_blue" => Color::LightBlue,
+        "light_magenta" => Color::LightMagenta,
+        "light_cyan" => Color::LightCyan,
+        "white" => Color::White,
+        other => {
+            if let Some(hex) = other.strip_prefix('#') {
+                match u32::from_str_radix(hex, 16) {
+                    Ok(rgb) if hex.len() == 6 => {
+                        Color::Rgb((rgb >> 16) as u8, (rgb >> 8) as u8, rgb as u8)
+                    }
+                    _ => return Err(format!("invalid colour '{}', expected #rrggbb", word)),
+                }
+            } else {
+                match other.parse::<u8>() {
+                    Ok(i) => Color::Indexed(i),
+                    Err(_) => return Err(format!("unknown colour '{}'", word)),
+                }
+            }
+        }
+    };
+    Ok(color)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_colours() {
+        assert_eq!(parse_color("red"), Ok(Color::Red));
+        assert_eq!(parse_color("Light-Blue"), Ok(Color::LightBlue));
+        assert_eq!(parse_color("dark_grey"), Ok(Color::DarkGray));
+        assert_eq!(parse_color("default"), Ok(Color::Reset));
+        assert_eq!(parse_color("208"), Ok(Color::Indexed(208)));
+        assert_eq!(parse_color("#ff8000"), Ok(Color::Rgb(255, 128, 0)));
+        assert!(parse_color("#fff").is_err());
+        assert!(parse_color("#gg0000").is_err());
+        assert!(parse_color("256").is_err());
+        assert!(parse_color("purple").is_err());
+    }
+
+    #[test]
+    fn parses_styles() {
+        assert_eq!(parse_style(""), Ok(Style::default()));
+        assert_eq!(
+            parse_style("black on gray bold"),
+            Ok(Style::default().fg(Color::Black).bg(Color::Gray).add_modifier(Modifier::BOLD))
+        );
+        assert_eq!(
+            parse_style("UNDERLINED reversed on #000000"),
+            Ok(Style::default()
+                .add_modifier(Modifier::UNDERLINED | Modifier::REVERSED)
+                .bg(Color::Rgb(0, 0, 0)))
+        );
+        assert_eq!(parse_style("red on"), Err(String::from("expected a colour after 'on'")));
+        assert_eq!(parse_style("blinking"), Err(String::from("unknown colour 'blinking'")));
+    }
+
+    #[test]
+    fn heat_blends_between_thresholds() {
+        let theme = Theme::dark();
+        assert_eq!(theme.heat(2.0), Style::default());
+        assert_eq!(theme.heat(5.0), theme.good.fg(indexed((0, 205, 0))));
+        assert_eq!(theme.heat(30.0), theme.warn.fg(indexed((205, 205, 0))));
+        assert_eq!(theme.heat(70.0), theme.bad);
+        assert_eq!(theme.heat(250.0), theme.bad);
+        // halfway from green to yellow, and on to red
+        assert_eq!(theme.heat(17.5), theme.good.fg(Color::Indexed(76)));
+        assert_eq!(theme.heat(50.0), theme.warn.fg(Color::Indexed(166)));
+
+        let mut truecolor = Theme::dark();
+        truecolor.good = Style::default().fg(Color::Rgb(0, 200, 0));
+        assert_eq!(truecolor.heat(17.5), truecolor.good.fg(Color::Rgb(103, 203, 0)));
+
+        let mono = Theme::monochrome();
+        assert_eq!(mono.heat(10.0), mono.good);
+        assert_eq!(mono.heat(40.0), mono.warn);
+        assert_eq!(mono.heat(90.0), mono.bad);
+    }
+
+    #[test]
+    fn palette() {
+        assert_eq!(rgb(Color::Indexed(9)), rgb(Color::LightRed));
+        assert_eq!(rgb(Color::Indexed(130)), Some((175, 95, 0)));
+        assert_eq!(rgb(Color::Indexed(244)), Some((128, 128, 128)));
+        assert_eq!(rgb(Color::Reset), None);
+        assert_eq!(indexed((175, 95, 0)), Color::Indexed(130));
+        assert_eq!(indexed((255, 255, 255)), Color::Indexed(231));
+    }
+
+    #[test]
+    fn every_role_can_be_set() {
+        let mut theme = Theme::dark();
+        for role in [
+            "text", "dimmed", "accent", "heading", "value", "info", "good", "warn", "bad",
+            "critical", "header", "selected", "menu_highlight", "badge", "status_bar",
+            "search_match",
+        ] {
+            assert!(theme.role_mut(role).is_some(), "{}", role);
+        }
+        assert!(theme.role_mut("background").is_none());
+    }
+}
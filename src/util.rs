@@ -1,25 +1,29 @@
 #![allow(dead_code)]
 use crate::constants::DEFAULT_TICK;
-use crossterm::{event, event::Event as CEvent, event::KeyCode as Key, event::KeyEvent};
-use signal_hook::consts::signal::{SIGABRT, SIGINT, SIGTERM};
+use crossterm::{event, event::Event as CEvent, event::KeyCode as Key, event::KeyEvent, event::MouseEvent};
+use signal_hook::consts::signal::{SIGABRT, SIGHUP, SIGINT, SIGTERM};
 use signal_hook::iterator::Signals;
-use std::path::{Path};
+use std::path::{Path, PathBuf};
 use std::sync::mpsc;
 use std::thread;
 use std::time::Duration;
 
 pub enum Event<I> {
     Input(I),
+    Mouse(MouseEvent),
+    /// Text pasted into the terminal, arriving at once instead of key by key.
+    Paste(String),
     Resize(u16, u16),
     Tick,
     Save,
+    Reload,
     Terminate,
 }
 
 #[allow(dead_code)]
 pub struct Events {
     rx: mpsc::Receiver<Event<KeyEvent>>,
-    input_handle: thread::JoinHandle<()>,
+    input_handle: Option<thread::JoinHandle<()>>,
     tick_handle: thread::JoinHandle<()>,
     sig_handle: thread::JoinHandle<()>,
 }
@@ -28,6 +32,8 @@ pub struct Events {
 pub struct Config {
     pub exit_key: Key,
     pub tick_rate: Duration,
+    /// No terminal to read keys from, SIGHUP asks for a reload instead of hanging up.
+    pub headless: bool,
 }
 
 impl Default for Config {
@@ -35,6 +41,7 @@ impl Default for Config {
         Config {
             exit_key: Key::Char('q'),
             tick_rate: Duration::from_millis(DEFAULT_TICK),
+            headless: false,
         }
     }
 }
@@ -44,22 +51,35 @@ impl Events {
         Events::with_config(Config {
             tick_rate,
             exit_key: Key::Char('q'),
+            headless: false,
+        })
+    }
+
+    pub fn headless(tick_rate: Duration) -> Events {
+        Events::with_config(Config {
+            tick_rate,
+            exit_key: Key::Char('q'),
+            headless: true,
         })
     }
 
     pub fn with_config(config: Config) -> Events {
         let (tx, rx) = mpsc::channel();
-        let input_handle = {
+        let input_handle = if config.headless {
+            None
+        } else {
             let tx = tx.clone();
-            thread::spawn(move || loop {
+            Some(thread::spawn(move || loop {
                 match event::read().expect("Couldn't read event") {
                     CEvent::Key(key) => tx.send(Event::Input(key)).expect("Couldn't send event."),
+                    CEvent::Mouse(mouse) => tx.send(Event::Mouse(mouse)).expect("Couldn't send event."),
+                    CEvent::Paste(text) => tx.send(Event::Paste(text)).expect("Couldn't send event."),
                     CEvent::Resize(cols, rows) => tx
                         .send(Event::Resize(cols, rows))
                         .expect("Couldn't send event."),
                     _ => (), // ignore
                 }
-            })
+            }))
         };
         let tick_handle = {
             let tx = tx.clone();
@@ -78,13 +98,17 @@ impl Events {
         };
         let sig_handle = {
             let tx = tx;
+            let mut handled = vec![SIGINT, SIGTERM, SIGABRT];
+            if config.headless {
+                handled.push(SIGHUP);
+            }
             let mut signals =
-                Signals::new(&[SIGINT, SIGTERM, SIGABRT]).expect("Couldn't create signal handler");
+                Signals::new(&handled).expect("Couldn't create signal handler");
             thread::spawn(move || {
                 let tx = tx.clone();
-                for _sig in signals.forever() {
-                    tx.send(Event::Terminate)
-                        .expect("Couldn't send Terminate event.");
+                for sig in signals.forever() {
+                    let event = if sig == SIGHUP { Event::Reload } else { Event::Terminate };
+                    tx.send(event).expect("Couldn't send signal event.");
                 }
             })
         };
@@ -101,15 +125,22 @@ impl Events {
     }
 }
 
-async fn is_elmodeer_running(path: &Path) -> bool {
+/// Where user settings (themes, column layout) are kept.
+pub fn config_dir() -> PathBuf {
+    dirs::config_dir()
+        .unwrap_or_else(std::env::temp_dir)
+        .join("el-modeer")
+}
+
+pub async fn is_elmodeer_running(path: &Path) -> bool {
     name_of_process_for_pidfile(path)
         .await
-        .map_or(false, |name| name == "elmodeer")
+        .map_or(false, |name| name == env!("CARGO_PKG_NAME"))
 }
 
-async fn name_of_process_for_pidfile(path: &Path) -> Option<String> {
+pub async fn name_of_process_for_pidfile(path: &Path) -> Option<String> {
     let data = std::fs::read_to_string(path).ok()?;
-    let pid: i32 = data.parse().ok()?;
+    let pid: i32 = data.trim().parse().ok()?;
     let process = heim::process::get(pid).await.ok()?;
     process.name().await.ok()
 }